@@ -1,5 +1,4 @@
 use std::fmt;
-use sov_modules_api::impl_hash32_type;
 
 #[derive(
     Debug,
@@ -44,10 +43,4 @@ where
     }
 }
 
-impl_hash32_type!(MarketId, MarketIdBech32, "market");
-
-impl PartialEq<MarketId> for &MarketId {
-    fn eq(&self, other: &MarketId) -> bool {
-        self.0 == other.0
-    }
-}
+pub use spicenet_shared::aaob::{MarketId, MarketIdBech32};
@@ -0,0 +1,563 @@
+//! Paged representation of an orderbook.
+//!
+//! Every price level of a market is stored as its own state entry keyed by [`PriceLevelKey`] and
+//! the sorted prices of the active levels of each side under a [`BookSideKey`], the [`BookHeader`]
+//! only keeps the sequence numbers. Reads and writes therefore scale with the levels a call touches
+//! instead of the size of the book, and the prices of a side are only written when one of its
+//! levels is added or removed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Result};
+use sov_modules_api::{Spec, StateAccessor, StateMap};
+use spicenet_shared::Side;
+
+use crate::{
+    get_price_from_order_id, AAOBError, BookLevel, LegacyOrderbookState, MarketId, Order, OrderId,
+    TopOfBook, AAOB,
+};
+
+/// Per-market header of the paged book
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Clone, Debug, PartialEq, Eq)]
+pub struct BookHeader {
+    pub market_id: MarketId,
+    /// sequence number that will be assigned to the next order of this market
+    pub seq_num: u64,
+    /// sequence number that will be assigned to the next fill of this market
+    pub fill_seq_num: u64,
+}
+
+impl BookHeader {
+    pub fn new(market_id: MarketId) -> Self {
+        Self {
+            market_id,
+            seq_num: 0,
+            fill_seq_num: 0,
+        }
+    }
+
+    /// returns the current sequence number and advances it
+    pub fn next_seq_num(&mut self) -> u64 {
+        let seq_num = self.seq_num;
+        self.seq_num += 1;
+        seq_num
+    }
+
+    /// returns the current fill sequence number and advances it
+    pub fn next_fill_seq_num(&mut self) -> u64 {
+        let fill_seq_num = self.fill_seq_num;
+        self.fill_seq_num += 1;
+        fill_seq_num
+    }
+}
+
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Clone, Debug, PartialEq, Eq)]
+pub struct PriceLevelKey {
+    pub market_id: MarketId,
+    pub side: Side,
+    pub price: u64,
+}
+
+/// Key of the prices of the active levels of one side of a market, stored ascending
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Clone, Debug, PartialEq, Eq)]
+pub struct BookSideKey {
+    pub market_id: MarketId,
+    pub side: Side,
+}
+
+/// Orders resting at a single price
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PriceLevel {
+    /// orders in time priority
+    pub orders: Vec<(OrderId, Order)>,
+}
+
+impl PriceLevel {
+    /// total base quantity resting at this level
+    pub fn base_qty(&self) -> u64 {
+        self.orders.iter().map(|(_, order)| order.base_qty).sum()
+    }
+}
+
+/// Working copy of a market's book.
+///
+/// Levels are loaded from state on first access and cached, [`AAOB::save_book`] only writes back
+/// what was modified: the header, the prices of a side and the levels.
+pub struct Book<'a> {
+    pub header: BookHeader,
+    /// header as it is in state, `None` if it was never saved
+    saved_header: Option<BookHeader>,
+    levels: &'a StateMap<PriceLevelKey, PriceLevel>,
+    /// prices of the active bid levels, ascending
+    bid_prices: Vec<u64>,
+    /// prices of the active ask levels, ascending
+    ask_prices: Vec<u64>,
+    dirty_bid_prices: bool,
+    dirty_ask_prices: bool,
+    bids: BTreeMap<u64, PriceLevel>,
+    asks: BTreeMap<u64, PriceLevel>,
+    dirty_bids: BTreeSet<u64>,
+    dirty_asks: BTreeSet<u64>,
+    /// the book was converted from a [`LegacyOrderbookState`] that is still in state
+    migrated: bool,
+}
+
+impl<'a> Book<'a> {
+    /// Book whose header and prices were loaded from state
+    pub fn new(
+        header: BookHeader,
+        bid_prices: Vec<u64>,
+        ask_prices: Vec<u64>,
+        levels: &'a StateMap<PriceLevelKey, PriceLevel>,
+    ) -> Self {
+        Self {
+            saved_header: Some(header.clone()),
+            header,
+            levels,
+            bid_prices,
+            ask_prices,
+            dirty_bid_prices: false,
+            dirty_ask_prices: false,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            dirty_bids: BTreeSet::new(),
+            dirty_asks: BTreeSet::new(),
+            migrated: false,
+        }
+    }
+
+    /// Converts a [`LegacyOrderbookState`], everything is written on save
+    pub fn from_legacy(
+        orderbook: &LegacyOrderbookState,
+        levels: &'a StateMap<PriceLevelKey, PriceLevel>,
+    ) -> Self {
+        let (header, migrated_levels) = orderbook.migrate();
+
+        let mut book = Self::new(header, vec![], vec![], levels);
+        book.saved_header = None;
+        book.migrated = true;
+
+        for (key, level) in migrated_levels {
+            book.cache_mut(key.side).insert(key.price, level);
+            book.mark_dirty(key.side, key.price);
+
+            let prices = book.prices_mut(key.side);
+            if let Err(index) = prices.binary_search(&key.price) {
+                prices.insert(index, key.price);
+            }
+        }
+
+        book
+    }
+
+    /// prices of the active levels of `side`, ascending
+    pub fn prices(&self, side: Side) -> &Vec<u64> {
+        match side {
+            Side::Bid => &self.bid_prices,
+            Side::Ask => &self.ask_prices,
+        }
+    }
+
+    /// the prices of `side` are marked as modified, they are written on save
+    fn prices_mut(&mut self, side: Side) -> &mut Vec<u64> {
+        match side {
+            Side::Bid => {
+                self.dirty_bid_prices = true;
+                &mut self.bid_prices
+            }
+            Side::Ask => {
+                self.dirty_ask_prices = true;
+                &mut self.ask_prices
+            }
+        }
+    }
+
+    /// price of the best level of `side`
+    pub fn best_price(&self, side: Side) -> Option<u64> {
+        match side {
+            Side::Bid => self.bid_prices.last().copied(),
+            Side::Ask => self.ask_prices.first().copied(),
+        }
+    }
+
+    /// prices of `side` from the best to the worst level
+    pub fn prices_by_priority(&self, side: Side) -> Vec<u64> {
+        match side {
+            Side::Bid => self.bid_prices.iter().rev().copied().collect(),
+            Side::Ask => self.ask_prices.clone(),
+        }
+    }
+
+    fn cache(&self, side: Side) -> &BTreeMap<u64, PriceLevel> {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    fn cache_mut(&mut self, side: Side) -> &mut BTreeMap<u64, PriceLevel> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    fn mark_dirty(&mut self, side: Side, price: u64) {
+        match side {
+            Side::Bid => self.dirty_bids.insert(price),
+            Side::Ask => self.dirty_asks.insert(price),
+        };
+    }
+
+    fn level_key(&self, side: Side, price: u64) -> PriceLevelKey {
+        PriceLevelKey {
+            market_id: self.header.market_id,
+            side,
+            price,
+        }
+    }
+
+    fn level_mut(
+        &mut self,
+        side: Side,
+        price: u64,
+        state: &mut impl StateAccessor,
+    ) -> Result<&mut PriceLevel> {
+        if !self.cache(side).contains_key(&price) {
+            let level = self
+                .levels
+                .get(&self.level_key(side, price), state)?
+                .unwrap_or_default();
+            self.cache_mut(side).insert(price, level);
+        }
+
+        Ok(self
+            .cache_mut(side)
+            .get_mut(&price)
+            .expect("level was loaded above"))
+    }
+
+    fn has_level(&self, side: Side, price: u64) -> bool {
+        self.prices(side).binary_search(&price).is_ok()
+    }
+
+    /// id and contents of the order at the top of `side`
+    pub fn find_bbo(
+        &mut self,
+        side: Side,
+        state: &mut impl StateAccessor,
+    ) -> Result<Option<(OrderId, Order)>> {
+        let price = match self.best_price(side) {
+            Some(price) => price,
+            None => return Ok(None),
+        };
+
+        Ok(self.level_mut(side, price, state)?.orders.first().copied())
+    }
+
+    pub fn get_spread(&self) -> (Option<u64>, Option<u64>) {
+        (self.best_price(Side::Bid), self.best_price(Side::Ask))
+    }
+
+    /// the best `max_levels` levels of `side` with their aggregated size
+    pub fn depth(
+        &mut self,
+        side: Side,
+        max_levels: usize,
+        state: &mut impl StateAccessor,
+    ) -> Result<Vec<BookLevel>> {
+        let mut levels = vec![];
+
+        for price in self.prices_by_priority(side).into_iter().take(max_levels) {
+            levels.push(BookLevel {
+                price,
+                base_qty: self.level_mut(side, price, state)?.base_qty(),
+            });
+        }
+
+        Ok(levels)
+    }
+
+    /// best level of each side, the price of each side is the one [`Book::find_bbo`] matches at
+    pub fn top_of_book(&mut self, state: &mut impl StateAccessor) -> Result<TopOfBook> {
+        Ok(TopOfBook {
+            best_bid: self.depth(Side::Bid, 1, state)?.first().copied(),
+            best_ask: self.depth(Side::Ask, 1, state)?.first().copied(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bid_prices.is_empty() && self.ask_prices.is_empty()
+    }
+
+    pub fn get(
+        &mut self,
+        side: Side,
+        order_id: OrderId,
+        state: &mut impl StateAccessor,
+    ) -> Result<Option<Order>> {
+        let price = get_price_from_order_id(order_id);
+
+        if !self.has_level(side, price) {
+            return Ok(None);
+        }
+
+        Ok(self
+            .level_mut(side, price, state)?
+            .orders
+            .iter()
+            .find(|(id, _)| *id == order_id)
+            .map(|(_, order)| *order))
+    }
+
+    /// appends `order` to the back of its price level
+    pub fn insert(
+        &mut self,
+        side: Side,
+        order_id: OrderId,
+        order: Order,
+        state: &mut impl StateAccessor,
+    ) -> Result<()> {
+        let price = order.price;
+
+        self.level_mut(side, price, state)?
+            .orders
+            .push((order_id, order));
+        self.mark_dirty(side, price);
+
+        if let Err(index) = self.prices(side).binary_search(&price) {
+            self.prices_mut(side).insert(index, price);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(
+        &mut self,
+        side: Side,
+        order_id: OrderId,
+        state: &mut impl StateAccessor,
+    ) -> Result<Option<Order>> {
+        let price = get_price_from_order_id(order_id);
+
+        if !self.has_level(side, price) {
+            return Ok(None);
+        }
+
+        let level = self.level_mut(side, price, state)?;
+        let order = match level.orders.iter().position(|(id, _)| *id == order_id) {
+            Some(index) => level.orders.remove(index).1,
+            None => return Ok(None),
+        };
+        let level_is_empty = level.orders.is_empty();
+
+        self.mark_dirty(side, price);
+
+        if level_is_empty {
+            if let Ok(index) = self.prices(side).binary_search(&price) {
+                self.prices_mut(side).remove(index);
+            }
+        }
+
+        Ok(Some(order))
+    }
+
+    pub fn set_base_qty(
+        &mut self,
+        side: Side,
+        order_id: OrderId,
+        base_qty: u64,
+        state: &mut impl StateAccessor,
+    ) -> Result<()> {
+        let price = get_price_from_order_id(order_id);
+
+        if !self.has_level(side, price) {
+            bail!(AAOBError::OrderNotFound);
+        }
+
+        match self
+            .level_mut(side, price, state)?
+            .orders
+            .iter_mut()
+            .find(|(id, _)| *id == order_id)
+        {
+            Some((_, order)) => order.set_base_qty(base_qty),
+            None => bail!(AAOBError::OrderNotFound),
+        }
+
+        self.mark_dirty(side, price);
+
+        Ok(())
+    }
+
+    /// level of `side` at `price`, empty when no order rests there
+    pub fn level(
+        &mut self,
+        side: Side,
+        price: u64,
+        state: &mut impl StateAccessor,
+    ) -> Result<PriceLevel> {
+        if !self.has_level(side, price) {
+            return Ok(PriceLevel::default());
+        }
+
+        Ok(self.level_mut(side, price, state)?.clone())
+    }
+
+    /// levels of `side` from the best to the worst price, this loads every level of the side
+    pub fn levels(
+        &mut self,
+        side: Side,
+        state: &mut impl StateAccessor,
+    ) -> Result<Vec<(u64, PriceLevel)>> {
+        let mut levels = vec![];
+
+        for price in self.prices_by_priority(side) {
+            let level = self.level_mut(side, price, state)?.clone();
+            levels.push((price, level));
+        }
+
+        Ok(levels)
+    }
+
+    /// orders of `side` in priority order, this loads every level of the side
+    pub fn orders(
+        &mut self,
+        side: Side,
+        state: &mut impl StateAccessor,
+    ) -> Result<Vec<(OrderId, Order)>> {
+        Ok(self
+            .levels(side, state)?
+            .into_iter()
+            .flat_map(|(_, level)| level.orders)
+            .collect())
+    }
+}
+
+impl<S: Spec> AAOB<S> {
+    fn side_key(market_id: MarketId, side: Side) -> BookSideKey {
+        BookSideKey { market_id, side }
+    }
+
+    /// Loads the book of `market_id`.
+    ///
+    /// Markets still stored as a [`LegacyOrderbookState`] are converted in memory, the conversion
+    /// is persisted the first time the book is saved.
+    pub fn load_book(
+        &self,
+        market_id: MarketId,
+        state: &mut impl StateAccessor,
+    ) -> Result<Option<Book<'_>>> {
+        if let Some(header) = self.books.get(&market_id, state)? {
+            let bid_prices = self
+                .book_prices
+                .get(&Self::side_key(market_id, Side::Bid), state)?
+                .unwrap_or_default();
+            let ask_prices = self
+                .book_prices
+                .get(&Self::side_key(market_id, Side::Ask), state)?
+                .unwrap_or_default();
+
+            return Ok(Some(Book::new(
+                header,
+                bid_prices,
+                ask_prices,
+                &self.price_levels,
+            )));
+        }
+
+        Ok(self
+            .orderbooks
+            .get(&market_id, state)?
+            .map(|orderbook| Book::from_legacy(&orderbook, &self.price_levels)))
+    }
+
+    /// Writes the header, the prices and the levels of `book` that were modified back to state
+    pub(crate) fn save_book(&self, book: Book<'_>, state: &mut impl StateAccessor) -> Result<()> {
+        let market_id = book.header.market_id;
+
+        for (side, dirty) in [(Side::Bid, &book.dirty_bids), (Side::Ask, &book.dirty_asks)] {
+            for price in dirty {
+                let key = book.level_key(side, *price);
+
+                match book.cache(side).get(price) {
+                    Some(level) if !level.orders.is_empty() => {
+                        self.price_levels.set(&key, level, state)?;
+                    }
+                    _ => self.price_levels.remove(&key, state)?,
+                }
+            }
+        }
+
+        for (side, dirty) in [
+            (Side::Bid, book.dirty_bid_prices),
+            (Side::Ask, book.dirty_ask_prices),
+        ] {
+            if !dirty {
+                continue;
+            }
+
+            let key = Self::side_key(market_id, side);
+            match book.prices(side) {
+                prices if prices.is_empty() => self.book_prices.remove(&key, state)?,
+                prices => self.book_prices.set(&key, prices, state)?,
+            }
+        }
+
+        if book.migrated {
+            self.orderbooks.remove(&market_id, state)?;
+        }
+
+        if book.saved_header.as_ref() != Some(&book.header) {
+            self.books.set(&market_id, &book.header, state)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the header, the prices and every level of `book` from state
+    pub(crate) fn delete_book(&self, book: Book<'_>, state: &mut impl StateAccessor) -> Result<()> {
+        let market_id = book.header.market_id;
+
+        for side in [Side::Bid, Side::Ask] {
+            for price in book.prices(side) {
+                self.price_levels
+                    .remove(&book.level_key(side, *price), state)?;
+            }
+            self.book_prices
+                .remove(&Self::side_key(market_id, side), state)?;
+        }
+
+        if book.migrated {
+            self.orderbooks.remove(&market_id, state)?;
+        }
+
+        self.books.remove(&market_id, state)?;
+
+        Ok(())
+    }
+}
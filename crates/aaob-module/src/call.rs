@@ -1,11 +1,11 @@
 use crate::{
-    address::MarketId, fp32_div, fp32_mul, get_market_id, orderbook::OrderId,
-    orderbook::OrderbookId, AAOBError, Event, Market, Order, AAOB,
+    address::MarketCallerAuthority, address::MarketId, fp32_div, fp32_mul, get_market_id,
+    get_order_id, get_owner_key, orderbook::OrderId, AAOBError, Book, BookHeader, Event,
+    Market, Order, OutReason, AAOB,
 };
 use anyhow::{bail, Result};
 use borsh::BorshDeserialize;
 use borsh::BorshSerialize;
-use sokoban::NodeAllocatorMap;
 use sov_modules_api::{Context, EventEmitter, SafeString, Spec, TxState};
 use spicenet_shared::Side;
 use std::cmp::PartialEq;
@@ -44,26 +44,67 @@ pub enum CallMessage {
         max_base_qty: u64,
         max_quote_qty: u64,
         limit_price: u64,
-        post_only: bool,
-        post_allowed: bool,
+        order_type: OrderType,
         self_trade_behavior: SelfTradeHandler,
         match_limit: u64,
         trg_id: u64,
+        client_order_id: u64,
     },
+    /// Cancels a single order, only its owner or the market's caller authority may do so
     CancelOrder {
         market_id: MarketId,
         order_id: OrderId,
         side: Side,
     },
+    /// Cancels then places orders against a single load of the book, all of them succeed or the
+    /// transaction reverts
+    BatchOrders {
+        market_id: MarketId,
+        cancels: Vec<OrderCancel>,
+        places: Vec<NewOrder>,
+    },
+    /// Replaces a resting order of the sender. The order keeps its queue priority when only its
+    /// size shrinks, otherwise it is cancelled and `order` is placed as a new order
+    ReplaceOrder {
+        market_id: MarketId,
+        order_id: OrderId,
+        order: NewOrder,
+    },
+    /// Cancels every order of the sender on both sides of the book
+    CancelAllOrders {
+        market_id: MarketId,
+    },
+    /// Cancels the sender's orders placed with any of `client_order_ids`
+    CancelOrdersByClientId {
+        market_id: MarketId,
+        client_order_ids: Vec<u64>,
+    },
+    /// Removes up to `limit` expired orders from the book, callable by anyone
+    PruneExpiredOrders {
+        market_id: MarketId,
+        limit: u64,
+    },
     CreateMarket {
         market_name: SafeString,
         fee_budget: u64,
         min_base_size: u64,
         tick_size: u64,
     },
+    /// Drains the book, emitting an [`Event::Out`] per resting order, and marks the market as closed
     CloseMarket {
         market_name: SafeString,
     },
+    /// Updates the market parameters that are set, `tick_size` must divide the price of every resting order
+    UpdateMarket {
+        market_id: MarketId,
+        fee_budget: Option<u64>,
+        min_base_size: Option<u64>,
+        tick_size: Option<u64>,
+    },
+    SetMarketStatus {
+        market_id: MarketId,
+        status: MarketStatus,
+    },
 }
 
 // impl<S: Spec> EventEmitter for AAOB<S> {
@@ -75,145 +116,361 @@ pub enum CallMessage {
 //     }
 // }
 
+/// Maximum number of cancels plus places of a single [`CallMessage::BatchOrders`]
+pub const MAX_BATCH_SIZE: usize = 64;
+
 impl<S: Spec> AAOB<S> {
-    #[allow(clippy::too_many_arguments)]
     pub(crate) fn create_order(
         &self,
         market_id: MarketId,
-        side: Side,
-        max_base_qty: u64,
-        max_quote_qty: u64,
-        limit_price: u64,
-        post_only: bool,
-        post_allowed: bool,
-        self_trade_behaviour: SelfTradeHandler,
-        trg_id: u64,
-        _context: &Context<S>,
+        order: NewOrder,
+        context: &Context<S>,
         state: &mut impl TxState<S>,
-        mut match_limit: u64,
-        // TODO: new order params
     ) -> Result<()> {
+        let market = self.load_public_market(market_id, state)?;
+
         // Get the orderbook state
-        let mut orderbook_state = self.orderbooks.get(&market_id, state).unwrap().unwrap();
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
 
-        let mut base_qty_remaining = max_base_qty;
-        let mut quote_qty_remaining = max_quote_qty;
-        // create new bid
-        let mut crossed = true;
-        let market: Market<S> = self
-            .markets
-            .get(&orderbook_state.market_id, state)
-            .unwrap()
-            .unwrap();
+        let owner = self.get_order_owner(context, state)?;
+        let now = self.time_module.get_time(state)?.unix_timestamp;
+
+        self.place_order(&market, &mut book, order, owner, now, state)?;
+        self.save_book(book, state)?;
+
+        Ok(())
+    }
+
+    /// Cancels then places orders against a single load of the book, which is only saved once all
+    /// of them succeeded
+    pub(crate) fn batch_orders(
+        &self,
+        market_id: MarketId,
+        cancels: Vec<OrderCancel>,
+        places: Vec<NewOrder>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        if cancels.len() + places.len() > MAX_BATCH_SIZE {
+            bail!(AAOBError::BatchTooLarge);
+        }
 
-        if limit_price % market.tick_size != 0 {
+        let market = self.load_public_market(market_id, state)?;
+
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        let owner = self.get_order_owner(context, state)?;
+        let now = self.time_module.get_time(state)?.unix_timestamp;
+
+        for cancel in cancels {
+            self.remove_order(
+                &market,
+                &mut book,
+                cancel.order_id,
+                cancel.side,
+                owner,
+                context,
+                state,
+            )?;
+        }
+
+        for order in places {
+            self.place_order(&market, &mut book, order, owner, now, state)?;
+        }
+
+        self.save_book(book, state)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn replace_order(
+        &self,
+        market_id: MarketId,
+        order_id: OrderId,
+        order: NewOrder,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let market = self.load_public_market(market_id, state)?;
+
+        if !market.can_place_orders() {
+            bail!(AAOBError::InvalidMarketStatus);
+        }
+
+        // checked before the base quantity is derived from the price
+        if order.limit_price == 0 || order.limit_price % market.tick_size != 0 {
+            bail!("replace_order: invalid limit price {}", order.limit_price);
+        }
+
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        let owner = self.get_order_owner(context, state)?;
+        let now = self.time_module.get_time(state)?.unix_timestamp;
+
+        let resting = book
+            .get(order.side, order_id, state)?
+            .ok_or(AAOBError::OrderNotFound)?;
+
+        // only the owner may replace, the caller authority can still cancel
+        if resting.owner != owner {
+            bail!(AAOBError::WrongCallerAuthority);
+        }
+
+        let base_qty = order
+            .max_base_qty
+            .min(fp32_div(order.max_quote_qty, order.limit_price));
+        let (_, post_allowed) = order.order_type.post_flags();
+
+        let only_shrinks = post_allowed
+            && order.limit_price == resting.price
+            && order.trg_id == resting.trg_id
+            && order.client_order_id == resting.client_order_id
+            && order.order_type.expiry_timestamp() == resting.expiry_timestamp
+            && base_qty <= resting.base_qty
+            && base_qty > 0
+            && base_qty >= market.min_base_size;
+
+        if only_shrinks {
+            book.set_base_qty(order.side, order_id, base_qty, state)?;
+
+            self.emit_event(
+                state,
+                Event::OrderReduced {
+                    order_id,
+                    market_id,
+                    side: order.side,
+                    base_qty,
+                },
+            );
+        } else {
+            self.remove_order(&market, &mut book, order_id, order.side, owner, context, state)?;
+            self.place_order(&market, &mut book, order, owner, now, state)?;
+        }
+
+        self.save_book(book, state)?;
+
+        Ok(())
+    }
+
+    /// Matches `order` against `book` and rests what is left, the caller saves the book
+    fn place_order(
+        &self,
+        market: &Market<S>,
+        book: &mut Book<'_>,
+        order: NewOrder,
+        owner: [u8; 32],
+        now: u64,
+        state: &mut impl TxState<S>,
+    ) -> Result<OrderSummary> {
+        let NewOrder {
+            side,
+            max_base_qty,
+            max_quote_qty,
+            limit_price,
+            order_type,
+            self_trade_behavior: self_trade_behaviour,
+            mut match_limit,
+            trg_id,
+            client_order_id,
+        } = order;
+        let market_id = book.header.market_id;
+
+        if !market.can_place_orders() {
+            bail!(AAOBError::InvalidMarketStatus);
+        }
+
+        if limit_price == 0 || limit_price % market.tick_size != 0 {
             bail!("create_order: invalid limit price {limit_price}");
         }
 
         let min_base_order_size = market.min_base_size;
 
-        let mut order_id: OrderId = 0;
+        let (post_only, post_allowed) = order_type.post_flags();
+        let expiry_timestamp = order_type.expiry_timestamp();
+
+        if expiry_timestamp != 0 && expiry_timestamp <= now {
+            bail!(AAOBError::InvalidExpiry);
+        }
+
+        // every order consumes a sequence number, whether it ends up resting or not
+        let seq_num = book.header.next_seq_num();
+        let order_id = get_order_id(side, limit_price, seq_num);
+
+        let mut base_qty_remaining = max_base_qty;
+        let mut quote_qty_remaining = max_quote_qty;
+        let mut crossed = true;
+        let mut fills = Vec::new();
+        let mut outs = Vec::new();
 
-        // don't need the `new_leaf_order_id` anymore as it is automatically done by sokoban
-        // let new_leaf_order_id = 10u128; // dummy for now, earlier event queue was deriving it which we aren't using anymore
         loop {
             if match_limit == 0 {
                 break;
             }
             // Find the best bid/offer on the opposite side
-            let bbo_h = match orderbook_state.find_bbo(side.opposite()) {
+            let (best_offer_id, best_offer) = match book.find_bbo(side.opposite(), state)? {
                 None => {
                     crossed = false;
                     break;
                 }
-                Some(h) => h,
+                Some(o) => o,
             };
 
-            let opposite_book_tree = orderbook_state.get_tree(side.opposite());
+            // expired orders are pruned lazily when they reach the top of the book
+            if best_offer.is_expired(now) {
+                book.remove(side.opposite(), best_offer_id, state)?;
 
-            let bbo_h_node = &opposite_book_tree // Getting the opposite tree (since bids and asks are separate trees)
-                .get_node(bbo_h) // Getting the best price node via its key found above in bbo_h var
-                .unwrap()
-                .key; // critbit node key
+                self.emit_event(
+                    state,
+                    Event::Out {
+                        order_id: best_offer_id,
+                        market_id,
+                        side: side.opposite(),
+                        trg_id: best_offer.trg_id,
+                        base_qty: best_offer.base_qty,
+                        reason: OutReason::Expired,
+                    },
+                );
+                outs.push(OutSummary {
+                    order_id: best_offer_id,
+                    side: side.opposite(),
+                    trg_id: best_offer.trg_id,
+                    base_qty: best_offer.base_qty,
+                    reason: OutReason::Expired,
+                });
 
-            let mut trade = opposite_book_tree.tree.get(bbo_h_node).unwrap().to_owned();
+                match_limit -= 1;
+                continue;
+            }
 
             crossed = match side {
-                Side::Bid => limit_price >= trade.price,
-                Side::Ask => limit_price <= trade.price,
+                Side::Bid => limit_price >= best_offer.price,
+                Side::Ask => limit_price <= best_offer.price,
             };
 
             if post_only || !crossed {
                 break;
             }
 
-            let offer_size = trade.base_qty;
-            let base_trade_qty = offer_size
-                .min(base_qty_remaining) // 5
-                .min(fp32_div(quote_qty_remaining, trade.price));
+            let base_trade_qty = best_offer
+                .base_qty
+                .min(base_qty_remaining)
+                .min(fp32_div(quote_qty_remaining, best_offer.price));
 
             if base_trade_qty == 0 {
                 // not able to fill up
                 break;
             }
 
-            let quote_maker_qty = fp32_mul(base_trade_qty, trade.price);
+            let quote_maker_qty = fp32_mul(base_trade_qty, best_offer.price);
 
             if quote_maker_qty == 0 {
                 break;
             }
 
-            if self_trade_behaviour != SelfTradeHandler::DecrementTake {
-                let will_order_self_trade =
-                    trg_id == (opposite_book_tree.tree.get(bbo_h_node).unwrap().trg_id);
-
-                if will_order_self_trade {
-                    let best_offer_id = opposite_book_tree.tree.get(bbo_h_node).unwrap().order_id;
+            if self_trade_behaviour != SelfTradeHandler::DecrementTake
+                && trg_id == best_offer.trg_id
+            {
+                if self_trade_behaviour == SelfTradeHandler::AbortTx {
+                    bail!(AAOBError::WouldSelfTrade);
+                }
 
-                    if self_trade_behaviour == SelfTradeHandler::AbortTx {
-                        bail!(AAOBError::WouldSelfTrade);
-                    }
+                assert_eq!(self_trade_behaviour, SelfTradeHandler::CancelProvide);
 
-                    assert_eq!(self_trade_behaviour, SelfTradeHandler::CancelProvide);
+                book.remove(side.opposite(), best_offer_id, state)?;
 
-                    orderbook_state
-                        .get_tree(side.opposite())
-                        .remove_by_key(best_offer_id.into());
+                self.emit_event(
+                    state,
+                    Event::Out {
+                        order_id: best_offer_id,
+                        market_id,
+                        side: side.opposite(),
+                        trg_id: best_offer.trg_id,
+                        base_qty: best_offer.base_qty,
+                        reason: OutReason::SelfTrade,
+                    },
+                );
+                outs.push(OutSummary {
+                    order_id: best_offer_id,
+                    side: side.opposite(),
+                    trg_id: best_offer.trg_id,
+                    base_qty: best_offer.base_qty,
+                    reason: OutReason::SelfTrade,
+                });
 
-                    match_limit -= 1;
-                    continue;
-                }
+                match_limit -= 1;
+                continue;
             }
 
-            trade.set_base_qty(trade.base_qty - base_trade_qty);
             base_qty_remaining -= base_trade_qty;
             quote_qty_remaining -= quote_maker_qty;
 
-            if trade.base_qty < min_base_order_size {
-                let best_offer_id = opposite_book_tree.tree.get(bbo_h_node).unwrap().order_id;
-
-                let curr_side = side; // current side
+            let maker_base_qty_remaining = best_offer.base_qty - base_trade_qty;
+            let maker_order_completed =
+                maker_base_qty_remaining == 0 || maker_base_qty_remaining < min_base_order_size;
+            let fill_seq_num = book.header.next_fill_seq_num();
 
-                orderbook_state
-                    .get_tree(curr_side)
-                    .remove_by_key(best_offer_id.into())
-                    .unwrap();
+            if maker_order_completed {
+                book.remove(side.opposite(), best_offer_id, state)?;
             } else {
-                orderbook_state.get_tree(side.opposite()).tree.insert(
-                    *bbo_h_node,
-                    Order {
-                        order_id: bbo_h as u64,
-                        price: limit_price,
-                        base_qty: base_qty_remaining,
-                        trg_id,
+                book.set_base_qty(
+                    side.opposite(),
+                    best_offer_id,
+                    maker_base_qty_remaining,
+                    state,
+                )?;
+            }
+
+            self.emit_event(
+                state,
+                Event::Fill {
+                    market_id,
+                    fill_seq_num,
+                    maker_order_id: best_offer_id,
+                    maker_trg_id: best_offer.trg_id,
+                    taker_trg_id: trg_id,
+                    taker_side: side,
+                    price: best_offer.price,
+                    base_qty: base_trade_qty,
+                    quote_qty: quote_maker_qty,
+                    maker_order_completed,
+                },
+            );
+            fills.push(FillSummary {
+                maker_order_id: best_offer_id,
+                maker_trg_id: best_offer.trg_id,
+                price: best_offer.price,
+                base_qty: base_trade_qty,
+                quote_qty: quote_maker_qty,
+                maker_order_completed,
+            });
+
+            if maker_order_completed && maker_base_qty_remaining > 0 {
+                self.emit_event(
+                    state,
+                    Event::Out {
+                        order_id: best_offer_id,
+                        market_id,
+                        side: side.opposite(),
+                        trg_id: best_offer.trg_id,
+                        base_qty: maker_base_qty_remaining,
+                        reason: OutReason::BelowMinBaseSize,
                     },
                 );
-                // TODO: need good review from @karthik about price and base_qty
-                // create a leaf `bbo_h_node` on `bbo_h` node
+                outs.push(OutSummary {
+                    order_id: best_offer_id,
+                    side: side.opposite(),
+                    trg_id: best_offer.trg_id,
+                    base_qty: maker_base_qty_remaining,
+                    reason: OutReason::BelowMinBaseSize,
+                });
             }
 
-            order_id = OrderId::from(bbo_h); // TODO: need good review from @karthik about ID
-
             match_limit -= 1;
         }
 
@@ -222,93 +479,38 @@ impl<S: Spec> AAOB<S> {
             base_qty_remaining,
         );
 
-        if crossed || !post_allowed || base_qty_to_post < min_base_order_size {
-            let out_reason = if base_qty_to_post < min_base_order_size {
-                CompletedReason::Filled
-            } else if crossed {
-                if match_limit == 0 {
-                    CompletedReason::MatchLimitExhausted
-                } else {
-                    CompletedReason::PostOnly
-                }
-            } else {
-                CompletedReason::PostNotAllowed
-            };
-
-            if let CompletedReason::PostOnly = out_reason {
-                self.emit_event(
-                    state,
-                    Event::OrderCreated {
-                        order_id,
-                        market_id,
-                        side,
-                        total_base_qty: max_base_qty - base_qty_remaining,
-                        total_quote_qty: max_quote_qty - quote_qty_remaining,
-                        total_base_qty_posted: 0,
-                    },
-                );
-
-                // let order_summary = OrderSummary {
-                //     order_id: None,
-                //     total_base_qty: max_base_qty - base_qty_remaining,
-                //     total_quote_qty: max_quote_qty - quote_qty_remaining,
-                //     total_base_qty_posted: 0,
-                // };
-
-                return Ok(());
-
-                // return Ok(OrderSummary {
-                //     order_id: None,
-                //     total_base_qty: max_base_qty - base_qty_remaining,
-                //     total_quote_qty: max_quote_qty - quote_qty_remaining,
-                //     total_base_qty_posted: 0,
-                // });
-            }
+        if order_type == OrderType::FillOrKill && base_qty_to_post > 0 {
+            bail!(AAOBError::FillOrKillNotFilled);
+        }
 
-            self.emit_event(
-                state,
-                Event::OrderCreated {
-                    order_id,
-                    market_id,
-                    side,
-                    total_base_qty: max_base_qty - base_qty_remaining,
-                    total_quote_qty: max_quote_qty - quote_qty_remaining,
-                    total_base_qty_posted: 0,
+        // a crossed book at this point means the order is post only or ran out of matches
+        let total_base_qty_posted = if crossed
+            || !post_allowed
+            || base_qty_to_post == 0
+            || base_qty_to_post < min_base_order_size
+        {
+            0
+        } else {
+            book.insert(
+                side,
+                order_id,
+                Order {
+                    order_id: seq_num,
+                    price: limit_price,
+                    base_qty: base_qty_to_post,
+                    trg_id,
+                    client_order_id,
+                    owner,
+                    expiry_timestamp,
                 },
-            );
-
-            // let order_summary = OrderSummary {
-            //     order_id: Some(order_id as u64),
-            //     total_base_qty: max_base_qty - base_qty_remaining,
-            //     total_quote_qty: max_quote_qty - quote_qty_remaining,
-            //     total_base_qty_posted: 0,
-            // };
-
-            return Ok(());
+                state,
+            )?;
 
-            // return Ok(OrderSummary {
-            //     order_id: Some(order_id),
-            //     total_base_qty: max_base_qty - base_qty_remaining,
-            //     total_quote_qty: max_quote_qty - quote_qty_remaining,
-            //     total_base_qty_posted: 0,
-            // });
-        }
-
-        // @TODO - also figure out the KV Id of the node to store this Order, rn kept 0
-        // Inserting the order as a leaf finally
-        orderbook_state.get_tree(side).tree.insert(
-            0,
-            Order {
-                order_id: order_id.try_into().unwrap(),
-                price: limit_price,
-                base_qty: base_qty_to_post,
-                trg_id,
-            },
-        );
+            base_qty_remaining -= base_qty_to_post;
+            quote_qty_remaining -= fp32_mul(base_qty_to_post, limit_price);
+            base_qty_to_post
+        };
 
-        base_qty_remaining -= base_qty_to_post;
-        quote_qty_remaining -= fp32_mul(base_qty_to_post, limit_price);
-        // Emit an event
         self.emit_event(
             state,
             Event::OrderCreated {
@@ -317,11 +519,18 @@ impl<S: Spec> AAOB<S> {
                 side,
                 total_base_qty: max_base_qty - base_qty_remaining,
                 total_quote_qty: max_quote_qty - quote_qty_remaining,
-                total_base_qty_posted: base_qty_to_post,
+                total_base_qty_posted,
             },
         );
 
-        Ok(())
+        Ok(OrderSummary {
+            order_id,
+            total_base_qty: max_base_qty - base_qty_remaining,
+            total_quote_qty: max_quote_qty - quote_qty_remaining,
+            posted_base_qty: total_base_qty_posted,
+            fills,
+            outs,
+        })
     }
 
     pub(crate) fn cancel_order(
@@ -329,38 +538,367 @@ impl<S: Spec> AAOB<S> {
         market_id: MarketId,
         order_id: OrderId,
         side: Side,
-        _context: &Context<S>,
+        context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
+        let market = self.load_public_market(market_id, state)?;
+
         // Get the orderbook state
-        let mut orderbook_state = self.orderbooks.get(&market_id, state).unwrap().unwrap();
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
 
-        let tree = orderbook_state.get_tree(side).tree;
+        let owner = self.get_order_owner(context, state)?;
 
-        // Try to find the order in the tree using its id (key)
-        let order_ref = tree.get_node(order_id as u32).key;
+        self.remove_order(&market, &mut book, order_id, side, owner, context, state)?;
+        self.save_book(book, state)?;
+
+        Ok(())
+    }
 
-        if order_ref == 0 {
-            bail!(AAOBError::OrderNotFound);
+    /// Cancels a resting order of `owner`, or any order when the sender is the market's caller
+    /// authority. The caller saves the book
+    #[allow(clippy::too_many_arguments)]
+    fn remove_order(
+        &self,
+        market: &Market<S>,
+        book: &mut Book<'_>,
+        order_id: OrderId,
+        side: Side,
+        owner: [u8; 32],
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<Order> {
+        if !market.can_cancel_orders() {
+            bail!(AAOBError::InvalidMarketStatus);
         }
 
-        orderbook_state
-            .get_tree(side)
-            .remove_by_key(order_id)
-            .unwrap();
+        let order = book
+            .get(side, order_id, state)?
+            .ok_or(AAOBError::OrderNotFound)?;
+
+        if context.sender().as_ref() != market.caller_authority.as_ref() && order.owner != owner {
+            bail!(AAOBError::WrongCallerAuthority);
+        }
+
+        book.remove(side, order_id, state)?;
 
         self.emit_event(
             state,
             Event::OrderCancelled {
                 order_id,
-                market_id,
+                market_id: book.header.market_id,
                 side,
             },
         );
 
+        Ok(order)
+    }
+
+    pub(crate) fn cancel_all_orders(
+        &self,
+        market_id: MarketId,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let owner = self.get_order_owner(context, state)?;
+
+        self.cancel_orders_where(market_id, |order| order.owner == owner, state)
+    }
+
+    pub(crate) fn cancel_orders_by_client_id(
+        &self,
+        market_id: MarketId,
+        client_order_ids: Vec<u64>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let owner = self.get_order_owner(context, state)?;
+
+        self.cancel_orders_where(
+            market_id,
+            |order| order.owner == owner && client_order_ids.contains(&order.client_order_id),
+            state,
+        )
+    }
+
+    pub(crate) fn prune_expired_orders(
+        &self,
+        market_id: MarketId,
+        limit: u64,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        self.load_public_market(market_id, state)?;
+
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        let now = self.time_module.get_time(state)?.unix_timestamp;
+        let mut remaining = limit;
+
+        for side in [Side::Bid, Side::Ask] {
+            let expired: Vec<(OrderId, Order)> = book
+                .orders(side, state)?
+                .into_iter()
+                .filter(|(_, order)| order.is_expired(now))
+                .take(remaining as usize)
+                .collect();
+
+            remaining -= expired.len() as u64;
+
+            for (order_id, order) in expired {
+                book.remove(side, order_id, state)?;
+
+                self.emit_event(
+                    state,
+                    Event::Out {
+                        order_id,
+                        market_id,
+                        side,
+                        trg_id: order.trg_id,
+                        base_qty: order.base_qty,
+                        reason: OutReason::Expired,
+                    },
+                );
+            }
+        }
+
+        self.save_book(book, state)?;
+
+        Ok(())
+    }
+
+    /// Removes every order of the book matching `predicate` and persists the book
+    fn cancel_orders_where(
+        &self,
+        market_id: MarketId,
+        predicate: impl Fn(&Order) -> bool,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let market = self.load_public_market(market_id, state)?;
+
+        if !market.can_cancel_orders() {
+            bail!(AAOBError::InvalidMarketStatus);
+        }
+
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        for side in [Side::Bid, Side::Ask] {
+            let order_ids: Vec<OrderId> = book
+                .orders(side, state)?
+                .into_iter()
+                .filter(|(_, order)| predicate(order))
+                .map(|(order_id, _)| order_id)
+                .collect();
+
+            for order_id in order_ids {
+                book.remove(side, order_id, state)?;
+
+                self.emit_event(
+                    state,
+                    Event::OrderCancelled {
+                        order_id,
+                        market_id,
+                        side,
+                    },
+                );
+            }
+        }
+
+        self.save_book(book, state)?;
+
         Ok(())
     }
 
+    /// Places `order` on a delegated market on behalf of its caller authority, which is trusted
+    /// to have authorized the order. Returns the fills and removed orders so the caller can
+    /// settle them.
+    pub fn place_delegated_order(
+        &self,
+        market_id: MarketId,
+        order: NewOrder,
+        state: &mut impl TxState<S>,
+    ) -> Result<OrderSummary> {
+        let market = self.load_delegated_market(market_id, state)?;
+
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        let owner = get_owner_key::<S>(market.caller_authority.as_ref());
+        let now = self.time_module.get_time(state)?.unix_timestamp;
+
+        let summary = self.place_order(&market, &mut book, order, owner, now, state)?;
+        self.save_book(book, state)?;
+
+        Ok(summary)
+    }
+
+    /// Cancels a resting order of a delegated market on behalf of its caller authority
+    pub fn cancel_delegated_order(
+        &self,
+        market_id: MarketId,
+        order_id: OrderId,
+        side: Side,
+        state: &mut impl TxState<S>,
+    ) -> Result<Order> {
+        let market = self.load_delegated_market(market_id, state)?;
+
+        if !market.can_cancel_orders() {
+            bail!(AAOBError::InvalidMarketStatus);
+        }
+
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        let order = book
+            .get(side, order_id, state)?
+            .ok_or(AAOBError::OrderNotFound)?;
+
+        book.remove(side, order_id, state)?;
+        self.save_book(book, state)?;
+
+        self.emit_event(
+            state,
+            Event::OrderCancelled {
+                order_id,
+                market_id,
+                side,
+            },
+        );
+
+        Ok(order)
+    }
+
+    /// Cancels up to `limit` resting orders of a delegated market on behalf of its caller
+    /// authority, bids first. Returns the cancelled orders, the book is empty once less than
+    /// `limit` orders are returned.
+    pub fn cancel_delegated_orders(
+        &self,
+        market_id: MarketId,
+        limit: usize,
+        state: &mut impl TxState<S>,
+    ) -> Result<Vec<(OrderId, Side, Order)>> {
+        let market = self.load_delegated_market(market_id, state)?;
+
+        if !market.can_cancel_orders() {
+            bail!(AAOBError::InvalidMarketStatus);
+        }
+
+        let mut book = self
+            .load_book(market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        let mut cancelled = Vec::new();
+        for side in [Side::Bid, Side::Ask] {
+            let orders: Vec<(OrderId, Order)> = book
+                .orders(side, state)?
+                .into_iter()
+                .take(limit - cancelled.len())
+                .collect();
+
+            for (order_id, order) in orders {
+                book.remove(side, order_id, state)?;
+
+                self.emit_event(
+                    state,
+                    Event::OrderCancelled {
+                        order_id,
+                        market_id,
+                        side,
+                    },
+                );
+                cancelled.push((order_id, side, order));
+            }
+        }
+
+        self.save_book(book, state)?;
+
+        Ok(cancelled)
+    }
+
+    /// Loads a market whose orders are managed by the traders themselves
+    fn load_public_market(
+        &self,
+        market_id: MarketId,
+        state: &mut impl TxState<S>,
+    ) -> Result<Market<S>> {
+        let market: Market<S> = self
+            .markets
+            .get(&market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        if market.delegated {
+            bail!(AAOBError::DelegatedMarket);
+        }
+
+        Ok(market)
+    }
+
+    fn load_delegated_market(
+        &self,
+        market_id: MarketId,
+        state: &mut impl TxState<S>,
+    ) -> Result<Market<S>> {
+        let market: Market<S> = self
+            .markets
+            .get(&market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        if !market.delegated {
+            bail!(AAOBError::WrongCallerAuthority);
+        }
+
+        Ok(market)
+    }
+
+    /// Fails unless the sender is the market's caller authority or the module admin.
+    ///
+    /// Delegated markets are always rejected, the module that listed them manages them through
+    /// [`AAOB::close_market_unchecked`].
+    fn check_market_authority(
+        &self,
+        market: &Market<S>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        if market.delegated {
+            bail!(AAOBError::DelegatedMarket);
+        }
+
+        let sender = context.sender();
+
+        if sender.as_ref() == market.caller_authority.as_ref() {
+            return Ok(());
+        }
+
+        match self.admin.get(state)? {
+            Some(admin) if admin.as_ref() == sender.as_ref() => Ok(()),
+            _ => bail!(AAOBError::WrongCallerAuthority),
+        }
+    }
+
+    /// Resolves the owner key of orders placed by the sender.
+    ///
+    /// Transactions signed by a key registered with Capsule act on behalf of its smart wallet.
+    fn get_order_owner(&self, context: &Context<S>, state: &mut impl TxState<S>) -> Result<[u8; 32]> {
+        let timestamp = self.time_module.get_time(state)?.unix_timestamp;
+
+        Ok(
+            match self
+                .capsule
+                .get_trading_address(context.sender(), timestamp, state)?
+            {
+                Some(smart_wallet) => get_owner_key::<S>(smart_wallet.as_ref()),
+                None => get_owner_key::<S>(context.sender().as_ref()),
+            },
+        )
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn create_market(
         &self,
@@ -371,18 +909,51 @@ impl<S: Spec> AAOB<S> {
         context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
-        let market = Market::new(
+        self.register_market(
             market_name,
+            MarketCallerAuthority::new(context.sender()),
+            false,
+            fee_budget,
+            min_base_size,
+            tick_size,
+            context,
+            state,
+        )?;
+        Ok(())
+    }
+
+    /// Creates a market controlled by `caller_authority`.
+    ///
+    /// Modules that list their own markets, like the dex, call this directly with their own
+    /// address as the caller authority. Orders of `delegated` markets can only go through
+    /// [`AAOB::place_delegated_order`] and [`AAOB::cancel_delegated_order`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn register_market(
+        &self,
+        market_name: &str,
+        caller_authority: MarketCallerAuthority<S>,
+        delegated: bool,
+        fee_budget: u64,
+        min_base_size: u64,
+        tick_size: u64,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<MarketId> {
+        let (market_id, market) = Market::new(
+            market_name,
+            caller_authority,
+            delegated,
             fee_budget,
             min_base_size,
             tick_size,
             &self.markets, // Map<MarketId, Market<S>>
             context,
             state,
-        );
-        let market_id = get_market_id::<S>(market_name);
-        self.markets.set(&market_id, &market.unwrap().1, state)?;
+        )?;
+        self.markets.set(&market_id, &market, state)?;
         self.market_ids.push(&market_id, state)?;
+        self.books
+            .set(&market_id, &BookHeader::new(market_id), state)?;
         self.emit_event(
             state,
             Event::MarketCreated {
@@ -390,44 +961,186 @@ impl<S: Spec> AAOB<S> {
                 market_name: market_name.to_string(),
             },
         );
-        Ok(())
+        Ok(market_id)
     }
 
     pub(crate) fn close_market(
         &self,
         market_name: &str,
+        context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
         let market_id = get_market_id::<S>(market_name);
-        let market = &self.markets.get(&market_id, state)?;
+        let market = match self.markets.get(&market_id, state)? {
+            Some(market) if market.status != MarketStatus::Closed => market,
+            _ => bail!("market with name {} does nots exists", market_name),
+        };
 
-        if !market.is_some() {
-            bail!("market with name {} does nots exists", market_name);
-        } else {
-            self.emit_event(
-                state,
-                Event::MarketClosed {
-                    market_id,
-                    market_name: market_name.to_string(),
-                },
-            );
+        self.check_market_authority(&market, context, state)?;
 
-            self.markets.remove(&market_id, state)?;
+        self.shut_down_market(market_id, market, state)
+    }
 
-            let results: Vec<Result<MarketId, _>> = self.market_ids.iter(state).unwrap().collect();
-            let mut market_ids: Vec<MarketId> =
-                results.into_iter().filter_map(Result::ok).collect();
+    /// Drains the book of `market_id` and marks the market as closed, the caller is responsible
+    /// for authorizing the operation
+    pub fn close_market_unchecked(
+        &self,
+        market_id: MarketId,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let market = match self.markets.get(&market_id, state)? {
+            Some(market) if market.status != MarketStatus::Closed => market,
+            _ => bail!(AAOBError::MarketNotFound),
+        };
 
-            let initial_len: usize = market_ids.len();
-            market_ids.retain(|id| id != market_id);
+        self.shut_down_market(market_id, market, state)
+    }
 
-            if market_ids.len() < initial_len {
-                self.market_ids.clear(state).expect("cant clear market ids");
-                self.market_ids
-                    .set_all(market_ids, state)
-                    .expect("cant set all market ids");
+    fn shut_down_market(
+        &self,
+        market_id: MarketId,
+        mut market: Market<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        // drain the book, every resting order is taken out
+        if let Some(mut book) = self.load_book(market_id, state)? {
+            for side in [Side::Bid, Side::Ask] {
+                for (order_id, order) in book.orders(side, state)? {
+                    self.emit_event(
+                        state,
+                        Event::Out {
+                            order_id,
+                            market_id,
+                            side,
+                            trg_id: order.trg_id,
+                            base_qty: order.base_qty,
+                            reason: OutReason::MarketClosed,
+                        },
+                    );
+                }
             }
+
+            self.delete_book(book, state)?;
         }
+
+        self.emit_event(
+            state,
+            Event::MarketClosed {
+                market_id,
+                market_name: market.name.clone(),
+            },
+        );
+
+        // the market is kept so that its name can not be reused
+        market.status = MarketStatus::Closed;
+        self.markets.set(&market_id, &market, state)?;
+
+        let results: Vec<Result<MarketId, _>> = self.market_ids.iter(state).unwrap().collect();
+        let mut market_ids: Vec<MarketId> = results.into_iter().filter_map(Result::ok).collect();
+
+        let initial_len: usize = market_ids.len();
+        market_ids.retain(|id| id != market_id);
+
+        if market_ids.len() < initial_len {
+            self.market_ids.clear(state).expect("cant clear market ids");
+            self.market_ids
+                .set_all(market_ids, state)
+                .expect("cant set all market ids");
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn update_market(
+        &self,
+        market_id: MarketId,
+        fee_budget: Option<u64>,
+        min_base_size: Option<u64>,
+        tick_size: Option<u64>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let mut market: Market<S> = self
+            .markets
+            .get(&market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        self.check_market_authority(&market, context, state)?;
+
+        if market.status == MarketStatus::Closed {
+            bail!(AAOBError::InvalidMarketStatus);
+        }
+
+        if let Some(tick_size) = tick_size {
+            if tick_size == 0 {
+                bail!("update_market: invalid tick size {tick_size}");
+            }
+
+            let book = self
+                .load_book(market_id, state)?
+                .ok_or(AAOBError::MarketNotFound)?;
+
+            // every order of a level shares its price, the index is enough
+            let off_tick = book
+                .prices(Side::Bid)
+                .iter()
+                .chain(book.prices(Side::Ask))
+                .any(|price| price % tick_size != 0);
+
+            if off_tick {
+                bail!(AAOBError::RestingOrdersOffTick);
+            }
+
+            market.tick_size = tick_size;
+        }
+
+        if let Some(fee_budget) = fee_budget {
+            market.fee_budget = fee_budget;
+        }
+
+        if let Some(min_base_size) = min_base_size {
+            market.min_base_size = min_base_size;
+        }
+
+        self.markets.set(&market_id, &market, state)?;
+
+        self.emit_event(
+            state,
+            Event::MarketUpdated {
+                market_id,
+                fee_budget: market.fee_budget,
+                min_base_size: market.min_base_size,
+                tick_size: market.tick_size,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub(crate) fn set_market_status(
+        &self,
+        market_id: MarketId,
+        status: MarketStatus,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let mut market: Market<S> = self
+            .markets
+            .get(&market_id, state)?
+            .ok_or(AAOBError::MarketNotFound)?;
+
+        self.check_market_authority(&market, context, state)?;
+
+        // closing goes through `CloseMarket` so that the book is drained
+        if market.status == MarketStatus::Closed || status == MarketStatus::Closed {
+            bail!(AAOBError::InvalidMarketStatus);
+        }
+
+        market.status = status;
+        self.markets.set(&market_id, &market, state)?;
+
+        self.emit_event(state, Event::MarketStatusChanged { market_id, status });
+
         Ok(())
     }
 }
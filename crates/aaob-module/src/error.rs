@@ -46,6 +46,20 @@ pub enum AAOBError {
     InvalidCallbackInfo,
     #[error("Account tag mismatch")]
     AccountTagMismatch,
+    #[error("The market could not be found.")]
+    MarketNotFound,
+    #[error("The fill or kill order could not be filled entirely.")]
+    FillOrKillNotFilled,
+    #[error("The order expiry is in the past.")]
+    InvalidExpiry,
+    #[error("The market status does not allow this operation.")]
+    InvalidMarketStatus,
+    #[error("Resting orders are not a multiple of the new tick size.")]
+    RestingOrdersOffTick,
+    #[error("The batch holds more orders than allowed.")]
+    BatchTooLarge,
+    #[error("Orders of this market can only be managed by its caller authority.")]
+    DelegatedMarket,
 }
 
 pub type AAOBResult<T = ()> = Result<T, AAOBError>;
@@ -1,4 +1,4 @@
-use crate::{MarketId, OrderId, OrderbookId};
+use crate::{MarketId, MarketStatus, OrderId};
 
 use spicenet_shared::utils::Side;
 
@@ -26,6 +26,15 @@ pub enum Event {
         market_id: MarketId,
         side: Side,
     },
+    /// A resting order was shrunk in place by [`crate::CallMessage::ReplaceOrder`], it keeps its
+    /// queue priority
+    OrderReduced {
+        order_id: OrderId,
+        market_id: MarketId,
+        side: Side,
+        /// base quantity resting after the replace
+        base_qty: u64,
+    },
     MarketCreated {
         market_id: MarketId,
         market_name: String,
@@ -34,4 +43,63 @@ pub enum Event {
         market_id: MarketId,
         market_name: String,
     },
+    MarketUpdated {
+        market_id: MarketId,
+        fee_budget: u64,
+        min_base_size: u64,
+        tick_size: u64,
+    },
+    MarketStatusChanged {
+        market_id: MarketId,
+        status: MarketStatus,
+    },
+    /// Emitted for every match between a taker and a resting (maker) order
+    Fill {
+        market_id: MarketId,
+        /// per-market sequence number of the fill
+        fill_seq_num: u64,
+        maker_order_id: OrderId,
+        maker_trg_id: u64,
+        taker_trg_id: u64,
+        /// side of the taker order
+        taker_side: Side,
+        price: u64,
+        base_qty: u64,
+        quote_qty: u64,
+        /// the maker order was removed from the book by this fill
+        maker_order_completed: bool,
+    },
+    /// Emitted when a resting order is removed from the book without being filled
+    Out {
+        order_id: OrderId,
+        market_id: MarketId,
+        side: Side,
+        trg_id: u64,
+        /// base quantity the order had left when it was removed
+        base_qty: u64,
+        reason: OutReason,
+    },
+}
+
+/// Why a resting order was taken out of the book
+#[derive(
+    borsh::BorshDeserialize,
+    borsh::BorshSerialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Debug,
+    PartialEq,
+    Clone,
+    Copy,
+    Eq,
+)]
+pub enum OutReason {
+    /// the order was partially filled and what is left is below the market's `min_base_size`
+    BelowMinBaseSize,
+    /// the order was cancelled by a taker of the same trg using [`crate::SelfTradeHandler::CancelProvide`]
+    SelfTrade,
+    /// the order was a [`crate::OrderType::GoodTilTime`] order past its expiry
+    Expired,
+    /// the order was resting when its market was closed
+    MarketClosed,
 }
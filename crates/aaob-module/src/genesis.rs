@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use sov_modules_api::GenesisState;
 use sov_modules_api::Spec;
 
-use super::{get_market_id, Market, AAOB};
+use super::{get_market_id, BookHeader, Market, AAOB};
 
 /// Initial configuration for AAOB module.
 
@@ -15,11 +15,16 @@ use super::{get_market_id, Market, AAOB};
     derive(serde::Serialize),
     derive(serde::Deserialize),
     derive(schemars::JsonSchema),
-    serde(bound = "Market<S>: Serialize + serde::de::DeserializeOwned")
+    serde(
+        bound = "Market<S>: Serialize + serde::de::DeserializeOwned, S::Address: Serialize + serde::de::DeserializeOwned"
+    ),
+    schemars(bound = "S::Address: ::schemars::JsonSchema")
 )]
 #[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
 pub struct AAOBConfig<S: Spec> {
-    initial_markets: Vec<Market<S>>,
+    pub initial_markets: Vec<Market<S>>,
+    /// Allowed to update, pause and close any market
+    pub admin: S::Address,
     #[cfg_attr(
         feature = "native",
         serde(skip_serializing, default)
@@ -34,10 +39,14 @@ impl<S: Spec> AAOB<S> {
         config: &<Self as sov_modules_api::Module>::Config,
         state: &mut impl GenesisState<S>,
     ) -> Result<()> {
+        self.admin.set(&config.admin, state)?;
+
         // Initialize markets
         for market in config.initial_markets.iter() {
             let address = get_market_id::<S>(&market.name);
             self.markets.set(&address, market, state)?;
+            self.market_ids.push(&address, state)?;
+            self.books.set(&address, &BookHeader::new(address), state)?;
         }
 
         Ok(())
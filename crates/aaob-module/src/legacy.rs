@@ -0,0 +1,124 @@
+//! Frozen copies of the slab based book as markets stored it before the paged [`crate::Book`].
+//!
+//! These types must keep the exact borsh layout of the stored books, they are only read to migrate
+//! markets and are never written back.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use sokoban::Critbit;
+use spicenet_shared::Side;
+
+use crate::{
+    get_order_id, BookHeader, MarketId, Order, PriceLevel, PriceLevelKey, StateType, MAX_SIZE,
+    NUM_NODES,
+};
+
+/// Resting order of the slab based book
+#[repr(C)]
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct LegacyOrder {
+    pub order_id: u64,
+    pub price: u64,
+    pub base_qty: u64,
+    pub trg_id: u64,
+}
+
+unsafe impl Pod for LegacyOrder {}
+unsafe impl Zeroable for LegacyOrder {}
+
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct LegacySlabHeader {
+    pub account_type: StateType,
+    pub root_node: u32,
+    pub total_orders: u64,
+    pub market_address: MarketId,
+}
+
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(Clone, BorshDeserialize, BorshSerialize, Debug, Eq, PartialEq, Hash)]
+pub struct LegacySlab {
+    pub header: LegacySlabHeader,
+    pub tree: Critbit<LegacyOrder, NUM_NODES, MAX_SIZE>,
+}
+
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LegacyOrderbookState {
+    pub market_id: MarketId,
+    pub bids: LegacySlab,
+    pub asks: LegacySlab,
+}
+
+impl LegacyOrderbookState {
+    /// Converts the book into the header and the non-empty levels of the paged book.
+    ///
+    /// The slab book matched the maximum key of the bids and the minimum key of the asks first, so
+    /// orders are given new sequence numbers in that order and keep their priority in their level.
+    /// Legacy orders have no owner, client order id or expiry.
+    pub fn migrate(&self) -> (BookHeader, Vec<(PriceLevelKey, PriceLevel)>) {
+        let mut header = BookHeader::new(self.market_id);
+        let mut levels: Vec<(PriceLevelKey, PriceLevel)> = vec![];
+
+        for side in [Side::Bid, Side::Ask] {
+            let orders: Vec<&LegacyOrder> = match side {
+                Side::Bid => (&self.bids.tree)
+                    .into_iter()
+                    .rev()
+                    .map(|(_, o)| o)
+                    .collect(),
+                Side::Ask => (&self.asks.tree).into_iter().map(|(_, o)| o).collect(),
+            };
+
+            for legacy_order in orders {
+                let seq_num = header.next_seq_num();
+                let order = Order {
+                    order_id: seq_num,
+                    price: legacy_order.price,
+                    base_qty: legacy_order.base_qty,
+                    trg_id: legacy_order.trg_id,
+                    ..Default::default()
+                };
+                let key = PriceLevelKey {
+                    market_id: self.market_id,
+                    side,
+                    price: order.price,
+                };
+                let order_id = get_order_id(side, order.price, seq_num);
+
+                match levels.iter_mut().find(|(level_key, _)| *level_key == key) {
+                    Some((_, level)) => level.orders.push((order_id, order)),
+                    None => levels.push((
+                        key,
+                        PriceLevel {
+                            orders: vec![(order_id, order)],
+                        },
+                    )),
+                }
+            }
+        }
+
+        (header, levels)
+    }
+}
@@ -1,19 +1,25 @@
 use sov_modules_api::{
     Context, Error, GenesisState, Module, ModuleId, ModuleInfo, Spec, StateMap,
-    StateVec, TxState, DaSpec
+    StateValue, StateVec, TxState, DaSpec
 };
 use std::marker::PhantomData;
 
+use capsule::Capsule;
+use spicenet_time::TimeModule;
+
 pub use address::*;
+pub use book::*;
 pub use call::*;
 pub use error::*;
 pub use event::*;
 pub use genesis::*;
+pub use legacy::*;
 pub use market::*;
 pub use orderbook::*;
 #[cfg(feature = "native")]
 pub use rpc::*;
 pub use state::*;
+pub use top_of_book::*;
 pub use tree::*;
 pub use utils::*;
 
@@ -22,15 +28,18 @@ pub use utils::*;
 // use crate::orderbook::OrderbookState;
 
 pub mod address;
+pub mod book;
 pub mod call;
 pub mod error;
 pub mod event;
 pub mod genesis;
+pub mod legacy;
 pub mod market;
 pub mod orderbook;
 #[cfg(feature = "native")]
 pub mod rpc;
 pub mod state;
+pub mod top_of_book;
 pub mod tree;
 pub mod utils;
 
@@ -45,8 +54,28 @@ pub struct AAOB<S: Spec> {
     #[state]
     market_ids: StateVec<MarketId>,
 
+    /// Slab based books of markets created before the paged book, converted by [`AAOB::load_book`]
+    #[state]
+    orderbooks: StateMap<OrderbookId, LegacyOrderbookState>,
+
+    #[state]
+    books: StateMap<MarketId, BookHeader>,
+
+    #[state]
+    book_prices: StateMap<BookSideKey, Vec<u64>>,
+
+    #[state]
+    price_levels: StateMap<PriceLevelKey, PriceLevel>,
+
+    /// Allowed to update, pause and close any market
     #[state]
-    orderbooks: StateMap<OrderbookId, OrderbookState>,
+    admin: StateValue<S::Address>,
+
+    #[module]
+    capsule: Capsule<S>,
+
+    #[module]
+    time_module: TimeModule<S>,
 
     #[phantom]
     _marker: PhantomData<S>,
@@ -78,27 +107,29 @@ impl<S: Spec> Module for AAOB<S> {
             CallMessage::CreateOrder {
                 market_id,
                 side,
-                post_only,
-                post_allowed,
+                order_type,
                 limit_price,
                 max_quote_qty,
                 max_base_qty,
                 self_trade_behavior,
                 match_limit,
                 trg_id,
+                client_order_id,
             } => self.create_order(
                 market_id,
-                side,
-                max_base_qty,
-                max_quote_qty,
-                limit_price,
-                post_only,
-                post_allowed,
-                self_trade_behavior,
-                trg_id,
+                NewOrder {
+                    side,
+                    max_base_qty,
+                    max_quote_qty,
+                    limit_price,
+                    order_type,
+                    self_trade_behavior,
+                    match_limit,
+                    trg_id,
+                    client_order_id,
+                },
                 context,
                 state,
-                match_limit,
             ),
 
             CallMessage::CancelOrder {
@@ -107,6 +138,31 @@ impl<S: Spec> Module for AAOB<S> {
                 side,
             } => self.cancel_order(market_id, order_id, side, context, state),
 
+            CallMessage::BatchOrders {
+                market_id,
+                cancels,
+                places,
+            } => self.batch_orders(market_id, cancels, places, context, state),
+
+            CallMessage::ReplaceOrder {
+                market_id,
+                order_id,
+                order,
+            } => self.replace_order(market_id, order_id, order, context, state),
+
+            CallMessage::CancelAllOrders { market_id } => {
+                self.cancel_all_orders(market_id, context, state)
+            }
+
+            CallMessage::CancelOrdersByClientId {
+                market_id,
+                client_order_ids,
+            } => self.cancel_orders_by_client_id(market_id, client_order_ids, context, state),
+
+            CallMessage::PruneExpiredOrders { market_id, limit } => {
+                self.prune_expired_orders(market_id, limit, state)
+            }
+
             CallMessage::CreateMarket {
                 market_name,
                 fee_budget,
@@ -121,8 +177,21 @@ impl<S: Spec> Module for AAOB<S> {
                 state,
             ),
 
-            CallMessage::CloseMarket { market_name } => self.close_market(&(String::from(market_name)), state),
-        };
+            CallMessage::CloseMarket { market_name } => {
+                self.close_market(&(String::from(market_name)), context, state)
+            }
+
+            CallMessage::UpdateMarket {
+                market_id,
+                fee_budget,
+                min_base_size,
+                tick_size,
+            } => self.update_market(market_id, fee_budget, min_base_size, tick_size, context, state),
+
+            CallMessage::SetMarketStatus { market_id, status } => {
+                self.set_market_status(market_id, status, context, state)
+            }
+        }?;
 
         Ok(())
     }
@@ -4,14 +4,17 @@ use anyhow::anyhow;
 use sov_modules_api::{Context, Spec, StateAccessor, StateMap};
 
 use crate::get_market_id;
+use crate::MarketCallerAuthority;
 use crate::MarketId;
-use crate::OrderbookId;
+use crate::MarketStatus;
 
 #[cfg_attr(
     feature = "native",
     derive(serde::Serialize),
     derive(serde::Deserialize),
-    derive(schemars::JsonSchema)
+    derive(schemars::JsonSchema),
+    serde(bound = "MarketCallerAuthority<S>: serde::Serialize + serde::de::DeserializeOwned"),
+    schemars(bound = "S::Address: ::schemars::JsonSchema")
 )]
 #[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone, Eq)]
 pub struct Market<S: Spec> {
@@ -20,7 +23,8 @@ pub struct Market<S: Spec> {
     // pub account_tag: u64,
     // pub orderbook_id: OrderbookId,
 
-    // pub caller_authority: MarketCallerAuthority<S>,
+    /// * delegated authority of the market, allowed to cancel any order of the book
+    pub caller_authority: MarketCallerAuthority<S>,
     /// * the length of an order actor's callback identifier
     // pub callback_id_len: u64,
 
@@ -33,6 +37,13 @@ pub struct Market<S: Spec> {
 
     pub tick_size: u64,
 
+    pub status: MarketStatus,
+
+    /// orders can only be placed and cancelled by the module acting as caller authority, see
+    /// [`crate::AAOB::place_delegated_order`]
+    #[cfg_attr(feature = "native", serde(default))]
+    pub delegated: bool,
+
     // pub cranker_reward: u64,
     #[cfg_attr(
         feature = "native",
@@ -47,7 +58,8 @@ impl<S: Spec> Market<S> {
         market_name: &str,
         // account_tag: u64,
         // orderbook_id: OrderbookId,
-        // caller_authority: MarketCallerAuthority<S>,
+        caller_authority: MarketCallerAuthority<S>,
+        delegated: bool,
         // callback_id_len: u64,
         // callback_info_len: u64,
         fee_budget: u64,
@@ -69,12 +81,14 @@ impl<S: Spec> Market<S> {
                     name: market_name.to_owned(),
                     // account_tag,
                     // orderbook_id,
-                    // caller_authority,
+                    caller_authority,
                     // callback_id_len,
                     // callback_info_len,
                     fee_budget,
                     min_base_size,
                     tick_size,
+                    status: MarketStatus::Active,
+                    delegated,
                     // cranker_reward,
                     _phantom: Default::default(),
                 },
@@ -82,3 +96,13 @@ impl<S: Spec> Market<S> {
         }
     }
 }
+
+impl<S: Spec> Market<S> {
+    pub fn can_place_orders(&self) -> bool {
+        self.status == MarketStatus::Active
+    }
+
+    pub fn can_cancel_orders(&self) -> bool {
+        matches!(self.status, MarketStatus::Active | MarketStatus::CancelOnly)
+    }
+}
@@ -1,10 +1,38 @@
-use sokoban::NodeAllocatorMap;
 use spicenet_shared::Side;
 
-use crate::{MarketId, Slab, StateType};
+use crate::{aggregate_levels, BookLevel, MarketId, Order, Slab, StateType, TopOfBook};
 
 pub type OrderbookId = MarketId;
-pub type OrderId = u128; // TODO: cross check
+/// Critbit key of a resting order, see [`get_order_id`]
+pub type OrderId = u128;
+
+/// Encodes price-time priority into a critbit key.
+///
+/// The upper 64 bits hold the limit price and the lower 64 bits the per-market sequence number.
+/// Bids store the complement of the sequence number so that the maximum key of the bids slab and
+/// the minimum key of the asks slab are always the oldest order at the best price.
+pub fn get_order_id(side: Side, price: u64, seq_num: u64) -> OrderId {
+    let seq_num = match side {
+        Side::Bid => !seq_num,
+        Side::Ask => seq_num,
+    };
+    ((price as u128) << 64) | seq_num as u128
+}
+
+/// Side of an [`OrderId`], sequence numbers never reach `2^63` so the top bit of the lower half is
+/// only set by the complement stored for bids
+pub fn get_side_from_order_id(order_id: OrderId) -> Side {
+    if order_id & (1 << 63) == 0 {
+        Side::Ask
+    } else {
+        Side::Bid
+    }
+}
+
+/// Price component of an [`OrderId`]
+pub fn get_price_from_order_id(order_id: OrderId) -> u64 {
+    (order_id >> 64) as u64
+}
 
 #[cfg_attr(
     feature = "native",
@@ -13,37 +41,67 @@ pub type OrderId = u128; // TODO: cross check
     derive(schemars::JsonSchema)
 )]
 #[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Clone, Debug, PartialEq, Eq, Hash)]
+/// Slab based book, superseded by the paged [`crate::Book`]. Books stored before it are read as
+/// [`crate::LegacyOrderbookState`]
 pub struct OrderbookState {
     pub market_id: MarketId,
     pub bids: Slab,
     pub asks: Slab,
+    /// sequence number that will be assigned to the next order of this market
+    pub seq_num: u64,
+    /// sequence number that will be assigned to the next fill of this market
+    pub fill_seq_num: u64,
 }
 
 impl OrderbookState {
+    pub fn new(market_id: MarketId) -> Self {
+        Self {
+            market_id,
+            bids: Slab::new(StateType::Bids, market_id),
+            asks: Slab::new(StateType::Asks, market_id),
+            seq_num: 0,
+            fill_seq_num: 0,
+        }
+    }
+
     pub fn find_bbo(&self, side: Side) -> Option<u32> {
         match side {
             Side::Bid => self.bids.find_max(),
             Side::Ask => self.asks.find_min(),
         }
     }
+
+    /// id and contents of the order at the top of `side`
+    pub fn best_order(&self, side: Side) -> Option<(OrderId, Order)> {
+        match side {
+            Side::Bid => self.bids.find_leaf(true),
+            Side::Ask => self.asks.find_leaf(false),
+        }
+    }
+
     pub fn get_spread(&self) -> (Option<u64>, Option<u64>) {
-        let best_bid_price = self.bids.find_max().map(|x| {
-            self.bids
-                .tree
-                .get(&self.bids.get_node(x).unwrap().key)
-                .unwrap()
-                .price
-        });
-        let best_ask_price = self.asks.find_max().map(|x| {
-            self.asks
-                .tree
-                .get(&self.asks.get_node(x).unwrap().key)
-                .unwrap()
-                .price
-        });
+        let best_bid_price = self.best_order(Side::Bid).map(|(_, order)| order.price);
+        let best_ask_price = self.best_order(Side::Ask).map(|(_, order)| order.price);
         (best_bid_price, best_ask_price)
     }
 
+    /// every level of `side` from the best to the worst price
+    pub fn depth(&self, side: Side) -> Vec<BookLevel> {
+        let slab = match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        };
+
+        aggregate_levels(side, slab.tree.into_iter().map(|(_, order)| order))
+    }
+
+    pub fn top_of_book(&self) -> TopOfBook {
+        TopOfBook {
+            best_bid: self.depth(Side::Bid).first().copied(),
+            best_ask: self.depth(Side::Ask).first().copied(),
+        }
+    }
+
     pub fn get_tree(&mut self, side: Side) -> &mut Slab {
         // TODO: (anishde12020) remove lifetimes
         match side {
@@ -51,11 +109,12 @@ impl OrderbookState {
             Side::Ask => &mut self.asks,
         }
     }
-    pub fn commit_to_header(self, market_address: MarketId) {
+
+    pub fn commit_to_header(&mut self) {
         self.bids
-            .write_header(None, market_address, StateType::Bids);
+            .write_header(None, self.market_id, StateType::Bids);
         self.asks
-            .write_header(None, market_address, StateType::Asks);
+            .write_header(None, self.market_id, StateType::Asks);
     }
 
     pub fn is_empty(&self) -> bool {
@@ -1,27 +1,47 @@
-use crate::{get_market_id, MarketId, OrderbookId, Slab, AAOB};
+use crate::{get_market_id, MarketId, Order, OrderId, OrderbookId, PriceLevel, TopOfBook, AAOB};
 use jsonrpsee::core::RpcResult;
 use jsonrpsee::types::error::ErrorCode;
 use sov_modules_api::macros::rpc_gen;
-use sov_modules_api::rest::utils::{ApiResult, Path, Query};
+use sov_modules_api::rest::utils::{errors, ApiResult, Path, Query};
 use sov_modules_api::rest::{ApiState, HasCustomRestApi};
-use sov_modules_api::{ApiStateAccessor, Spec, StateReader, StateReaderAndWriter};
+use sov_modules_api::{ApiStateAccessor, Spec, StateAccessor, StateReader, StateReaderAndWriter};
 use sov_state::User;
 use spicenet_shared::Side;
 use axum::routing::get;
 use sov_modules_api::prelude::axum;
 
+/// Number of levels per side returned by the L2 REST endpoint when no depth is given
+pub const DEFAULT_L2_DEPTH: u32 = 20;
+
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct OrderbookDetails {
     pub id: OrderbookId,
     pub market_id: MarketId,
-    pub bids: Slab,
-    pub asks: Slab,
+    /// bid levels from the best to the worst price
+    pub bids: Vec<PriceLevel>,
+    /// ask levels from the best to the worst price
+    pub asks: Vec<PriceLevel>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct BestBidOffer {
-    pub best_bid: Option<u32>,
-    pub best_ask: Option<u32>,
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+    /// total base quantity resting at the best bid
+    pub best_bid_size: Option<u64>,
+    /// total base quantity resting at the best ask
+    pub best_ask_size: Option<u64>,
+}
+
+impl From<TopOfBook> for BestBidOffer {
+    fn from(top: TopOfBook) -> Self {
+        Self {
+            best_bid: top.best_bid.map(|level| level.price),
+            best_ask: top.best_ask.map(|level| level.price),
+            best_bid_size: top.best_bid.map(|level| level.base_qty),
+            best_ask_size: top.best_ask.map(|level| level.base_qty),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -48,31 +68,97 @@ pub struct getMarketBBOQuery {
     pub markets: Vec<String>,
 }
 
+/// Aggregated size of a single price
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct L2Level {
+    pub price: u64,
+    pub base_qty: u64,
+    pub order_count: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct L2Depth {
+    pub market_id: MarketId,
+    /// bid levels from the best to the worst price
+    pub bids: Vec<L2Level>,
+    /// ask levels from the best to the worst price
+    pub asks: Vec<L2Level>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct OrderDetails {
+    pub market_id: MarketId,
+    pub order_id: OrderId,
+    pub side: Side,
+    pub seq_num: u64,
+    pub price: u64,
+    pub base_qty: u64,
+    pub trg_id: u64,
+    pub client_order_id: u64,
+    pub expiry_timestamp: u64,
+}
+
+impl OrderDetails {
+    fn new(market_id: MarketId, side: Side, order_id: OrderId, order: &Order) -> Self {
+        Self {
+            market_id,
+            order_id,
+            side,
+            seq_num: order.order_id,
+            price: order.price,
+            base_qty: order.base_qty,
+            trg_id: order.trg_id,
+            client_order_id: order.client_order_id,
+            expiry_timestamp: order.expiry_timestamp,
+        }
+    }
+}
+
+/// Individual orders of a book in priority order
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct L3Book {
+    pub market_id: MarketId,
+    pub bids: Vec<OrderDetails>,
+    pub asks: Vec<OrderDetails>,
+}
+
+#[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize, Clone)]
+pub struct L2DepthQuery {
+    pub depth: Option<u32>,
+}
+
 impl<S: Spec> AAOB<S> {
-    pub fn best_bid_offer<Reader: StateReader<User>>(
+    pub fn best_bid_offer(
         &self,
         orderbook_id: OrderbookId,
-        accessor: &mut Reader,
-    ) -> Result<Option<BestBidOffer>, Reader::Error> {
-        let orderbook = self.orderbooks.get(&orderbook_id, accessor)?.unwrap();
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Option<BestBidOffer>> {
+        let mut book = match self.load_book(orderbook_id, accessor)? {
+            Some(book) => book,
+            None => return Ok(None),
+        };
 
-        let best_bid = orderbook.find_bbo(Side::Bid);
-        let best_ask = orderbook.find_bbo(Side::Ask);
-        Ok(Option::from(BestBidOffer { best_bid, best_ask }))
+        Ok(Some(book.top_of_book(accessor)?.into()))
     }
 
-    pub fn orderbook<Reader: StateReader<User>>(
+    pub fn orderbook(
         &self,
         orderbook_id: OrderbookId,
-        accessor: &mut Reader,
-    ) -> Result<Option<OrderbookDetails>, Reader::Error> {
-        let ob = self.orderbooks.get(&orderbook_id, accessor)?;
-
-        Ok(ob.map(|ob| OrderbookDetails {
-            market_id: ob.market_id,
-            id: ob.market_id,
-            bids: ob.bids,
-            asks: ob.asks,
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Option<OrderbookDetails>> {
+        let mut book = match self.load_book(orderbook_id, accessor)? {
+            Some(book) => book,
+            None => return Ok(None),
+        };
+
+        let bids = book.levels(Side::Bid, accessor)?;
+        let asks = book.levels(Side::Ask, accessor)?;
+
+        Ok(Some(OrderbookDetails {
+            market_id: book.header.market_id,
+            id: book.header.market_id,
+            bids: bids.into_iter().map(|(_, level)| level).collect(),
+            asks: asks.into_iter().map(|(_, level)| level).collect(),
         }))
     }
 
@@ -106,11 +192,11 @@ impl<S: Spec> AAOB<S> {
             .collect()
     }
 
-    pub fn markets_bbo<Reader: StateReader<User>>(
+    pub fn markets_bbo(
         &self,
         market_names: Vec<String>,
-        accessor: &mut Reader,
-    ) -> Result<Vec<MarketBBO>, Reader::Error> {
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Vec<MarketBBO>> {
         let mut results = Vec::new();
 
         for market_name in market_names.iter() {
@@ -127,9 +213,129 @@ impl<S: Spec> AAOB<S> {
 
         Ok(results)
     }
+
+    /// L2 view of the book, at most `depth` levels per side
+    pub fn l2_depth(
+        &self,
+        market_id: MarketId,
+        depth: u32,
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Option<L2Depth>> {
+        let mut book = match self.load_book(market_id, accessor)? {
+            Some(book) => book,
+            None => return Ok(None),
+        };
+
+        let mut sides = vec![];
+        for side in [Side::Bid, Side::Ask] {
+            let mut levels = vec![];
+            // only the requested levels are loaded
+            for price in book
+                .prices_by_priority(side)
+                .into_iter()
+                .take(depth as usize)
+            {
+                let level = book.level(side, price, accessor)?;
+                levels.push(L2Level {
+                    price,
+                    base_qty: level.base_qty(),
+                    order_count: level.orders.len() as u64,
+                });
+            }
+            sides.push(levels);
+        }
+
+        let asks = sides.pop().unwrap_or_default();
+        let bids = sides.pop().unwrap_or_default();
+
+        Ok(Some(L2Depth {
+            market_id,
+            bids,
+            asks,
+        }))
+    }
+
+    pub fn l3_book(
+        &self,
+        market_id: MarketId,
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Option<L3Book>> {
+        let mut book = match self.load_book(market_id, accessor)? {
+            Some(book) => book,
+            None => return Ok(None),
+        };
+
+        let bids = book.orders(Side::Bid, accessor)?;
+        let asks = book.orders(Side::Ask, accessor)?;
+
+        Ok(Some(L3Book {
+            market_id,
+            bids: bids
+                .iter()
+                .map(|(order_id, order)| OrderDetails::new(market_id, Side::Bid, *order_id, order))
+                .collect(),
+            asks: asks
+                .iter()
+                .map(|(order_id, order)| OrderDetails::new(market_id, Side::Ask, *order_id, order))
+                .collect(),
+        }))
+    }
+
+    /// Looks `order_id` up on both sides of the book of `market_id`
+    pub fn order(
+        &self,
+        market_id: MarketId,
+        order_id: OrderId,
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Option<OrderDetails>> {
+        let mut book = match self.load_book(market_id, accessor)? {
+            Some(book) => book,
+            None => return Ok(None),
+        };
+
+        for side in [Side::Bid, Side::Ask] {
+            if let Some(order) = book.get(side, order_id, accessor)? {
+                return Ok(Some(OrderDetails::new(market_id, side, order_id, &order)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Every resting order of `trg_id` across all markets, this walks every book
+    pub fn open_orders(
+        &self,
+        trg_id: u64,
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Vec<OrderDetails>> {
+        let market_ids: Vec<MarketId> = self
+            .market_ids
+            .iter(accessor)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut orders = vec![];
+
+        for market_id in market_ids {
+            let mut book = match self.load_book(market_id, accessor)? {
+                Some(book) => book,
+                None => continue,
+            };
+
+            for side in [Side::Bid, Side::Ask] {
+                orders.extend(
+                    book.orders(side, accessor)?
+                        .iter()
+                        .filter(|(_, order)| order.trg_id == trg_id)
+                        .map(|(order_id, order)| OrderDetails::new(market_id, side, *order_id, order)),
+                );
+            }
+        }
+
+        Ok(orders)
+    }
 }
 
-#[rpc_gen(client, server, namespace = "orderbook")]
+#[rpc_gen(client, server, namespace = "aaob")]
 impl<S: Spec> AAOB<S> {
     #[rpc_method(name = "getBestBidOffer")]
     /// Get the best bid and offer
@@ -173,6 +379,54 @@ impl<S: Spec> AAOB<S> {
             _ => Err(ErrorCode::InternalError.into()),
         }
     }
+
+    #[rpc_method(name = "getL2Depth")]
+    /// Aggregated size of the best `depth` price levels of each side
+    pub fn get_l2_depth(
+        &self,
+        market_id: MarketId,
+        depth: u32,
+        state: &mut ApiStateAccessor<S>,
+    ) -> RpcResult<L2Depth> {
+        self.l2_depth(market_id, depth, state)
+            .map_err(|_| ErrorCode::InternalError.into())
+            .and_then(|result| result.ok_or(ErrorCode::InvalidParams.into()))
+    }
+
+    #[rpc_method(name = "getL3Orders")]
+    /// Every resting order of the book in priority order
+    pub fn get_l3_orders(
+        &self,
+        market_id: MarketId,
+        state: &mut ApiStateAccessor<S>,
+    ) -> RpcResult<L3Book> {
+        self.l3_book(market_id, state)
+            .map_err(|_| ErrorCode::InternalError.into())
+            .and_then(|result| result.ok_or(ErrorCode::InvalidParams.into()))
+    }
+
+    #[rpc_method(name = "getOrder")]
+    pub fn get_order(
+        &self,
+        market_id: MarketId,
+        order_id: OrderId,
+        state: &mut ApiStateAccessor<S>,
+    ) -> RpcResult<OrderDetails> {
+        self.order(market_id, order_id, state)
+            .map_err(|_| ErrorCode::InternalError.into())
+            .and_then(|result| result.ok_or(ErrorCode::InvalidParams.into()))
+    }
+
+    #[rpc_method(name = "getOpenOrders")]
+    /// Resting orders of a trg across every market
+    pub fn get_open_orders(
+        &self,
+        trg_id: u64,
+        state: &mut ApiStateAccessor<S>,
+    ) -> RpcResult<Vec<OrderDetails>> {
+        self.open_orders(trg_id, state)
+            .map_err(|_| ErrorCode::InternalError.into())
+    }
 }
 
 
@@ -185,6 +439,10 @@ impl<S: Spec> HasCustomRestApi for AAOB<S> {
             .route("/orderbook/markets", get(Self::get_markets_rest))
             .route("/orderbook/markets-bbo/:markets", get(Self::get_markets_bbo_rest))
             .route("/orderbook/orderbook/:orderbookId", get(Self::get_orderbook_rest))
+            .route("/orderbook/l2/:marketId", get(Self::get_l2_depth_rest))
+            .route("/orderbook/l3/:marketId", get(Self::get_l3_orders_rest))
+            .route("/orderbook/order/:marketId/:orderId", get(Self::get_order_rest))
+            .route("/orderbook/open-orders/:trgId", get(Self::get_open_orders_rest))
             .with_state(state.with(self.clone()))
     }
 }
@@ -197,11 +455,7 @@ impl<S: Spec> AAOB<S> {
     ) -> ApiResult<BestBidOffer> {
         let bbo = state.get_best_bid_offer(orderbook_id).unwrap();
 
-        Ok(BestBidOffer {
-            best_bid: bbo.best_bid,
-            best_ask: bbo.best_ask,
-        }
-        .into())
+        Ok(bbo.into())
     }
 
     async fn get_markets_rest(
@@ -235,4 +489,53 @@ impl<S: Spec> AAOB<S> {
 
         Ok(orderbook.into())
     }
-}
\ No newline at end of file
+
+    async fn get_l2_depth_rest(
+        state: ApiState<S, Self>,
+        mut accessor: ApiStateAccessor<S>,
+        Path(market_id): Path<MarketId>,
+        params: Query<L2DepthQuery>,
+    ) -> ApiResult<L2Depth> {
+        let depth = state
+            .get_l2_depth(market_id, params.depth.unwrap_or(DEFAULT_L2_DEPTH), &mut accessor)
+            .map_err(|_| errors::not_found_404("Market", market_id))?;
+
+        Ok(depth.into())
+    }
+
+    async fn get_l3_orders_rest(
+        state: ApiState<S, Self>,
+        mut accessor: ApiStateAccessor<S>,
+        Path(market_id): Path<MarketId>,
+    ) -> ApiResult<L3Book> {
+        let book = state
+            .get_l3_orders(market_id, &mut accessor)
+            .map_err(|_| errors::not_found_404("Market", market_id))?;
+
+        Ok(book.into())
+    }
+
+    async fn get_order_rest(
+        state: ApiState<S, Self>,
+        mut accessor: ApiStateAccessor<S>,
+        Path((market_id, order_id)): Path<(MarketId, OrderId)>,
+    ) -> ApiResult<OrderDetails> {
+        let order = state
+            .get_order(market_id, order_id, &mut accessor)
+            .map_err(|_| errors::not_found_404("Order", order_id))?;
+
+        Ok(order.into())
+    }
+
+    async fn get_open_orders_rest(
+        state: ApiState<S, Self>,
+        mut accessor: ApiStateAccessor<S>,
+        Path(trg_id): Path<u64>,
+    ) -> ApiResult<Vec<OrderDetails>> {
+        let orders = state
+            .get_open_orders(trg_id, &mut accessor)
+            .map_err(|_| errors::not_found_404("Trader risk group", trg_id))?;
+
+        Ok(orders.into())
+    }
+}
@@ -1,6 +1,9 @@
 use {
     borsh::{BorshDeserialize, BorshSerialize}, // num_traits::{FromPrimitive, ToBytes, ToPrimitive},
 };
+use spicenet_shared::Side;
+
+use crate::{OrderId, OutReason};
 
 #[cfg_attr(
     feature = "native",
@@ -35,6 +38,146 @@ pub enum SelfTradeHandler {
     AbortTx,
 }
 
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema),
+    derive(sov_modules_api::macros::UniversalWallet)
+)]
+#[derive(Debug, BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    /// Matches what it can and rests the remainder until it is filled or cancelled
+    Limit,
+    /// Rests the whole order, the transaction is a no-op if the order would cross the book
+    PostOnly,
+    /// Matches what it can, the remainder is never posted
+    ImmediateOrCancel,
+    /// Matches the whole order or fails the transaction
+    FillOrKill,
+    /// Same as [`OrderType::Limit`], the resting order expires once the chain's unix timestamp reaches `expiry_timestamp`
+    GoodTilTime { expiry_timestamp: u64 },
+}
+
+impl OrderType {
+    /// the legacy `(post_only, post_allowed)` flags of the matching loop
+    pub fn post_flags(&self) -> (bool, bool) {
+        match self {
+            OrderType::Limit | OrderType::GoodTilTime { .. } => (false, true),
+            OrderType::PostOnly => (true, true),
+            OrderType::ImmediateOrCancel | OrderType::FillOrKill => (false, false),
+        }
+    }
+
+    /// expiry stored on the resting order, `0` never expires
+    pub fn expiry_timestamp(&self) -> u64 {
+        match self {
+            OrderType::GoodTilTime { expiry_timestamp } => *expiry_timestamp,
+            _ => 0,
+        }
+    }
+}
+
+/// Parameters of an order placed through [`crate::CallMessage::BatchOrders`] or
+/// [`crate::CallMessage::ReplaceOrder`], same as the fields of [`crate::CallMessage::CreateOrder`]
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema),
+    derive(sov_modules_api::macros::UniversalWallet)
+)]
+#[derive(Debug, BorshDeserialize, BorshSerialize, Clone, PartialEq, Eq)]
+pub struct NewOrder {
+    pub side: Side,
+    pub max_base_qty: u64,
+    pub max_quote_qty: u64,
+    pub limit_price: u64,
+    pub order_type: OrderType,
+    pub self_trade_behavior: SelfTradeHandler,
+    pub match_limit: u64,
+    pub trg_id: u64,
+    pub client_order_id: u64,
+}
+
+/// A resting order cancelled through [`crate::CallMessage::BatchOrders`]
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema),
+    derive(sov_modules_api::macros::UniversalWallet)
+)]
+#[derive(Debug, BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq, Eq)]
+pub struct OrderCancel {
+    pub order_id: OrderId,
+    pub side: Side,
+}
+
+#[cfg_attr(
+    feature = "native",
+    derive(schemars::JsonSchema),
+    derive(sov_modules_api::macros::UniversalWallet)
+)]
+#[derive(
+    Debug,
+    BorshDeserialize,
+    BorshSerialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+)]
+pub enum MarketStatus {
+    /// Orders can be placed and cancelled
+    Active,
+    /// Resting orders can only be cancelled
+    CancelOnly,
+    /// Neither placing nor cancelling orders is possible
+    Paused,
+    /// The book was drained and the market can not be reopened
+    Closed,
+}
+
+/// Outcome of an order placed through [`crate::AAOB::place_delegated_order`], lets the caller
+/// authority settle fills without replaying the events
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OrderSummary {
+    pub order_id: OrderId,
+    /// base quantity matched and posted
+    pub total_base_qty: u64,
+    /// quote quantity matched and posted
+    pub total_quote_qty: u64,
+    /// base quantity left resting on the book, `0` when nothing was posted
+    pub posted_base_qty: u64,
+    pub fills: Vec<FillSummary>,
+    /// resting orders taken out of the book while matching
+    pub outs: Vec<OutSummary>,
+}
+
+/// Match between the taker and a resting order, same fields as [`crate::Event::Fill`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillSummary {
+    pub maker_order_id: OrderId,
+    pub maker_trg_id: u64,
+    pub price: u64,
+    pub base_qty: u64,
+    pub quote_qty: u64,
+    pub maker_order_completed: bool,
+}
+
+/// Resting order removed without being filled, same fields as [`crate::Event::Out`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutSummary {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub trg_id: u64,
+    pub base_qty: u64,
+    pub reason: OutReason,
+}
+
 #[repr(u8)]
 #[allow(missing_docs)]
 pub enum CompletedReason {
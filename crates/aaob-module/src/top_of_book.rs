@@ -0,0 +1,79 @@
+//! Top-of-book model shared by matching, the RPC layer and mark price calculation.
+//!
+//! Bids are ranked by descending and asks by ascending price, every reader of a book (paged,
+//! legacy slab or raw orders) reduces it to [`BookLevel`]s in that order.
+
+use std::collections::BTreeMap;
+
+use spicenet_shared::Side;
+
+use crate::Order;
+
+/// Price and total resting base quantity of a single level
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookLevel {
+    pub price: u64,
+    pub base_qty: u64,
+}
+
+/// Best level of each side of a book
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TopOfBook {
+    pub best_bid: Option<BookLevel>,
+    pub best_ask: Option<BookLevel>,
+}
+
+impl TopOfBook {
+    pub fn best(&self, side: Side) -> Option<BookLevel> {
+        match side {
+            Side::Bid => self.best_bid,
+            Side::Ask => self.best_ask,
+        }
+    }
+
+    /// best bid and best ask prices
+    pub fn spread(&self) -> (Option<u64>, Option<u64>) {
+        (
+            self.best_bid.map(|level| level.price),
+            self.best_ask.map(|level| level.price),
+        )
+    }
+}
+
+/// whether `price` ranks ahead of `other` on `side`
+pub fn is_better_price(side: Side, price: u64, other: u64) -> bool {
+    match side {
+        Side::Bid => price > other,
+        Side::Ask => price < other,
+    }
+}
+
+/// Aggregates `orders` of `side`, in any order, into levels from the best to the worst price
+pub fn aggregate_levels<'a>(side: Side, orders: impl IntoIterator<Item = &'a Order>) -> Vec<BookLevel> {
+    let mut levels: BTreeMap<u64, u64> = BTreeMap::new();
+
+    for order in orders {
+        *levels.entry(order.price).or_default() += order.base_qty;
+    }
+
+    let levels = levels
+        .into_iter()
+        .map(|(price, base_qty)| BookLevel { price, base_qty });
+
+    match side {
+        Side::Bid => levels.rev().collect(),
+        Side::Ask => levels.collect(),
+    }
+}
@@ -3,7 +3,7 @@
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use sokoban::critbit::CritbitNode;
-use sokoban::{Critbit, NodeAllocatorMap};
+use sokoban::{Critbit, NodeAllocatorMap, SENTINEL};
 use spicenet_shared::Side;
 use std::fmt::Debug;
 use {
@@ -37,16 +37,26 @@ pub struct SlabHeader {
 )]
 #[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
 pub struct Order {
-    pub order_id: u64, // can use custom id (price, timestamp, trg_id)
+    pub order_id: u64, // per-market sequence number, the full id is derived with `get_order_id`
     pub price: u64,
     pub base_qty: u64,
     pub trg_id: u64,
+    /// id chosen by the owner when placing the order
+    pub client_order_id: u64,
+    /// hash of the owner's address, see [`crate::get_owner_key`]
+    pub owner: [u8; 32],
+    /// unix timestamp at which the order expires, `0` for orders without expiry
+    pub expiry_timestamp: u64,
 }
 
 impl Order {
     pub fn set_base_qty(&mut self, base_qty: u64) {
         self.base_qty = base_qty;
     }
+
+    pub fn is_expired(&self, timestamp: u64) -> bool {
+        self.expiry_timestamp != 0 && self.expiry_timestamp <= timestamp
+    }
 }
 
 unsafe impl Pod for Order {}
@@ -79,6 +89,19 @@ pub struct Slab {
 
 /// * data access methods
 impl Slab {
+    /// creates an empty slab for one side of the book of `market_address`
+    pub fn new(account_type: StateType, market_address: MarketId) -> Self {
+        Self {
+            header: SlabHeader {
+                account_type,
+                root_node: SENTINEL,
+                total_orders: 0,
+                market_address,
+            },
+            tree: Critbit::default(),
+        }
+    }
+
     pub(crate) fn check(&self, side: Side) -> bool {
         match side {
             Side::Bid => self.header.account_type == StateType::Bids,
@@ -87,14 +110,14 @@ impl Slab {
     }
 
     pub(crate) fn write_header(
-        mut self,
+        &mut self,
         orders: Option<u64>,
         market_address: MarketId,
         account_type: StateType,
     ) {
         self.header = SlabHeader {
             root_node: self.tree.root,
-            total_orders: orders.unwrap_or(0),
+            total_orders: orders.unwrap_or(self.tree.len() as u64),
             market_address,
             account_type,
         }
@@ -164,7 +187,11 @@ impl Slab {
 impl Slab {
     #[inline(always)]
     pub fn root(&self) -> Option<u32> {
-        Some(self.tree.root)
+        if self.tree.root == SENTINEL {
+            None
+        } else {
+            Some(self.tree.root)
+        }
     }
 
     /// find the min/max key from the Slab
@@ -205,21 +232,28 @@ impl Slab {
 
     #[inline(always)]
     pub(crate) fn remove_min(&mut self) -> Option<Order> {
-        let key = self.get_node(self.find_min().unwrap()).unwrap().key; // Finding the minimal (smallest order node)
+        let key = self.get_node(self.find_min()?)?.key; // Finding the minimal (smallest order node)
 
         self.remove_by_key(key)
     }
 
     #[inline(always)]
     pub(crate) fn remove_max(&mut self) -> Option<Order> {
-        let key = self.get_node(self.find_max().unwrap()).unwrap().key; // Finding the maximum (largest order node)
+        let key = self.get_node(self.find_max()?)?.key; // Finding the maximum (largest order node)
 
         self.remove_by_key(key)
     }
 
     #[inline(always)]
-    pub fn find_by_key(&mut self, search_key: u128) -> Option<Order> {
-        Some(self.tree.get(&search_key).unwrap().to_owned())
+    pub fn find_by_key(&self, search_key: u128) -> Option<Order> {
+        self.tree.get(&search_key).copied()
+    }
+
+    /// key and contents of the leaf returned by [`Slab::find_min`]/[`Slab::find_max`]
+    pub fn find_leaf(&self, find_max: bool) -> Option<(u128, Order)> {
+        let key = self.get_node(self.find_min_max(find_max)?)?.key;
+
+        self.find_by_key(key).map(|order| (key, order))
     }
 
     // #[cfg(test)]
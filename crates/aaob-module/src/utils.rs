@@ -10,6 +10,13 @@ pub fn get_market_id<S: sov_modules_api::Spec>(market_name: &str) -> MarketId {
     hash.into()
 }
 
+/// Derives the key stored as the owner of an order from the owner's address bytes
+pub fn get_owner_key<S: sov_modules_api::Spec>(owner: &[u8]) -> [u8; 32] {
+    let mut hasher = <S::CryptoSpec as CryptoSpec>::Hasher::new();
+    hasher.update(owner);
+    hasher.finalize().into()
+}
+
 /// a is fp0, b is fp32 and result is a/b fp0
 pub(crate) fn fp32_div(a: u64, b_fp32: u64) -> u64 {
     (((a as u128) << 32) / (b_fp32 as u128)) as u64
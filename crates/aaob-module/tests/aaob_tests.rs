@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use spicenet_aaob::{get_market_id, CallMessage, Event, Market, SelfTradeHandler, AAOB};
+use spicenet_aaob::{get_market_id, get_order_id, CallMessage, Event, Market, SelfTradeHandler, AAOB};
 use std::convert::Infallible;
 
 use sov_modules_api::prelude::UnwrapInfallible;
@@ -16,8 +16,11 @@ use sov_test_utils::{
     generate_optimistic_runtime, AsUser, MockDaSpec, TestStorageSpec, TestUser, TransactionTestCase,
 };
 
-use spicenet_aaob::AAOBConfig;
+use spicenet_aaob::{
+    AAOBConfig, MarketCallerAuthority, MarketStatus, NewOrder, OrderCancel, OrderType, OutReason,
+};
 use spicenet_shared::Side;
+use spicenet_time::{TimeConfig, TimeModule};
 
 pub type S = sov_test_utils::TestSpec;
 pub type Storage = ProverStorage<TestStorageSpec>;
@@ -37,7 +40,7 @@ where
     return Address::<S>::from(*bytes);
 }
 
-generate_optimistic_runtime!(TestRuntime <= aaob: AAOB<S>);
+generate_optimistic_runtime!(TestRuntime <= aaob: AAOB<S>, time: TimeModule<S>);
 
 pub struct TestRoles<S: Spec> {
     pub admin: TestUser<S>,
@@ -59,26 +62,38 @@ fn setup() -> (TestRoles<S>, TestRunner<TestRuntime<S, MockDaSpec>, S>) {
         initial_markets: vec![
             Market {
                 name: "TBD/USD".to_string(),
-                orderbook_id: 1,
+                caller_authority: MarketCallerAuthority::new(&registry_admin.address()),
                 fee_budget: 10000000,
                 min_base_size: 0,
                 tick_size: 1000,
+                status: MarketStatus::Active,
+                delegated: false,
                 _phantom: std::marker::PhantomData,
             },
             Market {
                 name: "AAOB/USD".to_string(),
-                orderbook_id: 1,
+                caller_authority: MarketCallerAuthority::new(&registry_admin.address()),
                 fee_budget: 10000000,
                 min_base_size: 0,
                 tick_size: 1000,
+                status: MarketStatus::Active,
+                delegated: false,
                 _phantom: std::marker::PhantomData,
             },
         ],
+        admin: registry_admin.address(),
         _phantom: std::marker::PhantomData,
     };
 
-    let genesis_config =
-        GenesisConfig::from_minimal_config(genesis_config.clone().into(), aaobConfig.clone());
+    let time_config = TimeConfig::<S> {
+        sequencer_authority: generate_address_from_bytes(registry_admin.address().as_bytes()),
+    };
+
+    let genesis_config = GenesisConfig::from_minimal_config(
+        genesis_config.clone().into(),
+        aaobConfig.clone(),
+        time_config.clone(),
+    );
 
     let runner =
         TestRunner::new_with_genesis(genesis_config.into_genesis_params(), TestRuntime::default());
@@ -106,8 +121,7 @@ fn add_market() {
 
     let tx = TransactionTestCase {
         input: admin.create_plain_message::<AAOB<S>>(CallMessage::CreateMarket {
-            market_name: "PEP/USD".to_string(),
-            orderbook_id: 1,
+            market_name: "PEP/USD".try_into().unwrap(),
             fee_budget: 10000000,
             min_base_size: 0,
             tick_size: 1000,
@@ -119,6 +133,12 @@ fn add_market() {
             let markets = AAOB::<S>::default().get_markets(state).unwrap();
 
             println!("{:?}", markets);
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(get_market_id::<S>("PEP/USD"), state)
+                .unwrap();
+
+            assert!(orderbook.is_some());
         }),
     };
     runner.execute_transaction(tx);
@@ -137,7 +157,7 @@ fn close_market() {
 
     let tx = TransactionTestCase {
         input: admin.create_plain_message::<AAOB<S>>(CallMessage::CloseMarket {
-            market_name: "TBD/USD".to_string(),
+            market_name: "TBD/USD".try_into().unwrap(),
         }),
         assert: Box::new(move |result, state| {
             assert!(result.tx_receipt.is_successful());
@@ -146,7 +166,9 @@ fn close_market() {
 
             println!("{:?}", markets);
 
-            let orderbook = AAOB::<S>::default().get_orderbook(1, state).unwrap();
+            let orderbook = AAOB::<S>::default()
+                .orderbook(get_market_id::<S>("TBD/USD"), state)
+                .unwrap();
 
             println!("{:?}", orderbook);
         }),
@@ -170,22 +192,605 @@ fn create_order() {
     let tx = TransactionTestCase {
         input: user1.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
             market_id: market_id,
-            orderbook_id: 1,
             side: Side::Bid,
             max_base_qty: 0,
             max_quote_qty: 0,
             limit_price: 0,
-            post_only: false,
-            post_allowed: false,
+            order_type: OrderType::ImmediateOrCancel,
             self_trade_behavior: SelfTradeHandler::DecrementTake,
             match_limit: 0,
             trg_id: 0,
+            client_order_id: 0,
+        }),
+        // a zero price is not a multiple of the tick size
+        assert: Box::new(move |result, state| {
+            assert!(!result.tx_receipt.is_successful());
+        }),
+    };
+
+    runner.execute_transaction(tx);
+}
+
+#[test]
+fn resting_order_is_persisted_and_matched() {
+    let (
+        TestRoles {
+            admin,
+            user1,
+            user2,
+        },
+        mut runner,
+    ) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let price = 1000u64 << 32;
+
+    let tx = TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+            market_id,
+            side: Side::Ask,
+            max_base_qty: 10,
+            max_quote_qty: 10_000,
+            limit_price: price,
+            order_type: OrderType::Limit,
+            self_trade_behavior: SelfTradeHandler::DecrementTake,
+            match_limit: 10,
+            trg_id: 1,
+            client_order_id: 1,
         }),
         assert: Box::new(move |result, state| {
             assert!(result.tx_receipt.is_successful());
-            assert_eq!(result.events.len(), 1);
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (order_id, order) = orderbook.asks[0].orders[0];
+
+            assert_eq!(order_id, get_order_id(Side::Ask, price, 0));
+            assert_eq!(order.base_qty, 10);
+            assert!(orderbook.bids.is_empty());
+        }),
+    };
+    runner.execute_transaction(tx);
+
+    let tx = TransactionTestCase {
+        input: user2.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+            market_id,
+            side: Side::Bid,
+            max_base_qty: 4,
+            max_quote_qty: 4_000,
+            limit_price: price,
+            order_type: OrderType::Limit,
+            self_trade_behavior: SelfTradeHandler::DecrementTake,
+            match_limit: 10,
+            trg_id: 2,
+            client_order_id: 1,
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+            assert_eq!(result.events.len(), 2);
+            assert_eq!(
+                result.events[0],
+                TestRuntimeEvent::Aaob(Event::Fill {
+                    market_id,
+                    fill_seq_num: 0,
+                    maker_order_id: get_order_id(Side::Ask, price, 0),
+                    maker_trg_id: 1,
+                    taker_trg_id: 2,
+                    taker_side: Side::Bid,
+                    price,
+                    base_qty: 4,
+                    quote_qty: 4_000,
+                    maker_order_completed: false,
+                })
+            );
+            assert_eq!(
+                result.events[1],
+                TestRuntimeEvent::Aaob(Event::OrderCreated {
+                    order_id: get_order_id(Side::Bid, price, 1),
+                    market_id,
+                    side: Side::Bid,
+                    total_base_qty: 4,
+                    total_quote_qty: 4_000,
+                    total_base_qty_posted: 0,
+                })
+            );
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (_, order) = orderbook.asks[0].orders[0];
+
+            assert_eq!(order.base_qty, 6);
+            assert!(orderbook.bids.is_empty());
         }),
     };
+    runner.execute_transaction(tx);
+}
+
+#[test]
+fn best_bid_offer_reports_best_ask_and_size() {
+    let (TestRoles { user1, .. }, mut runner) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let low = 1000u64 << 32;
+    let high = 1001u64 << 32;
+
+    for (limit_price, max_base_qty) in [(high, 5), (low, 3), (low, 4)] {
+        let tx = TransactionTestCase {
+            input: user1.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+                market_id,
+                side: Side::Ask,
+                max_base_qty,
+                max_quote_qty: 10_000,
+                limit_price,
+                order_type: OrderType::Limit,
+                self_trade_behavior: SelfTradeHandler::DecrementTake,
+                match_limit: 10,
+                trg_id: 1,
+                client_order_id: 1,
+            }),
+            assert: Box::new(move |result, _state| {
+                assert!(result.tx_receipt.is_successful());
+            }),
+        };
+        runner.execute_transaction(tx);
+    }
+
+    let tx = TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+            market_id,
+            side: Side::Bid,
+            max_base_qty: 2,
+            max_quote_qty: 10_000,
+            limit_price: 999u64 << 32,
+            order_type: OrderType::Limit,
+            self_trade_behavior: SelfTradeHandler::DecrementTake,
+            match_limit: 10,
+            trg_id: 1,
+            client_order_id: 2,
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
 
+            let bbo = AAOB::<S>::default()
+                .best_bid_offer(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(bbo.best_bid, Some(999u64 << 32));
+            assert_eq!(bbo.best_bid_size, Some(2));
+            assert_eq!(bbo.best_ask, Some(low));
+            assert_eq!(bbo.best_ask_size, Some(7));
+        }),
+    };
     runner.execute_transaction(tx);
 }
+
+#[test]
+fn cancel_requires_order_owner() {
+    let (
+        TestRoles {
+            admin,
+            user1,
+            user2,
+        },
+        mut runner,
+    ) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let price = 1000u64 << 32;
+    let order_id = get_order_id(Side::Bid, price, 0);
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+            market_id,
+            side: Side::Bid,
+            max_base_qty: 10,
+            max_quote_qty: 10_000,
+            limit_price: price,
+            order_type: OrderType::PostOnly,
+            self_trade_behavior: SelfTradeHandler::DecrementTake,
+            match_limit: 10,
+            trg_id: 1,
+            client_order_id: 7,
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user2.create_plain_message::<AAOB<S>>(CallMessage::CancelOrder {
+            market_id,
+            order_id,
+            side: Side::Bid,
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(!result.tx_receipt.is_successful());
+        }),
+    });
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::CancelOrdersByClientId {
+            market_id,
+            client_order_ids: vec![7],
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+            assert_eq!(
+                result.events[0],
+                TestRuntimeEvent::Aaob(Event::OrderCancelled {
+                    order_id,
+                    market_id,
+                    side: Side::Bid,
+                })
+            );
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            assert!(orderbook.bids.is_empty());
+        }),
+    });
+}
+
+#[test]
+fn fill_or_kill_reverts_when_not_filled() {
+    let (
+        TestRoles {
+            admin,
+            user1,
+            user2,
+        },
+        mut runner,
+    ) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let price = 1000u64 << 32;
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+            market_id,
+            side: Side::Ask,
+            max_base_qty: 5,
+            max_quote_qty: 5_000,
+            limit_price: price,
+            order_type: OrderType::Limit,
+            self_trade_behavior: SelfTradeHandler::DecrementTake,
+            match_limit: 10,
+            trg_id: 1,
+            client_order_id: 1,
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user2.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+            market_id,
+            side: Side::Bid,
+            max_base_qty: 10,
+            max_quote_qty: 10_000,
+            limit_price: price,
+            order_type: OrderType::FillOrKill,
+            self_trade_behavior: SelfTradeHandler::DecrementTake,
+            match_limit: 10,
+            trg_id: 2,
+            client_order_id: 1,
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(!result.tx_receipt.is_successful());
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (_, order) = orderbook.asks[0].orders[0];
+
+            assert_eq!(order.base_qty, 5);
+        }),
+    });
+}
+
+fn ask(max_base_qty: u64, limit_price: u64, client_order_id: u64) -> NewOrder {
+    NewOrder {
+        side: Side::Ask,
+        max_base_qty,
+        max_quote_qty: max_base_qty * 1_000,
+        limit_price,
+        order_type: OrderType::Limit,
+        self_trade_behavior: SelfTradeHandler::DecrementTake,
+        match_limit: 10,
+        trg_id: 1,
+        client_order_id,
+    }
+}
+
+#[test]
+fn batch_orders_are_atomic() {
+    let (TestRoles { user1, .. }, mut runner) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let price = 1000u64 << 32;
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::BatchOrders {
+            market_id,
+            cancels: vec![],
+            places: vec![ask(5, price, 1), ask(3, price + (1 << 32), 2)],
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(orderbook.asks.len(), 2);
+        }),
+    });
+
+    // the second cancel targets an order that does not exist, nothing of the batch is applied
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::BatchOrders {
+            market_id,
+            cancels: vec![
+                OrderCancel {
+                    order_id: get_order_id(Side::Ask, price, 0),
+                    side: Side::Ask,
+                },
+                OrderCancel {
+                    order_id: get_order_id(Side::Ask, price, 7),
+                    side: Side::Ask,
+                },
+            ],
+            places: vec![ask(4, price, 3)],
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(!result.tx_receipt.is_successful());
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (order_id, order) = orderbook.asks[0].orders[0];
+
+            assert_eq!(order_id, get_order_id(Side::Ask, price, 0));
+            assert_eq!(order.base_qty, 5);
+            assert_eq!(orderbook.asks[0].orders.len(), 1);
+        }),
+    });
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::BatchOrders {
+            market_id,
+            cancels: vec![OrderCancel {
+                order_id: get_order_id(Side::Ask, price, 0),
+                side: Side::Ask,
+            }],
+            places: vec![ask(4, price, 3)],
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (_, order) = orderbook.asks[0].orders[0];
+
+            assert_eq!(order.client_order_id, 3);
+            assert_eq!(order.base_qty, 4);
+            assert_eq!(orderbook.asks[0].orders.len(), 1);
+        }),
+    });
+}
+
+#[test]
+fn replace_order_keeps_priority_only_when_shrinking() {
+    let (TestRoles { user1, .. }, mut runner) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let price = 1000u64 << 32;
+    let first = get_order_id(Side::Ask, price, 0);
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::BatchOrders {
+            market_id,
+            cancels: vec![],
+            places: vec![ask(5, price, 1), ask(5, price, 2)],
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::ReplaceOrder {
+            market_id,
+            order_id: first,
+            order: ask(2, price, 1),
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+            assert_eq!(
+                result.events[0],
+                TestRuntimeEvent::Aaob(Event::OrderReduced {
+                    order_id: first,
+                    market_id,
+                    side: Side::Ask,
+                    base_qty: 2,
+                })
+            );
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (order_id, order) = orderbook.asks[0].orders[0];
+
+            assert_eq!(order_id, first);
+            assert_eq!(order.base_qty, 2);
+        }),
+    });
+
+    // growing the order loses its priority
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::ReplaceOrder {
+            market_id,
+            order_id: first,
+            order: ask(8, price, 1),
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (_, order) = orderbook.asks[0].orders[0];
+            assert_eq!(order.client_order_id, 2);
+
+            let (order_id, order) = orderbook.asks[0].orders[1];
+            assert_eq!(order_id, get_order_id(Side::Ask, price, 2));
+            assert_eq!(order.base_qty, 8);
+        }),
+    });
+}
+
+#[test]
+fn replace_order_rejects_zero_price() {
+    let (TestRoles { user1, .. }, mut runner) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let price = 1000u64 << 32;
+    let order_id = get_order_id(Side::Ask, price, 0);
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::BatchOrders {
+            market_id,
+            cancels: vec![],
+            places: vec![ask(5, price, 1)],
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::ReplaceOrder {
+            market_id,
+            order_id,
+            order: ask(5, 0, 1),
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(!result.tx_receipt.is_successful());
+
+            let orderbook = AAOB::<S>::default()
+                .orderbook(market_id, state)
+                .unwrap()
+                .unwrap();
+
+            let (resting_id, order) = orderbook.asks[0].orders[0];
+
+            assert_eq!(resting_id, order_id);
+            assert_eq!(order.base_qty, 5);
+        }),
+    });
+}
+
+#[test]
+fn update_and_close_market() {
+    let (
+        TestRoles {
+            admin,
+            user1,
+            user2,
+        },
+        mut runner,
+    ) = setup();
+
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let price = 1000u64 << 32;
+
+    runner.execute_transaction(TransactionTestCase {
+        input: user1.create_plain_message::<AAOB<S>>(CallMessage::CreateOrder {
+            market_id,
+            side: Side::Bid,
+            max_base_qty: 10,
+            max_quote_qty: 10_000,
+            limit_price: price,
+            order_type: OrderType::PostOnly,
+            self_trade_behavior: SelfTradeHandler::DecrementTake,
+            match_limit: 10,
+            trg_id: 1,
+            client_order_id: 1,
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+
+    // only the market authority or the admin may update the market
+    runner.execute_transaction(TransactionTestCase {
+        input: user2.create_plain_message::<AAOB<S>>(CallMessage::SetMarketStatus {
+            market_id,
+            status: MarketStatus::Paused,
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(!result.tx_receipt.is_successful());
+        }),
+    });
+
+    // the resting order is not a multiple of the new tick size
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<AAOB<S>>(CallMessage::UpdateMarket {
+            market_id,
+            fee_budget: None,
+            min_base_size: None,
+            tick_size: Some(price + 1),
+        }),
+        assert: Box::new(move |result, _state| {
+            assert!(!result.tx_receipt.is_successful());
+        }),
+    });
+
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<AAOB<S>>(CallMessage::CloseMarket {
+            market_name: "AAOB/USD".try_into().unwrap(),
+        }),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+            assert_eq!(result.events.len(), 2);
+            assert_eq!(
+                result.events[0],
+                TestRuntimeEvent::Aaob(Event::Out {
+                    order_id: get_order_id(Side::Bid, price, 0),
+                    market_id,
+                    side: Side::Bid,
+                    trg_id: 1,
+                    base_qty: 10,
+                    reason: OutReason::MarketClosed,
+                })
+            );
+
+            let orderbook = AAOB::<S>::default().orderbook(market_id, state).unwrap();
+
+            assert!(orderbook.is_none());
+        }),
+    });
+}
@@ -0,0 +1,148 @@
+//! Migration of books stored by the slab based orderbook.
+//!
+//! The baseline types below are written out independently of [`LegacyOrderbookState`], so the test
+//! fails if the frozen layout drifts from what markets actually stored.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use sokoban::{Critbit, NodeAllocatorMap};
+use spicenet_aaob::{
+    get_market_id, get_order_id, LegacyOrderbookState, MarketId, Order, PriceLevel, PriceLevelKey,
+    StateType, MAX_SIZE, NUM_NODES,
+};
+use spicenet_shared::Side;
+
+pub type S = sov_test_utils::TestSpec;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, BorshSerialize, BorshDeserialize)]
+struct BaselineOrder {
+    order_id: u64,
+    price: u64,
+    base_qty: u64,
+    trg_id: u64,
+}
+
+unsafe impl Pod for BaselineOrder {}
+unsafe impl Zeroable for BaselineOrder {}
+
+#[derive(BorshSerialize)]
+struct BaselineSlabHeader {
+    account_type: StateType,
+    root_node: u32,
+    total_orders: u64,
+    market_address: MarketId,
+}
+
+#[derive(BorshSerialize)]
+struct BaselineSlab {
+    header: BaselineSlabHeader,
+    tree: Critbit<BaselineOrder, NUM_NODES, MAX_SIZE>,
+}
+
+#[derive(BorshSerialize)]
+struct BaselineOrderbookState {
+    market_id: MarketId,
+    bids: BaselineSlab,
+    asks: BaselineSlab,
+}
+
+/// `(slab key, price, base_qty, trg_id)` of each order
+fn baseline_slab(
+    market_id: MarketId,
+    account_type: StateType,
+    orders: &[(u128, u64, u64, u64)],
+) -> BaselineSlab {
+    let mut tree = Critbit::default();
+    for (index, (key, price, base_qty, trg_id)) in orders.iter().enumerate() {
+        tree.insert(
+            *key,
+            BaselineOrder {
+                order_id: index as u64,
+                price: *price,
+                base_qty: *base_qty,
+                trg_id: *trg_id,
+            },
+        );
+    }
+
+    BaselineSlab {
+        header: BaselineSlabHeader {
+            account_type,
+            root_node: tree.root,
+            total_orders: orders.len() as u64,
+            market_address: market_id,
+        },
+        tree,
+    }
+}
+
+fn order(seq_num: u64, price: u64, base_qty: u64, trg_id: u64) -> Order {
+    Order {
+        order_id: seq_num,
+        price,
+        base_qty,
+        trg_id,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn baseline_book_migrates_with_its_priority() {
+    let market_id = get_market_id::<S>("AAOB/USD");
+    let baseline = BaselineOrderbookState {
+        market_id,
+        bids: baseline_slab(
+            market_id,
+            StateType::Bids,
+            &[(1, 100, 3, 11), (3, 101, 1, 12), (2, 100, 2, 13)],
+        ),
+        asks: baseline_slab(
+            market_id,
+            StateType::Asks,
+            &[(6, 102, 5, 14), (5, 102, 4, 15)],
+        ),
+    };
+    let bytes = borsh::to_vec(&baseline).unwrap();
+
+    let legacy = LegacyOrderbookState::try_from_slice(&bytes).unwrap();
+    assert_eq!(borsh::to_vec(&legacy).unwrap(), bytes);
+
+    let (header, levels) = legacy.migrate();
+    assert_eq!(header.market_id, market_id);
+    assert_eq!(header.seq_num, 5);
+    assert_eq!(header.fill_seq_num, 0);
+
+    // the slab matched the highest bid key and the lowest ask key first
+    let level = |side: Side, price: u64, orders: Vec<Order>| {
+        (
+            PriceLevelKey {
+                market_id,
+                side,
+                price,
+            },
+            PriceLevel {
+                orders: orders
+                    .into_iter()
+                    .map(|order| (get_order_id(side, order.price, order.order_id), order))
+                    .collect(),
+            },
+        )
+    };
+    assert_eq!(
+        levels,
+        vec![
+            level(Side::Bid, 101, vec![order(0, 101, 1, 12)]),
+            level(
+                Side::Bid,
+                100,
+                vec![order(1, 100, 2, 13), order(2, 100, 3, 11)]
+            ),
+            level(
+                Side::Ask,
+                102,
+                vec![order(3, 102, 4, 15), order(4, 102, 5, 14)]
+            ),
+        ]
+    );
+}
@@ -0,0 +1,628 @@
+//! Property based comparison of the matching engine against a reference order book.
+//!
+//! Random sequences of `CreateMarket`, `CreateOrder` and `CancelOrder` are run through the module
+//! and through [`ModelBook`], a naive book that sorts its orders on every lookup. Time is advanced
+//! between transactions so that `GoodTilTime` orders expire while resting. Every transaction must
+//! revert in both or emit the same events, and the resting book must match afterwards.
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use spicenet_aaob::{
+    get_market_id, get_order_id, AAOBConfig, CallMessage, Event, MarketId, OrderId, OrderType,
+    OutReason, SelfTradeHandler, AAOB,
+};
+use spicenet_shared::Side;
+use spicenet_time::constants::SLOT_TIME;
+use spicenet_time::{TimeConfig, TimeModule};
+
+use sov_modules_api::Address;
+use sov_test_utils::runtime::genesis::optimistic::HighLevelOptimisticGenesisConfig;
+use sov_test_utils::runtime::TestRunner;
+use sov_test_utils::{generate_optimistic_runtime, AsUser, MockDaSpec, TestUser, TransactionTestCase};
+
+pub type S = sov_test_utils::TestSpec;
+
+generate_optimistic_runtime!(TestRuntime <= aaob: AAOB<S>, time: TimeModule<S>);
+
+const MARKETS: [&str; 3] = ["M0/USD", "M1/USD", "M2/USD"];
+const USERS: usize = 3;
+const TICK: u64 = 1 << 32;
+
+fn setup() -> (Vec<TestUser<S>>, TestRunner<TestRuntime<S, MockDaSpec>, S>) {
+    let genesis_config =
+        HighLevelOptimisticGenesisConfig::generate().add_accounts_with_default_balance(USERS);
+
+    let users = genesis_config.additional_accounts.clone();
+
+    let aaob_config = AAOBConfig::<S> {
+        initial_markets: vec![],
+        admin: users[0].address(),
+        _phantom: std::marker::PhantomData,
+    };
+
+    let time_config = TimeConfig::<S> {
+        sequencer_authority: Address::<S>::from(*users[0].address().as_bytes()),
+    };
+
+    let genesis_config = GenesisConfig::from_minimal_config(
+        genesis_config.clone().into(),
+        aaob_config,
+        time_config,
+    );
+
+    let runner =
+        TestRunner::new_with_genesis(genesis_config.into_genesis_params(), TestRuntime::default());
+
+    (users, runner)
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    CreateMarket {
+        market: usize,
+        user: usize,
+        min_base_size: u64,
+    },
+    CreateOrder {
+        market: usize,
+        user: usize,
+        side: Side,
+        ticks: u64,
+        max_base_qty: u64,
+        max_quote_qty: u64,
+        order_type: OrderType,
+        self_trade_behavior: SelfTradeHandler,
+        match_limit: u64,
+        trg_id: u64,
+        /// slots until a `GoodTilTime` order expires, counted from the time of the transaction
+        expires_in: u64,
+    },
+    CancelOrder {
+        market: usize,
+        user: usize,
+        side: Side,
+        pick: Index,
+    },
+    /// moves the clock forward by one slot
+    AdvanceTime,
+}
+
+fn side() -> impl Strategy<Value = Side> {
+    prop_oneof![Just(Side::Bid), Just(Side::Ask)]
+}
+
+fn order_type() -> impl Strategy<Value = OrderType> {
+    prop_oneof![
+        Just(OrderType::Limit),
+        Just(OrderType::PostOnly),
+        Just(OrderType::ImmediateOrCancel),
+        Just(OrderType::FillOrKill),
+        // the expiry is set from `expires_in` when the action is run
+        Just(OrderType::GoodTilTime { expiry_timestamp: 0 }),
+    ]
+}
+
+fn self_trade_behavior() -> impl Strategy<Value = SelfTradeHandler> {
+    prop_oneof![
+        Just(SelfTradeHandler::DecrementTake),
+        Just(SelfTradeHandler::CancelProvide),
+        Just(SelfTradeHandler::AbortTx),
+    ]
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    let create_market = (0..MARKETS.len(), 0..USERS, 0..3u64).prop_map(
+        |(market, user, min_base_size)| Action::CreateMarket {
+            market,
+            user,
+            min_base_size,
+        },
+    );
+
+    let create_order = (
+        (0..MARKETS.len(), 0..USERS, side(), 1..8u64),
+        (1..20u64, 0..200u64, order_type(), self_trade_behavior()),
+        (0..5u64, 1..3u64, 0..4u64),
+    )
+        .prop_map(
+            |(
+                (market, user, side, ticks),
+                (max_base_qty, max_quote_qty, order_type, self_trade_behavior),
+                (match_limit, trg_id, expires_in),
+            )| Action::CreateOrder {
+                market,
+                user,
+                side,
+                ticks,
+                max_base_qty,
+                max_quote_qty,
+                order_type,
+                self_trade_behavior,
+                match_limit,
+                trg_id,
+                expires_in,
+            },
+        );
+
+    let cancel_order = (0..MARKETS.len(), 0..USERS, side(), any::<Index>()).prop_map(
+        |(market, user, side, pick)| Action::CancelOrder {
+            market,
+            user,
+            side,
+            pick,
+        },
+    );
+
+    prop_oneof![
+        1 => create_market,
+        6 => create_order,
+        2 => cancel_order,
+        2 => Just(Action::AdvanceTime),
+    ]
+}
+
+/// A resting order of the reference book
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ModelOrder {
+    order_id: OrderId,
+    seq_num: u64,
+    price: u64,
+    base_qty: u64,
+    trg_id: u64,
+    client_order_id: u64,
+    user: usize,
+    expiry_timestamp: u64,
+}
+
+impl ModelOrder {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expiry_timestamp != 0 && self.expiry_timestamp <= now
+    }
+}
+
+/// Reference book of one market.
+///
+/// Orders are kept in arrival order, priority is recomputed by sorting a side on every lookup.
+#[derive(Clone, Debug)]
+struct ModelBook {
+    market_id: MarketId,
+    creator: usize,
+    min_base_size: u64,
+    seq_num: u64,
+    fill_seq_num: u64,
+    bids: Vec<ModelOrder>,
+    asks: Vec<ModelOrder>,
+}
+
+fn fp32_div(a: u64, b_fp32: u64) -> u64 {
+    (((a as u128) << 32) / (b_fp32 as u128)) as u64
+}
+
+fn fp32_mul(a: u64, b_fp32: u64) -> u64 {
+    (((a as u128) * (b_fp32 as u128)) >> 32) as u64
+}
+
+impl ModelBook {
+    fn side_mut(&mut self, side: Side) -> &mut Vec<ModelOrder> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    /// orders of `side` best first: best price, then lowest sequence number
+    fn sorted(&self, side: Side) -> Vec<ModelOrder> {
+        let mut orders = match side {
+            Side::Bid => self.bids.clone(),
+            Side::Ask => self.asks.clone(),
+        };
+        orders.sort_by_key(|order| {
+            let price = match side {
+                Side::Bid => u64::MAX - order.price,
+                Side::Ask => order.price,
+            };
+            (price, order.seq_num)
+        });
+        orders
+    }
+
+    fn remove(&mut self, side: Side, order_id: OrderId) {
+        self.side_mut(side).retain(|order| order.order_id != order_id);
+    }
+
+    /// events of a `CreateOrder` sent at `now`, `None` when the transaction reverts
+    #[allow(clippy::too_many_arguments)]
+    fn create_order(
+        &mut self,
+        user: usize,
+        side: Side,
+        limit_price: u64,
+        max_base_qty: u64,
+        max_quote_qty: u64,
+        order_type: OrderType,
+        self_trade_behavior: SelfTradeHandler,
+        match_limit: u64,
+        trg_id: u64,
+        client_order_id: u64,
+        now: u64,
+    ) -> Option<Vec<Event>> {
+        let market_id = self.market_id;
+        let maker_side = side.opposite();
+        let expiry_timestamp = match order_type {
+            OrderType::GoodTilTime { expiry_timestamp } if expiry_timestamp <= now => return None,
+            OrderType::GoodTilTime { expiry_timestamp } => expiry_timestamp,
+            _ => 0,
+        };
+
+        let mut events = vec![];
+        let seq_num = self.seq_num;
+        self.seq_num += 1;
+        let order_id = get_order_id(side, limit_price, seq_num);
+
+        let mut base_remaining = max_base_qty;
+        let mut quote_remaining = max_quote_qty;
+        // the remainder may only rest when matching stopped on an empty or uncrossed book, not when
+        // it ran out of match budget or quantity
+        let mut stopped_uncrossed = false;
+
+        // every examined maker order uses one unit of the match budget, including pruned ones
+        for _ in 0..match_limit {
+            let best = match self.sorted(maker_side).first().copied() {
+                Some(best) => best,
+                None => {
+                    stopped_uncrossed = true;
+                    break;
+                }
+            };
+
+            if best.is_expired(now) {
+                self.remove(maker_side, best.order_id);
+                events.push(Event::Out {
+                    order_id: best.order_id,
+                    market_id,
+                    side: maker_side,
+                    trg_id: best.trg_id,
+                    base_qty: best.base_qty,
+                    reason: OutReason::Expired,
+                });
+                continue;
+            }
+
+            let crosses = match side {
+                Side::Bid => limit_price >= best.price,
+                Side::Ask => limit_price <= best.price,
+            };
+
+            if !crosses {
+                stopped_uncrossed = true;
+                break;
+            }
+
+            if order_type == OrderType::PostOnly {
+                break;
+            }
+
+            let base_qty = best
+                .base_qty
+                .min(base_remaining)
+                .min(fp32_div(quote_remaining, best.price));
+            let quote_qty = fp32_mul(base_qty, best.price);
+
+            if base_qty == 0 || quote_qty == 0 {
+                break;
+            }
+
+            if trg_id == best.trg_id {
+                match self_trade_behavior {
+                    SelfTradeHandler::DecrementTake => {}
+                    SelfTradeHandler::AbortTx => return None,
+                    SelfTradeHandler::CancelProvide => {
+                        self.remove(maker_side, best.order_id);
+                        events.push(Event::Out {
+                            order_id: best.order_id,
+                            market_id,
+                            side: maker_side,
+                            trg_id: best.trg_id,
+                            base_qty: best.base_qty,
+                            reason: OutReason::SelfTrade,
+                        });
+                        continue;
+                    }
+                }
+            }
+
+            base_remaining -= base_qty;
+            quote_remaining -= quote_qty;
+
+            let maker_remaining = best.base_qty - base_qty;
+            let maker_order_completed =
+                maker_remaining == 0 || maker_remaining < self.min_base_size;
+
+            self.remove(maker_side, best.order_id);
+            if !maker_order_completed {
+                self.side_mut(maker_side).push(ModelOrder {
+                    base_qty: maker_remaining,
+                    ..best
+                });
+            }
+
+            events.push(Event::Fill {
+                market_id,
+                fill_seq_num: self.fill_seq_num,
+                maker_order_id: best.order_id,
+                maker_trg_id: best.trg_id,
+                taker_trg_id: trg_id,
+                taker_side: side,
+                price: best.price,
+                base_qty,
+                quote_qty,
+                maker_order_completed,
+            });
+            self.fill_seq_num += 1;
+
+            if maker_order_completed && maker_remaining > 0 {
+                events.push(Event::Out {
+                    order_id: best.order_id,
+                    market_id,
+                    side: maker_side,
+                    trg_id: best.trg_id,
+                    base_qty: maker_remaining,
+                    reason: OutReason::BelowMinBaseSize,
+                });
+            }
+        }
+
+        let base_to_post = fp32_div(quote_remaining, limit_price).min(base_remaining);
+
+        if order_type == OrderType::FillOrKill && base_to_post > 0 {
+            return None;
+        }
+
+        let may_rest = !matches!(
+            order_type,
+            OrderType::ImmediateOrCancel | OrderType::FillOrKill
+        );
+
+        let posted = if stopped_uncrossed
+            && may_rest
+            && base_to_post > 0
+            && base_to_post >= self.min_base_size
+        {
+            self.side_mut(side).push(ModelOrder {
+                order_id,
+                seq_num,
+                price: limit_price,
+                base_qty: base_to_post,
+                trg_id,
+                client_order_id,
+                user,
+                expiry_timestamp,
+            });
+            base_remaining -= base_to_post;
+            quote_remaining -= fp32_mul(base_to_post, limit_price);
+            base_to_post
+        } else {
+            0
+        };
+
+        events.push(Event::OrderCreated {
+            order_id,
+            market_id,
+            side,
+            total_base_qty: max_base_qty - base_remaining,
+            total_quote_qty: max_quote_qty - quote_remaining,
+            total_base_qty_posted: posted,
+        });
+
+        Some(events)
+    }
+
+    /// resting orders in priority order as `(order_id, price, base_qty, trg_id, client_order_id)`
+    fn resting(&self) -> Vec<(OrderId, u64, u64, u64, u64)> {
+        self.sorted(Side::Bid)
+            .into_iter()
+            .chain(self.sorted(Side::Ask))
+            .map(|o| (o.order_id, o.price, o.base_qty, o.trg_id, o.client_order_id))
+            .collect()
+    }
+}
+
+fn run(actions: Vec<Action>) {
+    let (users, mut runner) = setup();
+    let mut books: Vec<Option<ModelBook>> = vec![None; MARKETS.len()];
+
+    for (client_order_id, action) in actions.into_iter().enumerate() {
+        let client_order_id = client_order_id as u64;
+        let now = runner.query_state(|state| {
+            TimeModule::<S>::default()
+                .get_time(state)
+                .unwrap()
+                .unix_timestamp
+        });
+
+        let (user, market, message, expected) = match action {
+            Action::CreateMarket {
+                market,
+                user,
+                min_base_size,
+            } => {
+                let market_id = get_market_id::<S>(MARKETS[market]);
+                let expected = match books[market] {
+                    Some(_) => None,
+                    None => {
+                        books[market] = Some(ModelBook {
+                            market_id,
+                            creator: user,
+                            min_base_size,
+                            seq_num: 0,
+                            fill_seq_num: 0,
+                            bids: vec![],
+                            asks: vec![],
+                        });
+                        Some(vec![Event::MarketCreated {
+                            market_id,
+                            market_name: MARKETS[market].to_string(),
+                        }])
+                    }
+                };
+                let message = CallMessage::CreateMarket {
+                    market_name: MARKETS[market].try_into().unwrap(),
+                    fee_budget: 0,
+                    min_base_size,
+                    tick_size: TICK,
+                };
+                (user, market, message, expected)
+            }
+            Action::CreateOrder {
+                market,
+                user,
+                side,
+                ticks,
+                max_base_qty,
+                max_quote_qty,
+                order_type,
+                self_trade_behavior,
+                match_limit,
+                trg_id,
+                expires_in,
+            } => {
+                let limit_price = ticks * TICK;
+                let order_type = match order_type {
+                    OrderType::GoodTilTime { .. } => OrderType::GoodTilTime {
+                        expiry_timestamp: now + expires_in * SLOT_TIME,
+                    },
+                    order_type => order_type,
+                };
+                // the model only keeps the book when the transaction goes through
+                let expected = books[market].clone().and_then(|mut book| {
+                    let events = book.create_order(
+                        user,
+                        side,
+                        limit_price,
+                        max_base_qty,
+                        max_quote_qty,
+                        order_type,
+                        self_trade_behavior.clone(),
+                        match_limit,
+                        trg_id,
+                        client_order_id,
+                        now,
+                    )?;
+                    books[market] = Some(book);
+                    Some(events)
+                });
+                let message = CallMessage::CreateOrder {
+                    market_id: get_market_id::<S>(MARKETS[market]),
+                    side,
+                    max_base_qty,
+                    max_quote_qty,
+                    limit_price,
+                    order_type,
+                    self_trade_behavior,
+                    match_limit,
+                    trg_id,
+                    client_order_id,
+                };
+                (user, market, message, expected)
+            }
+            Action::CancelOrder {
+                market,
+                user,
+                side,
+                pick,
+            } => {
+                let market_id = get_market_id::<S>(MARKETS[market]);
+                let mut order_id = get_order_id(side, TICK, u64::MAX);
+                let mut expected = None;
+
+                if let Some(book) = books[market].as_mut() {
+                    let creator = book.creator;
+                    let orders = book.side_mut(side);
+                    if !orders.is_empty() {
+                        let index = pick.index(orders.len());
+                        order_id = orders[index].order_id;
+                        if user == creator || user == orders[index].user {
+                            orders.remove(index);
+                            expected = Some(vec![Event::OrderCancelled {
+                                order_id,
+                                market_id,
+                                side,
+                            }]);
+                        }
+                    }
+                }
+
+                let message = CallMessage::CancelOrder {
+                    market_id,
+                    order_id,
+                    side,
+                };
+                (user, market, message, expected)
+            }
+            Action::AdvanceTime => {
+                runner.execute_transaction(TransactionTestCase {
+                    input: users[0].create_plain_message::<TimeModule<S>>(
+                        spicenet_time::CallMessage::UpdateTimestamp {},
+                    ),
+                    assert: Box::new(move |result, state| {
+                        assert!(result.tx_receipt.is_successful());
+                        assert_eq!(
+                            TimeModule::<S>::default()
+                                .get_time(state)
+                                .unwrap()
+                                .unix_timestamp,
+                            now + SLOT_TIME
+                        );
+                    }),
+                });
+                continue;
+            }
+        };
+
+        let resting = books[market].as_ref().map(ModelBook::resting);
+        let market_id = get_market_id::<S>(MARKETS[market]);
+
+        runner.execute_transaction(TransactionTestCase {
+            input: users[user].create_plain_message::<AAOB<S>>(message),
+            assert: Box::new(move |result, state| {
+                match expected {
+                    Some(events) => {
+                        assert!(result.tx_receipt.is_successful());
+                        assert_eq!(
+                            result.events,
+                            events
+                                .into_iter()
+                                .map(TestRuntimeEvent::Aaob)
+                                .collect::<Vec<_>>()
+                        );
+                    }
+                    None => assert!(!result.tx_receipt.is_successful()),
+                }
+
+                let orderbook = AAOB::<S>::default().orderbook(market_id, state).unwrap();
+                let actual = orderbook.map(|orderbook| {
+                    orderbook
+                        .bids
+                        .iter()
+                        .chain(orderbook.asks.iter())
+                        .flat_map(|level| level.orders.iter())
+                        .map(|(order_id, o)| {
+                            (*order_id, o.price, o.base_qty, o.trg_id, o.client_order_id)
+                        })
+                        .collect::<Vec<_>>()
+                });
+
+                assert_eq!(actual, resting);
+            }),
+        });
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn matching_engine_matches_reference_book(actions in prop::collection::vec(action(), 1..40)) {
+        run(actions);
+    }
+}
@@ -0,0 +1,140 @@
+//! Randomised comparison of the top-of-book model against a naive sorted-vector book.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sokoban::NodeAllocatorMap;
+use spicenet_aaob::{
+    aggregate_levels, get_market_id, get_order_id, BookLevel, Order, OrderId, OrderbookState, TopOfBook,
+};
+use spicenet_shared::Side;
+
+pub type S = sov_test_utils::TestSpec;
+
+const ROUNDS: usize = 200;
+const MAX_OPS: usize = 300;
+
+/// Orders of one side kept as a plain vector, sorted on every read
+#[derive(Default)]
+struct ReferenceSide {
+    orders: Vec<(OrderId, Order)>,
+}
+
+impl ReferenceSide {
+    /// orders in priority order: best price first, oldest first within a price
+    fn sorted(&self, side: Side) -> Vec<(OrderId, Order)> {
+        let mut orders = self.orders.clone();
+        orders.sort_by(|(_, a), (_, b)| match side {
+            Side::Bid => b.price.cmp(&a.price).then(a.order_id.cmp(&b.order_id)),
+            Side::Ask => a.price.cmp(&b.price).then(a.order_id.cmp(&b.order_id)),
+        });
+        orders
+    }
+
+    fn levels(&self, side: Side) -> Vec<BookLevel> {
+        let mut levels: Vec<BookLevel> = vec![];
+
+        for (_, order) in self.sorted(side) {
+            match levels.last_mut() {
+                Some(level) if level.price == order.price => level.base_qty += order.base_qty,
+                _ => levels.push(BookLevel {
+                    price: order.price,
+                    base_qty: order.base_qty,
+                }),
+            }
+        }
+
+        levels
+    }
+}
+
+fn random_order(rng: &mut StdRng, seq_num: u64) -> Order {
+    Order {
+        order_id: seq_num,
+        // a narrow price range so that levels hold several orders
+        price: rng.gen_range(90..110),
+        base_qty: rng.gen_range(1..1_000),
+        trg_id: rng.gen_range(0..4),
+        ..Default::default()
+    }
+}
+
+fn assert_matches_reference(book: &OrderbookState, bids: &ReferenceSide, asks: &ReferenceSide) {
+    let expected = TopOfBook {
+        best_bid: bids.levels(Side::Bid).first().copied(),
+        best_ask: asks.levels(Side::Ask).first().copied(),
+    };
+
+    assert_eq!(book.depth(Side::Bid), bids.levels(Side::Bid));
+    assert_eq!(book.depth(Side::Ask), asks.levels(Side::Ask));
+    assert_eq!(book.top_of_book(), expected);
+    assert_eq!(book.get_spread(), expected.spread());
+
+    // the order matched first is the oldest order at the best price
+    assert_eq!(
+        book.best_order(Side::Bid),
+        bids.sorted(Side::Bid).first().copied()
+    );
+    assert_eq!(
+        book.best_order(Side::Ask),
+        asks.sorted(Side::Ask).first().copied()
+    );
+}
+
+#[test]
+fn slab_top_of_book_matches_reference() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+
+    for _ in 0..ROUNDS {
+        let mut book = OrderbookState::new(get_market_id::<S>("AAOB/USD"));
+        let mut bids = ReferenceSide::default();
+        let mut asks = ReferenceSide::default();
+        let mut seq_num = 0;
+
+        for _ in 0..rng.gen_range(0..MAX_OPS) {
+            let side = if rng.gen_bool(0.5) { Side::Bid } else { Side::Ask };
+            let (slab, reference) = match side {
+                Side::Bid => (&mut book.bids, &mut bids),
+                Side::Ask => (&mut book.asks, &mut asks),
+            };
+
+            if !reference.orders.is_empty() && rng.gen_bool(0.3) {
+                let (order_id, order) = reference
+                    .orders
+                    .swap_remove(rng.gen_range(0..reference.orders.len()));
+                assert_eq!(slab.remove_by_key(order_id), Some(order));
+            } else {
+                let order = random_order(&mut rng, seq_num);
+                let order_id = get_order_id(side, order.price, seq_num);
+                seq_num += 1;
+
+                slab.tree.insert(order_id, order);
+                reference.orders.push((order_id, order));
+            }
+
+            assert_matches_reference(&book, &bids, &asks);
+        }
+    }
+}
+
+#[test]
+fn aggregate_levels_matches_reference() {
+    let mut rng = StdRng::seed_from_u64(0xb00c);
+
+    for _ in 0..ROUNDS {
+        for side in [Side::Bid, Side::Ask] {
+            let reference = ReferenceSide {
+                orders: (0..rng.gen_range(0..MAX_OPS as u64))
+                    .map(|seq_num| {
+                        let order = random_order(&mut rng, seq_num);
+                        (get_order_id(side, order.price, seq_num), order)
+                    })
+                    .collect(),
+            };
+
+            assert_eq!(
+                aggregate_levels(side, reference.orders.iter().map(|(_, order)| order)),
+                reference.levels(side)
+            );
+        }
+    }
+}
@@ -58,6 +58,13 @@ pub enum CallMessage<S: Spec> {
         signature: Vec<u8>,
         nonce: u64,
     },
+    /// Links the sender of the transaction to `wallet_type`, the signature has to be produced by `wallet_type`
+    /// over the register signer message with the next signer nonce of the wallet
+    RegisterSigner {
+        wallet_type: WalletType,
+        signature: Vec<u8>,
+        nonce: u64,
+    },
 }
 
 impl<S: Spec> Capsule<S> {
@@ -201,7 +208,7 @@ impl<S: Spec> Capsule<S> {
     ) -> Result<()> {
         match self.smart_wallets.get(&address, state)? {
             Some(mut existing_wallet) => {
-                let message = WalletState::revoke_wallet_message(&address, &wallet_type, nonce)?;
+                let message = WalletState::revoke_wallet_message(&address, &wallet_type, nonce);
                 if !approving_wallet.verify_signature(signature.as_ref(), &message)? {
                     bail!("Invalid signature");
                 }
@@ -221,4 +228,44 @@ impl<S: Spec> Capsule<S> {
 
         Ok(())
     }
+
+    pub fn register_signer(
+        &self,
+        wallet_type: WalletType,
+        signature: Vec<u8>,
+        nonce: u64,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let signer = context.sender().clone();
+
+        match self.wallets.get(&wallet_type, state)? {
+            Some(wallet) => {
+                if wallet.revoked {
+                    bail!("Wallet {} is revoked", wallet_type);
+                }
+
+                let expected_nonce = self
+                    .signer_nonces
+                    .get(&wallet_type, state)?
+                    .unwrap_or_default();
+                if nonce != expected_nonce {
+                    bail!("Invalid nonce {}, expected {}", nonce, expected_nonce);
+                }
+
+                let message = WalletState::register_signer_message(&signer, &wallet_type, nonce);
+                if !wallet.verify_signature(signature.as_ref(), &message)? {
+                    bail!("Invalid signature");
+                }
+
+                self.signers.set(&signer, &wallet_type, state)?;
+                self.signer_nonces.set(&wallet_type, &(nonce + 1), state)?;
+
+                self.emit_event(state, Event::SignerRegistered { wallet, signer });
+            }
+            None => bail!("Wallet not found"),
+        }
+
+        Ok(())
+    }
 }
@@ -12,7 +12,7 @@ use crate::state::wallet::Wallet;
     Clone
 )]
 #[serde(
-    bound = "Address<S>: serde::Serialize + serde::de::DeserializeOwned, Wallet<S>: serde::Serialize + serde::de::DeserializeOwned"
+    bound = "Address<S>: serde::Serialize + serde::de::DeserializeOwned, Wallet<S>: serde::Serialize + serde::de::DeserializeOwned, S::Address: serde::Serialize + serde::de::DeserializeOwned"
 )]
 pub enum Event<S: Spec> {
     WalletCreated {
@@ -27,4 +27,8 @@ pub enum Event<S: Spec> {
         wallet: Wallet<S>,
         address: Address<S>,
     },
+    SignerRegistered {
+        wallet: Wallet<S>,
+        signer: S::Address,
+    },
 }
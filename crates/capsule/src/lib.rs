@@ -32,6 +32,14 @@ pub struct Capsule<S: Spec> {
 
     #[state]
     wallets: StateMap<WalletType, Wallet<S>>,
+
+    #[state]
+    /// Rollup addresses that sign transactions on behalf of a wallet
+    signers: StateMap<S::Address, WalletType>,
+
+    #[state]
+    /// Nonce the next signer registration of a wallet has to be signed with
+    signer_nonces: StateMap<WalletType, u64>,
 }
 
 impl<S: Spec> Module for Capsule<S> {
@@ -129,6 +137,11 @@ impl<S: Spec> Module for Capsule<S> {
                 context,
                 state,
             ),
+            CallMessage::RegisterSigner {
+                wallet_type,
+                signature,
+                nonce,
+            } => self.register_signer(wallet_type, signature, nonce, context, state),
         }?;
 
         Ok(call_result)
@@ -1,8 +1,9 @@
 use std::fmt::Display;
 
 // use crate::state::pubkey::Pubkey;
-use anyhow::{anyhow, bail, Result};
+use anyhow::{bail, Result};
 use borsh::BorshDeserialize;
+use sov_modules_api::macros::config_value;
 use sov_modules_api::{Address, Spec, StateAccessor, StateMap};
 use spicenet_shared::addresses::TrgId;
 use std::fmt::Formatter;
@@ -186,7 +187,7 @@ impl<S: Spec> WalletState<S> {
             smart_wallet: address.clone(),
         };
 
-        let message = Self::create_wallet_message(&wallet.wallet_type, nonce)?;
+        let message = Self::create_wallet_message(&wallet.wallet_type, nonce);
 
         if !wallet.verify_signature(signature, &message)? {
             bail!("Invalid signature");
@@ -209,7 +210,7 @@ impl<S: Spec> WalletState<S> {
         signature: &[u8],
         nonce: u64,
     ) -> Result<Wallet<S>> {
-        let message = Self::add_admin_wallet_message(address, &admin_wallet, nonce)?;
+        let message = Self::add_admin_wallet_message(address, &admin_wallet, nonce);
         if !approving_wallet.verify_signature(signature, &message)? {
             bail!("Invalid signature");
         }
@@ -242,7 +243,7 @@ impl<S: Spec> WalletState<S> {
             &scopes,
             expiration_timestamp,
             nonce,
-        )?;
+        );
         if !approving_wallet.verify_signature(signature, &message)? {
             bail!("Invalid signature");
         }
@@ -270,7 +271,7 @@ impl<S: Spec> WalletState<S> {
         signature: &[u8],
         nonce: u64,
     ) -> Result<Wallet<S>> {
-        let message = Self::add_recovery_wallet_message(address, &recovery_wallet, nonce)?;
+        let message = Self::add_recovery_wallet_message(address, &recovery_wallet, nonce);
         if !approving_wallet.verify_signature(signature, &message)? {
             bail!("Invalid signature");
         }
@@ -328,16 +329,16 @@ impl<S: Spec> Wallet<S> {
     pub fn verify_signature(&self, signature: &[u8], message: &[u8]) -> Result<bool> {
         match self.wallet_type {
             WalletType::Solana { address } => {
-                Ok(verify_ed25519_signature(signature, message, &address).is_ok())
+                Ok(verify_ed25519_signature(&address, message, signature).is_ok())
             }
             WalletType::Aptos { address } => {
-                Ok(verify_ed25519_signature(signature, message, &address).is_ok())
+                Ok(verify_ed25519_signature(&address, message, signature).is_ok())
             }
             WalletType::Ethereum { address } => {
-                Ok(verify_ethereum_signature(signature, message, &address).is_ok())
+                Ok(verify_ethereum_signature(&address, message, signature).is_ok())
             }
             WalletType::Sui { address } => {
-                Ok(verify_ed25519_signature(signature, message, &address).is_ok())
+                Ok(verify_ed25519_signature(&address, message, signature).is_ok())
             }
         }
     }
@@ -349,26 +350,23 @@ impl<S: Spec> Wallet<S> {
 /// Add ephemeral wallet - "I am adding an ephemeral wallet {type:address} to {address} with scopes {scopes} and expiration timestamp {expiration_timestamp}. Nonce: {nonce}"
 /// Add recovery wallet - "I am adding a recovery wallet {type:address} to {address}. Nonce: {nonce}"
 /// Revoke wallet - "I am revoking the wallet {type:address} of {address}. Nonce: {nonce}"
+/// Register signer - "I am registering {signer} as a signer for the wallet {type:address} on chain {chain_id}. Nonce: {nonce}"
 
 impl<S: Spec> WalletState<S> {
-    pub fn create_wallet_message(master_wallet: &WalletType, nonce: u64) -> Result<[u8; 1024]> {
+    pub fn create_wallet_message(master_wallet: &WalletType, nonce: u64) -> Vec<u8> {
         format!(
             "I am creating a new smart wallet and adding an admin wallet {master_wallet}. Nonce: {nonce}"
         )
-            .as_bytes()
-            .try_into()
-            .map_err(|err| anyhow!("Failed to convert message to bytes, {:?}", err))
+            .into_bytes()
     }
 
     pub fn add_admin_wallet_message(
         address: &Address<S>,
         admin_wallet: &WalletType,
         nonce: u64,
-    ) -> Result<[u8; 1024]> {
+    ) -> Vec<u8> {
         format!("I am adding an admin wallet {admin_wallet} to {address}. Nonce: {nonce}")
-            .as_bytes()
-            .try_into()
-            .map_err(|_| anyhow!("Failed to convert message to bytes"))
+            .into_bytes()
     }
 
     pub fn add_ephemeral_wallet_message(
@@ -377,34 +375,42 @@ impl<S: Spec> WalletState<S> {
         scopes: &ScopeVec,
         expiration_timestamp: u64,
         nonce: u64,
-    ) -> Result<[u8; 1024]> {
+    ) -> Vec<u8> {
         format!(
             "I am adding an ephemeral wallet {ephemeral_wallet} to {address} with scopes {scopes} and expiration timestamp {expiration_timestamp}. Nonce: {nonce}"
         )
-            .as_bytes()
-            .try_into()
-            .map_err(|_| anyhow!("Failed to convert message to bytes"))
+            .into_bytes()
     }
 
     pub fn add_recovery_wallet_message(
         address: &Address<S>,
         recovery_wallet: &WalletType,
         nonce: u64,
-    ) -> Result<[u8; 1024]> {
+    ) -> Vec<u8> {
         format!("I am adding a recovery wallet {recovery_wallet} to {address}. Nonce: {nonce}")
-            .as_bytes()
-            .try_into()
-            .map_err(|_| anyhow!("Failed to convert message to bytes"))
+            .into_bytes()
     }
 
     pub fn revoke_wallet_message(
         address: &Address<S>,
         wallet_type: &WalletType,
         nonce: u64,
-    ) -> Result<[u8; 1024]> {
+    ) -> Vec<u8> {
         format!("I am revoking the wallet {wallet_type} of {address}. Nonce: {nonce}")
-            .as_bytes()
-            .try_into()
-            .map_err(|_| anyhow!("Failed to convert message to bytes"))
+            .into_bytes()
+    }
+
+    /// The chain id keeps the signature from being replayed on another rollup, the nonce from being
+    /// replayed on this one
+    pub fn register_signer_message(
+        signer: &S::Address,
+        wallet_type: &WalletType,
+        nonce: u64,
+    ) -> Vec<u8> {
+        let chain_id: u64 = config_value!("CHAIN_ID");
+        format!(
+            "I am registering {signer} as a signer for the wallet {wallet_type} on chain {chain_id}. Nonce: {nonce}"
+        )
+        .into_bytes()
     }
 }
@@ -1,7 +1,10 @@
 use anyhow::bail;
-use sov_modules_api::{Spec, StateAccessor};
+use sov_modules_api::{Address, Spec, StateAccessor};
 
-use crate::{state::wallet::WalletType, Capsule};
+use crate::{
+    state::wallet::{Scope, WalletType},
+    Capsule,
+};
 
 impl<S: Spec> Capsule<S> {
     pub fn check_duplicate_wallet(
@@ -27,3 +30,34 @@ impl<S: Spec> Capsule<S> {
         Ok(())
     }
 }
+
+impl<S: Spec> Capsule<S> {
+    /// Returns the smart wallet `signer` trades for, `None` if `signer` is not registered with any wallet.
+    ///
+    /// Fails if the registered wallet is revoked, expired at `timestamp` or lacks [`Scope::Trading`].
+    pub fn get_trading_address(
+        &self,
+        signer: &S::Address,
+        timestamp: u64,
+        state: &mut impl StateAccessor,
+    ) -> anyhow::Result<Option<Address<S>>> {
+        let wallet_type = match self.signers.get(signer, state)? {
+            Some(wallet_type) => wallet_type,
+            None => return Ok(None),
+        };
+
+        match self.wallets.get(&wallet_type, state)? {
+            Some(wallet) => {
+                if !wallet.is_active(timestamp) {
+                    bail!("Wallet {} is not active", wallet_type);
+                }
+                if !wallet.is_allowed(Scope::Trading) {
+                    bail!("Wallet {} is not allowed to trade", wallet_type);
+                }
+
+                Ok(Some(wallet.smart_wallet))
+            }
+            None => bail!("Wallet not found"),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use capsule::call::CallMessage;
+use capsule::state::wallet::{WalletState, WalletType};
+use capsule::{Capsule, CapsuleConfig};
+use ed25519_dalek::{Signer, SigningKey};
+use sov_modules_api::Spec;
+use sov_test_utils::runtime::genesis::optimistic::HighLevelOptimisticGenesisConfig;
+use sov_test_utils::runtime::TestRunner;
+use sov_test_utils::{
+    generate_optimistic_runtime, AsUser, MockDaSpec, TestUser, TransactionTestCase,
+};
+
+pub type S = sov_test_utils::TestSpec;
+
+generate_optimistic_runtime!(TestCapsuleModuleRuntime <= capsule: Capsule<S>);
+
+type Runner = TestRunner<TestCapsuleModuleRuntime<S, MockDaSpec>, S>;
+
+pub struct TestRoles<S: Spec> {
+    pub admin: TestUser<S>,
+    pub signer1: TestUser<S>,
+    pub signer2: TestUser<S>,
+}
+
+fn setup() -> (TestRoles<S>, Runner) {
+    let genesis_config =
+        HighLevelOptimisticGenesisConfig::generate().add_accounts_with_default_balance(3);
+
+    let admin = genesis_config.additional_accounts[0].clone();
+    let signer1 = genesis_config.additional_accounts[1].clone();
+    let signer2 = genesis_config.additional_accounts[2].clone();
+
+    let genesis_config =
+        GenesisConfig::from_minimal_config(genesis_config.clone().into(), CapsuleConfig {});
+
+    let runner = TestRunner::new_with_genesis(
+        genesis_config.into_genesis_params(),
+        TestCapsuleModuleRuntime::default(),
+    );
+
+    (
+        TestRoles {
+            admin,
+            signer1,
+            signer2,
+        },
+        runner,
+    )
+}
+
+fn solana_wallet(key: &SigningKey) -> WalletType {
+    WalletType::Solana {
+        address: key.verifying_key().to_bytes(),
+    }
+}
+
+/// Sends `msg` to the capsule as `user`, `successful` is the expected outcome of the transaction
+fn execute(runner: &mut Runner, user: &TestUser<S>, msg: CallMessage<S>, successful: bool) {
+    runner.execute_transaction(TransactionTestCase {
+        input: user.create_plain_message::<Capsule<S>>(msg),
+        assert: Box::new(move |result, _| {
+            assert_eq!(result.tx_receipt.is_successful(), successful);
+        }),
+    });
+}
+
+/// `RegisterSigner` for the sender `signer`, signed by `key` over `nonce`
+fn register_signer(
+    key: &SigningKey,
+    signer: &TestUser<S>,
+    wallet_type: &WalletType,
+    nonce: u64,
+) -> CallMessage<S> {
+    let message = WalletState::<S>::register_signer_message(&signer.address(), wallet_type, nonce);
+
+    CallMessage::RegisterSigner {
+        wallet_type: wallet_type.clone(),
+        signature: key.sign(&message).to_bytes().to_vec(),
+        nonce,
+    }
+}
+
+fn trading_address_is_set(runner: &mut Runner, signer: &TestUser<S>) -> bool {
+    let signer = signer.address();
+    runner.query_state(|state| {
+        Capsule::<S>::default()
+            .get_trading_address(&signer, 0, state)
+            .unwrap()
+            .is_some()
+    })
+}
+
+#[test]
+fn register_signer() {
+    let (
+        TestRoles {
+            admin,
+            signer1,
+            signer2,
+        },
+        mut runner,
+    ) = setup();
+
+    let key = SigningKey::from_bytes(&[1; 32]);
+    let wallet_type = solana_wallet(&key);
+
+    let message = WalletState::<S>::create_wallet_message(&wallet_type, 0);
+    execute(
+        &mut runner,
+        &admin,
+        CallMessage::CreateWallet {
+            wallet_type: wallet_type.clone(),
+            signature: key.sign(&message).to_bytes().to_vec(),
+            nonce: 0,
+        },
+        true,
+    );
+
+    // signed by another key, or for another signer
+    let other_key = SigningKey::from_bytes(&[2; 32]);
+    execute(
+        &mut runner,
+        &signer1,
+        register_signer(&other_key, &signer1, &wallet_type, 0),
+        false,
+    );
+    execute(
+        &mut runner,
+        &signer1,
+        register_signer(&key, &signer2, &wallet_type, 0),
+        false,
+    );
+    // signed over a nonce ahead of the wallet
+    execute(
+        &mut runner,
+        &signer1,
+        register_signer(&key, &signer1, &wallet_type, 1),
+        false,
+    );
+    assert!(!trading_address_is_set(&mut runner, &signer1));
+
+    let registration = register_signer(&key, &signer1, &wallet_type, 0);
+    execute(&mut runner, &signer1, registration.clone(), true);
+    assert!(trading_address_is_set(&mut runner, &signer1));
+
+    // the same signature can not be used twice
+    execute(&mut runner, &signer1, registration, false);
+
+    execute(
+        &mut runner,
+        &signer2,
+        register_signer(&key, &signer2, &wallet_type, 1),
+        true,
+    );
+    assert!(trading_address_is_set(&mut runner, &signer2));
+}
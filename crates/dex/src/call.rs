@@ -1,11 +1,32 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use borsh::BorshDeserialize;
 use borsh::BorshSerialize;
-use sov_modules_api::{CallResponse, Spec};
+use sov_bank::{Coins, IntoPayable, TokenId};
+use sov_modules_api::{
+    CallResponse, Context, EventEmitter, ModuleInfo, SafeString, Spec, TxState,
+};
 
+use crate::event::Event;
+use crate::settlement::{
+    close_expired_position, realize_social_losses, remove_resting_order, settle_fill,
+    settle_funding, to_lots, to_ticks, transfer_positions,
+};
 use crate::state::*;
+use crate::utils::{get_mpg_id, get_product_id, get_trg_book_id, get_trg_id};
 use crate::Dex;
-use spicenet_shared::TrgId;
+use spicenet_aaob::{get_side_from_order_id, MarketCallerAuthority, OrderId, SelfTradeHandler};
+use spicenet_shared::{
+    ComboLeg, ComboProduct, DexError, Fractional, MPGId, MPGType, MarketId, MarketProductGroup,
+    MpgAuthority, OutrightProduct, PriceEwma, Product, ProductId, ProductMetadata, ProductStatus,
+    ProductTrait, TraderRiskGroup, TrgId, UtilError, MAX_LEGS, NAME_LEN, NO_ASK_PRICE,
+    NO_BID_PRICE, ZERO_FRAC,
+};
+use spicenet_shared::risk::{
+    ActionStatus, HealthOutput, HealthStatus, OrderRiskInfo, RiskEngineOpCodes, RiskInfo,
+};
+
+/// AAOB markets backing dex products quote prices directly in ticks of the product
+const PRODUCT_MARKET_TICK_SIZE: u64 = 1 << 32;
 
 #[cfg_attr(
     feature = "native",
@@ -16,44 +37,1686 @@ use spicenet_shared::TrgId;
 )]
 #[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
 pub enum CallMessage<S: Spec> {
-    InitTrg {},
-    UpdateProductFunding {
+    /// Creates the sender's trader risk group in `mpg_id`
+    InitializeTrg {
+        mpg_id: MPGId,
+    },
+    /// Moves `amount` of the MPG collateral token from the sender into the dex vault
+    DepositFunds {
+        trg_id: TrgId<S>,
+        amount: u64,
+    },
+    /// Moves `amount` of the MPG collateral token out of the dex vault, the TRG has to stay healthy
+    WithdrawFunds {
+        trg_id: TrgId<S>,
         amount: u64,
+    },
+    /// Places an order of the TRG on the AAOB market of `product_id`, fills settle immediately
+    NewOrder {
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        order: OrderParams,
+    },
+    /// Cancels a resting order of the TRG
+    CancelOrder {
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        order_id: OrderId,
+    },
+    /// Adds `amount` per unit of position to the cumulative funding of an outright product, by
+    /// the MPG authority
+    UpdateProductFunding {
+        mpg_id: MPGId,
+        product_id: ProductId,
+        amount: Fractional,
         new_product_status: ProductStatus,
-    }, // New variant for funding
+    },
+    /// Moves the funding accrued by the positions of the TRG into its cash balance, by anyone
+    SettleFunding {
+        trg_id: TrgId<S>,
+    },
+    /// Creates a market product group, the sender becomes its authority
+    CreateMarketProductGroup {
+        name: SafeString,
+        decimals: u64,
+        collateral_token_id: TokenId,
+        ewma_windows: [u64; 4],
+        max_maker_fee_bps: i16,
+        min_maker_fee_bps: i16,
+        max_taker_fee_bps: i16,
+        min_taker_fee_bps: i16,
+    },
+    /// Lists an outright product backed by a new AAOB market
+    InitializeMarketProduct {
+        mpg_id: MPGId,
+        params: OutrightProductParams,
+    },
+    /// Lists a combo of outright products of the MPG backed by a new AAOB market
+    InitializeCombo {
+        mpg_id: MPGId,
+        params: ComboProductParams,
+    },
+    /// Removes an expired and dormant product, closing its AAOB market
+    RemoveMarketProduct {
+        mpg_id: MPGId,
+        product_id: ProductId,
+    },
+    UpdateMpgAuthority {
+        mpg_id: MPGId,
+        new_authority: S::Address,
+    },
+    /// Stops everything but cancels and withdrawals on the MPG while `on`, by the MPG authority
+    SetKillSwitch {
+        mpg_id: MPGId,
+        on: bool,
+    },
+    /// Restricts trading, deposits and withdrawals to the MPG authority while `on`
+    SetAdminMode {
+        mpg_id: MPGId,
+        on: bool,
+    },
+    SetCollateralLimits {
+        mpg_id: MPGId,
+        deposit_limit: Fractional,
+        withdraw_limit: Fractional,
+    },
+    /// Moves the fees collected by the MPG to its authority
+    SweepFees {
+        mpg_id: MPGId,
+    },
+    /// Replaces the volume fee tiers of the MPG, an empty table charges the MPG maximum fees
+    SetFeeTiers {
+        mpg_id: MPGId,
+        tiers: Vec<FeeTier>,
+    },
+    /// Lets other TRGs of the MPG name this TRG as their referrer
+    RegisterReferrer {
+        trg_id: TrgId<S>,
+    },
+    /// Names the referrer of the TRG, it can only be set once
+    SetReferrer {
+        trg_id: TrgId<S>,
+        referrer: TrgId<S>,
+    },
+    /// Moves the referral fees earned by the TRG into its cash balance
+    ClaimReferralFees {
+        trg_id: TrgId<S>,
+    },
+    /// Proposes a block trade of `creator` with `counterparty`, nothing moves until it is signed
+    InitializePrintTrade {
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+        products: Vec<PrintTradeProduct>,
+        operator: TrgId<S>,
+        operator_creator_fee_proportion: Fractional,
+        operator_counterparty_fee_proportion: Fractional,
+    },
+    /// Accepts a print trade as its counterparty, the terms have to match the proposed ones
+    SignPrintTrade {
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+        products: Vec<PrintTradeProduct>,
+        operator_creator_fee_proportion: Fractional,
+        operator_counterparty_fee_proportion: Fractional,
+    },
+    /// Withdraws a print trade that is not signed yet, by its creator or its counterparty
+    CancelPrintTrade {
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+    },
+    /// Cancels up to `num_orders` resting orders of an expiring product, by anyone
+    ClearExpiredOrderbook {
+        mpg_id: MPGId,
+        product_id: ProductId,
+        num_orders: u16,
+    },
+    /// Settles the position of the TRG in an expired outright at its final payoff, by anyone
+    ClearExpiredPosition {
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+    },
+    /// Risk engine call, the dex resolves the MPGs and TRGs it refers to
+    Risk {
+        msg: spicenet_risk::CallMessage<S>,
+    },
+    /// Liquidates `liquidatee`, its positions and cash are taken over by the sender's `liquidator`
+    TransferFullPosition {
+        liquidatee: TrgId<S>,
+        liquidator: TrgId<S>,
+    },
 }
+
 impl<S: Spec> Dex<S> {
-    pub(crate) fn initialize_trg(&self) -> Result<CallResponse> {
+    pub(crate) fn initialize_trg(
+        &self,
+        mpg_id: MPGId,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mpg = match self.market_product_groups.get(&mpg_id, state)? {
+            Some(mpg) => mpg,
+            None => bail!(DexError::MarketProductGroupDoesNotExist),
+        };
+        check_kill_switch(&mpg)?;
+
+        let owner = context.sender();
+        let trg_id = get_trg_id::<S>(owner, &mpg_id);
+        if self.trader_risk_groups.get(&trg_id, state)?.is_some() {
+            bail!(UtilError::AccountAlreadyInitialized);
+        }
+
+        // AAOB orders only carry a u64 trg id, two TRGs must never share one
+        let book_id = get_trg_book_id::<S>(&trg_id);
+        if self.trg_book_ids.get(&book_id, state)?.is_some() {
+            bail!(UtilError::AccountAlreadyInitialized);
+        }
+
+        let trg = TraderRiskGroup::new(trg_id.clone(), mpg_id, owner);
+        self.risk_engine
+            .initialize_variance_cache(trg.clone(), context, state)?;
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+        self.trg_book_ids.set(&book_id, &trg_id, state)?;
+
+        self.emit_event(
+            state,
+            Event::TrgCreated {
+                trg_id,
+                mpg_id,
+                owner: owner.clone(),
+                cash_balance: trg.cash_balance,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn deposit_funds(
+        &self,
+        trg_id: TrgId<S>,
+        amount: u64,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut trg = self.load_trg_as_owner(&trg_id, context, state)?;
+        let mpg = self.load_mpg(&trg.market_product_group, state)?;
+        check_kill_switch(&mpg)?;
+        check_admin_mode(&mpg, context)?;
+        let collateral = self.load_collateral_config(&mpg.id, state)?;
+
+        let cash = to_cash(amount, mpg.decimals)?;
+        if cash <= ZERO_FRAC {
+            bail!(DexError::FundsError);
+        }
+
+        trg.total_deposited = trg.total_deposited.checked_add(cash)?;
+        if trg.total_deposited > collateral.deposit_limit {
+            bail!(DexError::DepositLimitExceeded);
+        }
+        trg.cash_balance = trg.cash_balance.checked_add(cash)?;
+
+        self.bank.transfer_from(
+            context.sender(),
+            self.id().to_payable(),
+            Coins {
+                amount,
+                token_id: collateral.token_id,
+            },
+            state,
+        )?;
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+
+        self.emit_event(
+            state,
+            Event::FundsDeposited {
+                trg_id,
+                amount,
+                cash_balance: trg.cash_balance,
+            },
+        );
+
         Ok(CallResponse::default())
     }
 
-    pub(crate) fn update_product_funding(
+    pub(crate) fn withdraw_funds(
         &self,
+        trg_id: TrgId<S>,
         amount: u64,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut trg = self.load_trg_as_owner(&trg_id, context, state)?;
+        let mut mpg = self.load_mpg(&trg.market_product_group, state)?;
+        // withdrawals stay open when the MPG is killed so traders can get their funds out
+        check_admin_mode(&mpg, context)?;
+        let collateral = self.load_collateral_config(&mpg.id, state)?;
+        realize_social_losses(&mpg, &mut trg)?;
+        settle_funding(&mpg, &mut trg)?;
+
+        let cash = to_cash(amount, mpg.decimals)?;
+        if cash <= ZERO_FRAC || cash > trg.cash_balance {
+            bail!(DexError::InvalidWithdrawalAmount);
+        }
+
+        trg.total_withdrawn = trg.total_withdrawn.checked_add(cash)?;
+        if trg.total_withdrawn > collateral.withdraw_limit {
+            bail!(DexError::WithdrawLimitExceeded);
+        }
+        trg.cash_balance = trg.cash_balance.checked_sub(cash)?;
+
+        // the risk engine sees the TRG as it will be after the withdrawal
+        self.check_health(
+            &mut mpg,
+            &trg,
+            RiskInfo {
+                op_type: RiskEngineOpCodes::CheckWithdrawHealth,
+                ..Default::default()
+            },
+            context,
+            state,
+        )?;
+
+        self.bank.transfer_from(
+            self.id().to_payable(),
+            context.sender(),
+            Coins {
+                amount,
+                token_id: collateral.token_id,
+            },
+            state,
+        )?;
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+        self.market_product_groups.set(&mpg.id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::FundsWithdrawn {
+                trg_id,
+                amount,
+                cash_balance: trg.cash_balance,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn new_order(
+        &self,
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        order: OrderParams,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut trg = self.load_trg_as_owner(&trg_id, context, state)?;
+        let mut mpg = self.load_mpg(&trg.market_product_group, state)?;
+        check_kill_switch(&mpg)?;
+        check_admin_mode(&mpg, context)?;
+
+        let (product_index, product) = match mpg.find_product_index(&product_id) {
+            Some((product_index, product)) => (product_index, product.clone()),
+            None => bail!(DexError::MissingMarketProduct),
+        };
+        if order.match_limit == 0 {
+            bail!(DexError::MatchLimitZero);
+        }
+        if order.self_trade_behavior == SelfTradeHandler::DecrementTake {
+            bail!(DexError::SelfTradeBehaviorDecrementTakeIsDisallowed);
+        }
+        realize_social_losses(&mpg, &mut trg)?;
+        settle_funding(&mpg, &mut trg)?;
+
+        let summary = self.aaob.place_delegated_order(
+            product.orderbook_id,
+            spicenet_aaob::NewOrder {
+                side: order.side,
+                max_base_qty: to_lots(&product, order.max_base_qty)?,
+                max_quote_qty: u64::MAX,
+                // the market tick is one fp32 unit, a tick count past u32 does not fit
+                limit_price: to_ticks(&product, order.limit_price)?
+                    .checked_mul(PRODUCT_MARKET_TICK_SIZE)
+                    .ok_or(DexError::InvalidOrderPrice)?,
+                order_type: order.order_type,
+                self_trade_behavior: order.self_trade_behavior,
+                match_limit: order.match_limit,
+                trg_id: get_trg_book_id::<S>(&trg_id),
+                client_order_id: order.client_order_id,
+            },
+            state,
+        )?;
+        let fees = self.settle_order(
+            &mut mpg,
+            product_index,
+            &mut trg,
+            order.side,
+            order.client_order_id,
+            &summary,
+            state,
+        )?;
+
+        // the risk engine sees the TRG with its fills and its posted order
+        let mut risk_info = RiskInfo {
+            op_type: RiskEngineOpCodes::NewOrder,
+            num_orders: 1,
+            ..Default::default()
+        };
+        risk_info.orders[0] = OrderRiskInfo {
+            side: order.side,
+            order_price: order.limit_price,
+            is_combo: matches!(product, Product::Combo { .. }),
+            idx: product_index,
+        };
+        self.check_health(&mut mpg, &trg, risk_info, context, state)?;
+
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+        self.market_product_groups.set(&mpg.id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::OrderPlaced {
+                trg_id,
+                product_id,
+                order_id: summary.order_id,
+                client_order_id: order.client_order_id,
+                filled_base_qty: summary.total_base_qty - summary.posted_base_qty,
+                posted_base_qty: summary.posted_base_qty,
+                fees,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn cancel_order(
+        &self,
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        order_id: OrderId,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut trg = self.load_trg_as_owner(&trg_id, context, state)?;
+        let mpg = self.load_mpg(&trg.market_product_group, state)?;
+
+        // cancels stay possible on expiring products so TRGs can clean up their orders
+        let (product_index, product) = mpg.find_product_index_among_all(&product_id)?;
+        if !trg.open_orders.has_open_order(product_index, order_id) {
+            bail!(DexError::InvalidOrderID);
+        }
+
+        let side = get_side_from_order_id(order_id);
+        self.aaob
+            .cancel_delegated_order(product.orderbook_id, order_id, side, state)?;
+        remove_resting_order(&mut trg, product_index, order_id, side, product.base_decimals)?;
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+
+        self.emit_event(
+            state,
+            Event::OrderCancelled {
+                trg_id,
+                product_id,
+                order_id,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn update_product_funding_as_authority(
+        &self,
+        mpg_id: MPGId,
+        product_id: ProductId,
+        amount: Fractional,
         new_product_status: ProductStatus,
-        market_product_group: MarketProductGroup<S>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        self.load_mpg_as_authority(&mpg_id, context, state)?;
+        self.update_product_funding(mpg_id, &product_id, amount, new_product_status, state)?;
+
+        Ok(CallResponse::default())
+    }
+
+    /// Adds `amount` per unit of position to the cumulative funding of an outright product and
+    /// moves it to `new_product_status`. Positions realize the funding through their snapshot on
+    /// their next settlement.
+    ///
+    /// Only reachable from the MPG authority call and from the instruments module settling the
+    /// derivative backing the product.
+    pub fn update_product_funding(
+        &self,
+        mpg_id: MPGId,
         product_id: &ProductId,
+        amount: Fractional,
+        new_product_status: ProductStatus,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let mut mpg = self.load_mpg(&mpg_id, state)?;
+        check_kill_switch(&mpg)?;
+        if new_product_status == ProductStatus::Uninitialized {
+            bail!(DexError::InvalidProductStatusInUpdateFunding);
+        }
+
+        let (product_index, _) = mpg.find_product_index_among_all(product_id)?;
+        let cash_decimals = mpg.decimals;
+        let outright = mpg.active_products[product_index].try_to_outright_mut()?;
+        outright.apply_new_funding(amount, cash_decimals)?;
+        outright.product_status = new_product_status;
+        let cumulative_funding_per_share = outright.cumulative_funding_per_share;
+
+        mpg.sequence_number += 1;
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::ProductFundingUpdated {
+                mpg_id,
+                product_id: *product_id,
+                amount,
+                cumulative_funding_per_share,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub(crate) fn settle_trg_funding(
+        &self,
+        trg_id: TrgId<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut trg = self.load_trg(&trg_id, state)?;
+        let mpg = self.load_mpg(&trg.market_product_group, state)?;
+
+        realize_social_losses(&mpg, &mut trg)?;
+        settle_funding(&mpg, &mut trg)?;
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+
+        self.emit_event(
+            state,
+            Event::FundingSettled {
+                trg_id,
+                cash_balance: trg.cash_balance,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn create_market_product_group(
+        &self,
+        name: &str,
+        decimals: u64,
+        collateral_token_id: TokenId,
+        ewma_windows: [u64; 4],
+        max_maker_fee_bps: i16,
+        min_maker_fee_bps: i16,
+        max_taker_fee_bps: i16,
+        min_taker_fee_bps: i16,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mpg_id = get_mpg_id::<S>(name);
+        if self.market_product_groups.get(&mpg_id, state)?.is_some() {
+            bail!(DexError::MarketProductGroupAlreadyExists);
+        }
+
+        if min_maker_fee_bps > max_maker_fee_bps || min_taker_fee_bps > max_taker_fee_bps {
+            bail!("min fee bps must not be greater than max fee bps");
+        }
+        // maker rebates are paid out of taker fees, collected fees must never go negative
+        if (min_maker_fee_bps as i32 + min_taker_fee_bps as i32) < 0 {
+            bail!("maker rebates must not exceed taker fees");
+        }
+
+        let mpg = MarketProductGroup {
+            id: mpg_id,
+            mpg_type: MPGType::MPG,
+            mpg_authority: MpgAuthority::new(context.sender()),
+            name: to_name_bytes(name)?,
+            collected_fees: ZERO_FRAC,
+            decimals,
+            active_flags_products: Default::default(),
+            ewma_windows,
+            active_products: Default::default(),
+            max_maker_fee_bps,
+            min_maker_fee_bps,
+            max_taker_fee_bps,
+            min_taker_fee_bps,
+            sequence_number: 0,
+            is_mpg_killed: false,
+            in_admin_mode: false,
+            risk_output_register: Default::default(),
+            risk_engine_module_id: self.risk_engine.id().clone(),
+            fee_model_module_id: self.id().clone(),
+        };
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+        // deposits and withdrawals are uncapped until the authority sets limits
+        self.collateral_configs.set(
+            &mpg_id,
+            &CollateralConfig {
+                token_id: collateral_token_id,
+                deposit_limit: Fractional::new(i64::MAX, 0),
+                withdraw_limit: Fractional::new(i64::MAX, 0),
+            },
+            state,
+        )?;
+
+        self.emit_event(
+            state,
+            Event::MarketProductGroupCreated {
+                mpg_id,
+                name: name.to_string(),
+                mpg_authority: context.sender().clone(),
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn initialize_market_product(
+        &self,
+        mpg_id: MPGId,
+        params: OutrightProductParams,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+        check_kill_switch(&mpg)?;
+
+        let name = String::from(params.name);
+        let product_id = get_product_id::<S>(&mpg_id, &name);
+        let orderbook_id =
+            self.register_product_market(&mpg_id, &name, params.min_base_size, context, state)?;
+        let slot = self.time_module.get_slot(state)?.slot;
+
+        let outright = OutrightProduct {
+            metadata: new_product_metadata(
+                product_id,
+                to_name_bytes(&name)?,
+                params.price_index,
+                orderbook_id,
+                params.tick_size,
+                params.base_decimals,
+                params.price_offset,
+                slot,
+            ),
+            num_tracking_risk_states: 0,
+            product_status: ProductStatus::Initialized,
+            dust: ZERO_FRAC,
+            cumulative_funding_per_share: ZERO_FRAC,
+            cumulative_social_loss_per_share: ZERO_FRAC,
+            open_long_interest: ZERO_FRAC,
+            open_short_interest: ZERO_FRAC,
+            mark_price_qualifying_cum_value: params.mark_price_qualifying_cum_value,
+            mark_price_max_qualifying_width: params.mark_price_max_qualifying_width,
+            padding: [0; 10],
+        };
+
+        let product_index = mpg.add_product(Product::Outright {
+            outright_product: outright,
+        })?;
+        mpg.sequence_number += 1;
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::MarketProductInitialized {
+                mpg_id,
+                product_id,
+                product_index,
+                orderbook_id,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn initialize_combo(
+        &self,
+        mpg_id: MPGId,
+        params: ComboProductParams,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+        check_kill_switch(&mpg)?;
+
+        if params.legs.len() < 2 || params.legs.len() > MAX_LEGS {
+            bail!(DexError::InvalidComboLegs);
+        }
+
+        let mut legs = Vec::with_capacity(params.legs.len());
+        for (i, leg) in params.legs.iter().enumerate() {
+            if leg.ratio == 0 || params.legs[..i].iter().any(|l| l.product_id == leg.product_id) {
+                bail!(DexError::InvalidComboLegs);
+            }
+
+            // `find_product_index` only looks at active products, so expiring outrights are refused
+            let product_index = match mpg.find_product_index(&leg.product_id) {
+                Some((idx, product)) if product.try_to_outright().is_ok() => idx,
+                _ => bail!(DexError::InvalidComboLegs),
+            };
+
+            legs.push(ComboLeg {
+                product_index,
+                product_key: leg.product_id,
+                ratio: leg.ratio,
+            });
+        }
+        // unused legs are zeroed out, only the first `num_legs` are read
+        let legs_array: [ComboLeg; MAX_LEGS] = std::array::from_fn(|i| match legs.get(i) {
+            Some(leg) => leg.clone(),
+            None => ComboLeg {
+                product_index: 0,
+                product_key: [0u8; 32].into(),
+                ratio: 0,
+            },
+        });
+
+        let name = String::from(params.name);
+        let product_id = get_product_id::<S>(&mpg_id, &name);
+        let orderbook_id =
+            self.register_product_market(&mpg_id, &name, params.min_base_size, context, state)?;
+        let slot = self.time_module.get_slot(state)?.slot;
+
+        let combo = ComboProduct {
+            metadata: new_product_metadata(
+                product_id,
+                to_name_bytes(&name)?,
+                // combos are priced off their legs, they have no index of their own
+                0,
+                orderbook_id,
+                params.tick_size,
+                params.base_decimals,
+                params.price_offset,
+                slot,
+            ),
+            num_legs: params.legs.len(),
+            legs_array,
+        };
+
+        let product_index = mpg.add_product(Product::Combo {
+            combo_product: combo,
+        })?;
+        mpg.mpg_type = MPGType::MPGWithCombos;
+        mpg.sequence_number += 1;
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::ComboInitialized {
+                mpg_id,
+                product_id,
+                product_index,
+                orderbook_id,
+                legs: params
+                    .legs
+                    .iter()
+                    .map(|leg| (leg.product_id, leg.ratio))
+                    .collect(),
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn remove_market_product(
+        &self,
+        mpg_id: MPGId,
+        product_id: ProductId,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+        check_kill_switch(&mpg)?;
+
+        let (_, product) = mpg.find_product_index_among_all(&product_id)?;
+        if let Ok(outright) = product.try_to_outright() {
+            if !outright.is_expired() {
+                bail!(DexError::ContractIsNotExpired);
+            }
+            if !outright.is_removable() {
+                bail!(DexError::ContractHasNonZeroOpenInterestOrRiskStateAccounts);
+            }
+            // combos trading this outright have to be removed first
+            if has_combos(&mpg, product_id) {
+                bail!(DexError::CombosNotRemoved);
+            }
+        }
+
+        self.delist_product(&mut mpg, product_id, state)?;
+
+        Ok(CallResponse::default())
+    }
+
+    /// Cancels up to `num_orders` resting orders of an expiring product, by anyone. An expiring
+    /// outright becomes expired once its book is empty, then positions can be cleared.
+    pub(crate) fn clear_expired_orderbook(
+        &self,
+        mpg_id: MPGId,
+        product_id: ProductId,
+        num_orders: u16,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg(&mpg_id, state)?;
+
+        let (product_index, product) = mpg.find_product_index_among_all(&product_id)?;
+        if product.get_product_status(&mpg) != ProductStatus::Expiring {
+            bail!(DexError::ContractIsNotExpiring);
+        }
+        if num_orders == 0 {
+            bail!(DexError::NoOp);
+        }
+
+        let (num_cancelled, is_book_empty) =
+            self.clear_orderbook(&mpg, product_index, num_orders as usize, state)?;
+
+        if is_book_empty {
+            if let Ok(outright) = mpg.active_products[product_index].try_to_outright_mut() {
+                outright.product_status = ProductStatus::Expired;
+                mpg.sequence_number += 1;
+                self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+                self.emit_event(state, Event::ProductExpired { mpg_id, product_id });
+            }
+        }
+
+        self.emit_event(
+            state,
+            Event::ExpiredOrderbookCleared {
+                mpg_id,
+                product_id,
+                num_cancelled: num_cancelled as u64,
+                is_book_empty,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    /// Settles the position of the TRG in an expired outright at the final payoff and closes it,
+    /// by anyone. The product is removed once no position tracks it anymore.
+    pub(crate) fn clear_expired_position(
+        &self,
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut trg = self.load_trg(&trg_id, state)?;
+        let mut mpg = self.load_mpg(&trg.market_product_group, state)?;
+
+        let (product_index, product) = mpg.find_product_index_among_all(&product_id)?;
+        let outright = match product.try_to_outright() {
+            Ok(outright) => outright,
+            Err(_) => bail!(DexError::ProductNotOutright),
+        };
+        if !outright.is_expired() {
+            bail!(DexError::ContractIsNotExpired);
+        }
+
+        let position = close_expired_position(&mut mpg, &mut trg, product_index)?;
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+
+        let is_removable = mpg.active_products[product_index]
+            .try_to_outright()?
+            .is_removable();
+        if is_removable && !has_combos(&mpg, product_id) {
+            self.delist_product(&mut mpg, product_id, state)?;
+        } else {
+            self.market_product_groups.set(&mpg.id, &mpg, state)?;
+        }
+
+        self.emit_event(
+            state,
+            Event::ExpiredPositionCleared {
+                trg_id,
+                product_id,
+                position,
+                cash_balance: trg.cash_balance,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn update_mpg_authority(
+        &self,
+        mpg_id: MPGId,
+        new_authority: S::Address,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+
+        let old_authority = mpg.mpg_authority.get_address().clone();
+        mpg.mpg_authority = MpgAuthority::new(&new_authority);
+        mpg.sequence_number += 1;
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::MpgAuthorityUpdated {
+                mpg_id,
+                old_authority,
+                new_authority,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    /// Turns the kill switch of the MPG on or off, only cancels and withdrawals are possible while
+    /// it is on
+    pub(crate) fn set_kill_switch(
+        &self,
+        mpg_id: MPGId,
+        on: bool,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+
+        mpg.is_mpg_killed = on;
+        mpg.sequence_number += 1;
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+        self.emit_event(state, Event::KillSwitchSet { mpg_id, on });
+
+        Ok(CallResponse::default())
+    }
+
+    /// Turns admin mode of the MPG on or off, only the authority may trade, deposit and withdraw
+    /// while it is on
+    pub(crate) fn set_admin_mode(
+        &self,
+        mpg_id: MPGId,
+        on: bool,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+
+        mpg.in_admin_mode = on;
+        mpg.sequence_number += 1;
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+        self.emit_event(state, Event::AdminModeSet { mpg_id, on });
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn set_collateral_limits(
+        &self,
+        mpg_id: MPGId,
+        deposit_limit: Fractional,
+        withdraw_limit: Fractional,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
     ) -> Result<CallResponse> {
-        assert!(
-            market_product_group.is_initialized(),
-            UtilError::AccountUninitialized
+        self.load_mpg_as_authority(&mpg_id, context, state)?;
+        let mut collateral = self.load_collateral_config(&mpg_id, state)?;
+
+        if deposit_limit < ZERO_FRAC {
+            bail!(DexError::NegativeDepositLimit);
+        }
+        if withdraw_limit < ZERO_FRAC {
+            bail!(DexError::NegativeWithdrawLimit);
+        }
+
+        collateral.deposit_limit = deposit_limit;
+        collateral.withdraw_limit = withdraw_limit;
+        self.collateral_configs.set(&mpg_id, &collateral, state)?;
+
+        self.emit_event(
+            state,
+            Event::CollateralLimitsUpdated {
+                mpg_id,
+                deposit_limit,
+                withdraw_limit,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn sweep_fees(
+        &self,
+        mpg_id: MPGId,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+        let collateral = self.load_collateral_config(&mpg_id, state)?;
+
+        // fees are swept in whole base units of the collateral token, the remainder stays
+        let swept = mpg.collected_fees.round_unchecked(mpg.decimals as u32)?;
+        if swept <= ZERO_FRAC {
+            bail!(DexError::FundsError);
+        }
+        let amount = swept.m as u64;
+
+        mpg.collected_fees = mpg.collected_fees.checked_sub(swept)?;
+        self.bank.transfer_from(
+            self.id().to_payable(),
+            context.sender(),
+            Coins {
+                amount,
+                token_id: collateral.token_id,
+            },
+            state,
         )?;
-        assert!(
-            !market_product_group.is_killed,
-            DexError::MarketProductGroupKillswitchIsOn
+        self.market_product_groups.set(&mpg_id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::FeesSwept {
+                mpg_id,
+                amount,
+                collected_fees: mpg.collected_fees,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    fn load_mpg(
+        &self,
+        mpg_id: &MPGId,
+        state: &mut impl TxState<S>,
+    ) -> Result<MarketProductGroup<S>> {
+        match self.market_product_groups.get(mpg_id, state)? {
+            Some(mpg) => Ok(mpg),
+            None => bail!(DexError::MarketProductGroupDoesNotExist),
+        }
+    }
+
+    fn load_collateral_config(
+        &self,
+        mpg_id: &MPGId,
+        state: &mut impl TxState<S>,
+    ) -> Result<CollateralConfig> {
+        match self.collateral_configs.get(mpg_id, state)? {
+            Some(collateral) => Ok(collateral),
+            None => bail!(DexError::MarketProductGroupDoesNotExist),
+        }
+    }
+
+    /// Replaces the volume based fee tiers of the MPG.
+    ///
+    /// Tiers must start at zero volume, be sorted by strictly increasing volume and stay within
+    /// the fee bounds of the MPG.
+    pub(crate) fn set_fee_tiers(
+        &self,
+        mpg_id: MPGId,
+        tiers: Vec<FeeTier>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mpg = self.load_mpg_as_authority(&mpg_id, context, state)?;
+
+        if tiers.len() > MAX_FEE_TIERS {
+            bail!(DexError::InvalidFeeSchedule);
+        }
+        if let Some(first) = tiers.first() {
+            if first.min_volume != ZERO_FRAC {
+                bail!(DexError::InvalidFeeSchedule);
+            }
+        }
+        if tiers.windows(2).any(|w| w[0].min_volume >= w[1].min_volume) {
+            bail!(DexError::InvalidFeeSchedule);
+        }
+        let within_bounds = tiers.iter().all(|tier| {
+            (mpg.min_maker_fee_bps as i32..=mpg.max_maker_fee_bps as i32)
+                .contains(&tier.maker_fee_bps)
+                && (mpg.min_taker_fee_bps as i32..=mpg.max_taker_fee_bps as i32)
+                    .contains(&tier.taker_fee_bps)
+        });
+        if !within_bounds {
+            bail!(DexError::InvalidFeeSchedule);
+        }
+
+        let schedule = FeeSchedule { tiers };
+        self.fee_schedules.set(&mpg_id, &schedule, state)?;
+
+        self.emit_event(
+            state,
+            Event::FeeTiersUpdated {
+                mpg_id,
+                tiers: schedule
+                    .tiers
+                    .iter()
+                    .map(|tier| (tier.min_volume, tier.maker_fee_bps, tier.taker_fee_bps))
+                    .collect(),
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn register_referrer(
+        &self,
+        trg_id: TrgId<S>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        self.load_trg_as_owner(&trg_id, context, state)?;
+        if self.referrers.get(&trg_id, state)?.is_some() {
+            bail!(DexError::ReferrerAlreadyRegistered);
+        }
+
+        self.referrers.set(&trg_id, &0, state)?;
+
+        self.emit_event(state, Event::ReferrerRegistered { trg_id });
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn set_referrer(
+        &self,
+        trg_id: TrgId<S>,
+        referrer: TrgId<S>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let trg = self.load_trg_as_owner(&trg_id, context, state)?;
+        if self.trg_referrers.get(&trg_id, state)?.is_some() {
+            bail!(DexError::ReferrerAlreadySet);
+        }
+        if referrer == trg_id {
+            bail!("a trader risk group can not refer itself");
+        }
+
+        let referred_count = match self.referrers.get(&referrer, state)? {
+            Some(referred_count) => referred_count,
+            None => bail!(DexError::ReferrerNotRegistered),
+        };
+        if self.load_trg(&referrer, state)?.market_product_group != trg.market_product_group {
+            bail!(DexError::ReferrerNotRegistered);
+        }
+
+        self.trg_referrers.set(&trg_id, &referrer, state)?;
+        self.referrers.set(&referrer, &(referred_count + 1), state)?;
+
+        self.emit_event(state, Event::ReferrerSet { trg_id, referrer });
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn claim_referral_fees(
+        &self,
+        trg_id: TrgId<S>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut trg = self.load_trg_as_owner(&trg_id, context, state)?;
+
+        // referral fees were kept out of collected_fees, the collateral is already in the vault
+        let amount = trg.referral_fees;
+        if amount <= ZERO_FRAC {
+            bail!(DexError::FundsError);
+        }
+        trg.cash_balance = trg.cash_balance.checked_add(amount)?;
+        trg.referral_fees = ZERO_FRAC;
+        self.trader_risk_groups.set(&trg_id, &trg, state)?;
+
+        self.emit_event(
+            state,
+            Event::ReferralFeesClaimed {
+                trg_id,
+                amount,
+                cash_balance: trg.cash_balance,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn initialize_print_trade(
+        &self,
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+        products: Vec<PrintTradeProduct>,
+        operator: TrgId<S>,
+        operator_creator_fee_proportion: Fractional,
+        operator_counterparty_fee_proportion: Fractional,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let creator_trg = self.load_trg_as_owner(&creator, context, state)?;
+        let mpg = self.load_mpg(&creator_trg.market_product_group, state)?;
+        check_kill_switch(&mpg)?;
+        check_admin_mode(&mpg, context)?;
+
+        if creator == counterparty
+            || self.load_trg(&counterparty, state)?.market_product_group != mpg.id
+            || self.load_trg(&operator, state)?.market_product_group != mpg.id
+        {
+            bail!(DexError::InvalidPrintTradeParticipants);
+        }
+
+        if products.is_empty() || products.len() > PrintTrade::<S>::MAX_PRODUCTS_PER_TRADE {
+            bail!(DexError::PrintTradeInvalidNumProducts);
+        }
+        for product in &products {
+            let (_, listed) = match mpg.find_product_index(&product.product_key) {
+                Some(found) => found,
+                None => bail!(DexError::MissingMarketProduct),
+            };
+            if to_lots(listed, product.size).is_err() {
+                bail!(DexError::IncorrectPrintTradeSize);
+            }
+        }
+
+        let one = Fractional::new(1, 0);
+        let valid_proportion = |p: Fractional| p >= ZERO_FRAC && p <= one;
+        if !valid_proportion(operator_creator_fee_proportion)
+            || !valid_proportion(operator_counterparty_fee_proportion)
+        {
+            bail!(DexError::InvalidPrintTradeOperatorFees);
+        }
+
+        let key = (creator.clone(), counterparty.clone(), seed);
+        if self.print_trades.get(&key, state)?.is_some() {
+            bail!(UtilError::AccountAlreadyInitialized);
+        }
+
+        let print_trade = PrintTrade {
+            creator: creator.clone(),
+            counterparty: counterparty.clone(),
+            seed,
+            market_product_group: mpg.id,
+            products,
+            operator,
+            operator_creator_fee_proportion,
+            operator_counterparty_fee_proportion,
+            is_signed: false,
+            is_cancelled: CancelStatus::Active,
+        };
+        self.print_trades.set(&key, &print_trade, state)?;
+
+        self.emit_event(
+            state,
+            Event::PrintTradeInitialized {
+                creator,
+                counterparty,
+                seed,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn sign_print_trade(
+        &self,
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+        products: Vec<PrintTradeProduct>,
+        operator_creator_fee_proportion: Fractional,
+        operator_counterparty_fee_proportion: Fractional,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut counterparty_trg = self.load_trg_as_owner(&counterparty, context, state)?;
+        let key = (creator.clone(), counterparty.clone(), seed);
+        let mut print_trade = self.load_active_print_trade(&key, state)?;
+
+        // the counterparty signs the terms it has seen, the creator can not swap them
+        if products.len() != print_trade.products.len() {
+            bail!(DexError::PrintTradeInvalidNumProducts);
+        }
+        for (signed, proposed) in products.iter().zip(&print_trade.products) {
+            if signed.product_key != proposed.product_key {
+                bail!(DexError::PrintTradeProductMismatch);
+            }
+            if signed.size != proposed.size {
+                bail!(DexError::IncorrectPrintTradeSize);
+            }
+            if signed.price != proposed.price {
+                bail!(DexError::IncorrectPrintTradePrice);
+            }
+            if signed.side != proposed.side {
+                bail!(DexError::IncorrectPrintTradeSide);
+            }
+        }
+        if operator_creator_fee_proportion != print_trade.operator_creator_fee_proportion {
+            bail!(DexError::IncorrectPrintTradeOperatorCreatorFees);
+        }
+        if operator_counterparty_fee_proportion
+            != print_trade.operator_counterparty_fee_proportion
+        {
+            bail!(DexError::IncorrectPrintTradeOperatorCounterpartyFees);
+        }
+
+        let mut creator_trg = self.load_trg(&creator, state)?;
+        let mut mpg = self.load_mpg(&print_trade.market_product_group, state)?;
+        check_kill_switch(&mpg)?;
+        check_admin_mode(&mpg, context)?;
+        realize_social_losses(&mpg, &mut creator_trg)?;
+        realize_social_losses(&mpg, &mut counterparty_trg)?;
+
+        let mut notional = ZERO_FRAC;
+        for product in &print_trade.products {
+            let product_index = match mpg.find_product_index(&product.product_key) {
+                Some((product_index, _)) => product_index,
+                None => bail!(DexError::MissingMarketProduct),
+            };
+            let leg_notional = settle_fill(
+                &mut mpg,
+                product_index,
+                &mut creator_trg,
+                &mut counterparty_trg,
+                product.side,
+                product.size,
+                product.price,
+            )?;
+            notional = notional.checked_add(leg_notional.abs())?;
+        }
+
+        let creator_fee = notional.checked_mul(operator_creator_fee_proportion)?;
+        let counterparty_fee = notional.checked_mul(operator_counterparty_fee_proportion)?;
+        let operator_fees = creator_fee.checked_add(counterparty_fee)?;
+        creator_trg.cash_balance = creator_trg.cash_balance.checked_sub(creator_fee)?;
+        counterparty_trg.cash_balance =
+            counterparty_trg.cash_balance.checked_sub(counterparty_fee)?;
+        if print_trade.operator == creator {
+            creator_trg.cash_balance = creator_trg.cash_balance.checked_add(operator_fees)?;
+        } else if print_trade.operator == counterparty {
+            counterparty_trg.cash_balance =
+                counterparty_trg.cash_balance.checked_add(operator_fees)?;
+        } else {
+            let mut operator_trg = self.load_trg(&print_trade.operator, state)?;
+            operator_trg.cash_balance = operator_trg.cash_balance.checked_add(operator_fees)?;
+            self.trader_risk_groups
+                .set(&print_trade.operator, &operator_trg, state)?;
+        }
+
+        let risk_info = RiskInfo {
+            op_type: RiskEngineOpCodes::SignPT,
+            ..Default::default()
+        };
+        self.check_health(&mut mpg, &creator_trg, risk_info.clone(), context, state)?;
+        self.check_health(&mut mpg, &counterparty_trg, risk_info, context, state)?;
+
+        print_trade.is_signed = true;
+        self.trader_risk_groups.set(&creator, &creator_trg, state)?;
+        self.trader_risk_groups
+            .set(&counterparty, &counterparty_trg, state)?;
+        self.market_product_groups.set(&mpg.id, &mpg, state)?;
+        self.print_trades.set(&key, &print_trade, state)?;
+
+        self.emit_event(
+            state,
+            Event::PrintTradeSigned {
+                creator,
+                counterparty,
+                seed,
+                notional,
+                operator_fees,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn cancel_print_trade(
+        &self,
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let key = (creator.clone(), counterparty.clone(), seed);
+        let mut print_trade = self.load_active_print_trade(&key, state)?;
+
+        let sender = context.sender().as_ref();
+        print_trade.is_cancelled = if self.load_trg(&creator, state)?.owner.as_ref() == sender {
+            CancelStatus::CreatorCancelled
+        } else if self.load_trg(&counterparty, state)?.owner.as_ref() == sender {
+            CancelStatus::CounterpartyCancelled
+        } else {
+            bail!(DexError::InvalidTrgOwner);
+        };
+        self.print_trades.set(&key, &print_trade, state)?;
+
+        self.emit_event(
+            state,
+            Event::PrintTradeCancelled {
+                creator,
+                counterparty,
+                seed,
+                by_creator: print_trade.is_cancelled == CancelStatus::CreatorCancelled,
+            },
+        );
+
+        Ok(CallResponse::default())
+    }
+
+    pub(crate) fn transfer_full_position(
+        &self,
+        liquidatee: TrgId<S>,
+        liquidator: TrgId<S>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<CallResponse> {
+        let mut liquidator_trg = self.load_trg_as_owner(&liquidator, context, state)?;
+        let mut liquidatee_trg = self.load_trg(&liquidatee, state)?;
+        if liquidator == liquidatee
+            || liquidator_trg.market_product_group != liquidatee_trg.market_product_group
+        {
+            bail!(DexError::InvalidLiquidator);
+        }
+
+        let mut mpg = self.load_mpg(&liquidatee_trg.market_product_group, state)?;
+        check_kill_switch(&mpg)?;
+        check_admin_mode(&mpg, context)?;
+
+        // the risk engine values the liquidatee without open orders and with its losses realized
+        self.cancel_all_orders(&mpg, &mut liquidatee_trg, state)?;
+        realize_social_losses(&mpg, &mut liquidatee_trg)?;
+        realize_social_losses(&mpg, &mut liquidator_trg)?;
+        settle_funding(&mpg, &mut liquidatee_trg)?;
+        settle_funding(&mpg, &mut liquidator_trg)?;
+
+        self.risk_engine.validate_account_liquidation(
+            &mut mpg,
+            liquidatee_trg.clone(),
+            RiskInfo {
+                op_type: RiskEngineOpCodes::PositionTransfer,
+                ..Default::default()
+            },
+            context,
+            state,
         )?;
-        assert!(
-            new_product_status != ProductStatus::Uninitialized,
-            DexError::InvalidProductStatusInUpdateFunding
+        let liquidation_status = match &mpg.risk_output_register.health_output {
+            HealthOutput::Liquidatable { liquidation_status }
+                if liquidation_status.health_status == HealthStatus::Liquidatable
+                    && liquidation_status.action_result == ActionStatus::Approved =>
+            {
+                liquidation_status.clone()
+            }
+            _ => bail!(DexError::NotLiquidatable),
+        };
+        let liquidation_price = liquidation_status.liquidation_price;
+        if liquidation_price < ZERO_FRAC
+            && -liquidation_price != liquidation_status.total_social_loss
+        {
+            bail!(DexError::NegativeLiquidationPriceDoesNotEqualSocialLoss);
+        }
+
+        // the liquidator pays the liquidation price for the whole portfolio, a negative price is
+        // paid to the liquidator out of social loss
+        transfer_positions(&mut mpg, &mut liquidatee_trg, &mut liquidator_trg)?;
+        liquidator_trg.cash_balance = liquidator_trg
+            .cash_balance
+            .checked_add(liquidatee_trg.cash_balance)?
+            .checked_sub(liquidation_price)?;
+        liquidatee_trg.cash_balance = liquidation_price.max(ZERO_FRAC);
+
+        let cash_decimals = mpg.decimals;
+        for social_loss in &liquidation_status.social_losses {
+            if !social_loss.is_social_loss() {
+                continue;
+            }
+            if social_loss.amount < ZERO_FRAC {
+                bail!(DexError::NegativeSocialLoss);
+            }
+
+            // realized lazily by every position through its social loss snapshot
+            let outright = mpg.active_products[social_loss.product_idx].try_to_outright_mut()?;
+            let total_shares = outright
+                .open_long_interest
+                .checked_add(outright.open_short_interest)?;
+            outright.apply_social_loss(social_loss.amount, total_shares, cash_decimals)?;
+        }
+
+        self.check_health(
+            &mut mpg,
+            &liquidator_trg,
+            RiskInfo {
+                op_type: RiskEngineOpCodes::PositionTransfer,
+                ..Default::default()
+            },
+            context,
+            state,
         )?;
 
-        let (idx, _) = market_product_group.find_product_index(product_id)?;
-        let cash_decimals = market_product_group.decimals;
-        let product = market_product_group.active_products[idx].try_to_outright_mut()?;
-        product.apply_new_funding(amount, cash_decimals)?;
+        self.trader_risk_groups
+            .set(&liquidatee, &liquidatee_trg, state)?;
+        self.trader_risk_groups
+            .set(&liquidator, &liquidator_trg, state)?;
+        self.market_product_groups.set(&mpg.id, &mpg, state)?;
+
+        self.emit_event(
+            state,
+            Event::PositionTransferred {
+                liquidatee,
+                liquidator,
+                liquidation_price,
+                total_social_loss: liquidation_status.total_social_loss,
+            },
+        );
 
-        product.product_status = new_product_status;
-        market_product_group.sequence_number += 1;
         Ok(CallResponse::default())
     }
+
+    fn load_active_print_trade(
+        &self,
+        key: &PrintTradeKey<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<PrintTrade<S>> {
+        let print_trade = match self.print_trades.get(key, state)? {
+            Some(print_trade) => print_trade,
+            None => bail!(UtilError::AccountUninitialized),
+        };
+        if !print_trade.is_active() {
+            bail!(DexError::PrintTradeNotActive);
+        }
+
+        Ok(print_trade)
+    }
+
+    /// Runs the risk engine on `trg`, the action has to be approved
+    fn check_health(
+        &self,
+        mpg: &mut MarketProductGroup<S>,
+        trg: &TraderRiskGroup<S>,
+        risk_info: RiskInfo,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        self.risk_engine
+            .validate_account_health(mpg, trg.clone(), risk_info, context, state)?;
+        match &mpg.risk_output_register.health_output {
+            HealthOutput::Healthy { health_status }
+                if health_status.action_status == ActionStatus::Approved =>
+            {
+                Ok(())
+            }
+            _ => bail!(DexError::InvalidAccountHealthError),
+        }
+    }
+
+    pub(crate) fn load_trg(
+        &self,
+        trg_id: &TrgId<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<TraderRiskGroup<S>> {
+        match self.trader_risk_groups.get(trg_id, state)? {
+            Some(trg) => Ok(trg),
+            None => bail!(DexError::TraderRiskGroupDoesNotExist),
+        }
+    }
+
+    /// Loads the TRG, making sure the sender owns it
+    fn load_trg_as_owner(
+        &self,
+        trg_id: &TrgId<S>,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<TraderRiskGroup<S>> {
+        let trg = self.load_trg(trg_id, state)?;
+
+        if context.sender().as_ref() != trg.owner.as_ref() {
+            bail!(DexError::InvalidTrgOwner);
+        }
+
+        Ok(trg)
+    }
+
+    /// Loads the MPG, making sure the sender is its authority. The kill switch is left to the
+    /// caller, the authority must still be able to manage a killed MPG
+    fn load_mpg_as_authority(
+        &self,
+        mpg_id: &MPGId,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<MarketProductGroup<S>> {
+        let mpg = self.load_mpg(mpg_id, state)?;
+
+        if context.sender().as_ref() != mpg.mpg_authority.as_ref() {
+            bail!(DexError::InvalidMpgAuthority);
+        }
+
+        Ok(mpg)
+    }
+
+    /// Removes a product from the MPG and closes its AAOB market, the caller checks that the
+    /// product can be removed
+    fn delist_product(
+        &self,
+        mpg: &mut MarketProductGroup<S>,
+        product_id: ProductId,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let (_, product) = mpg.find_product_index_among_all(&product_id)?;
+        let orderbook_id = *product.get_orderbook_id();
+
+        mpg.remove_product(&product_id)?;
+        if mpg.active_combo_products().next().is_none() {
+            mpg.mpg_type = MPGType::MPG;
+        }
+        mpg.sequence_number += 1;
+        self.market_product_groups.set(&mpg.id, mpg, state)?;
+
+        self.aaob.close_market_unchecked(orderbook_id, state)?;
+
+        self.emit_event(
+            state,
+            Event::MarketProductRemoved {
+                mpg_id: mpg.id,
+                product_id,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Opens the AAOB market backing a product. The market is delegated to the dex module so
+    /// orders can only be placed and cancelled through the dex.
+    fn register_product_market(
+        &self,
+        mpg_id: &MPGId,
+        product_name: &str,
+        min_base_size: u64,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<MarketId> {
+        let dex_address: S::Address = self.id().to_address();
+        self.aaob.register_market(
+            &format!("{}:{}", mpg_id, product_name),
+            MarketCallerAuthority::new(&dex_address),
+            true,
+            0,
+            min_base_size,
+            PRODUCT_MARKET_TICK_SIZE,
+            context,
+            state,
+        )
+    }
+}
+
+/// Converts an amount of collateral token base units to cash in MPG decimals
+fn to_cash(amount: u64, decimals: u64) -> Result<Fractional> {
+    match i64::try_from(amount) {
+        Ok(m) => Ok(Fractional::new(m, decimals)),
+        Err(_) => bail!(DexError::FundsError),
+    }
+}
+
+/// Fails when the kill switch of the MPG is on
+fn check_kill_switch<S: Spec>(mpg: &MarketProductGroup<S>) -> Result<()> {
+    if mpg.is_mpg_killed {
+        bail!(DexError::MarketProductGroupKillswitchIsOn);
+    }
+    Ok(())
+}
+
+/// Fails when the MPG is in admin mode and the sender is not its authority
+fn check_admin_mode<S: Spec>(mpg: &MarketProductGroup<S>, context: &Context<S>) -> Result<()> {
+    if mpg.in_admin_mode && context.sender().as_ref() != mpg.mpg_authority.as_ref() {
+        bail!(DexError::MarketProductGroupAdminModeIsOn);
+    }
+    Ok(())
+}
+
+/// Whether a combo of the MPG has the outright `product_id` as a leg
+fn has_combos<S: Spec>(mpg: &MarketProductGroup<S>, product_id: ProductId) -> bool {
+    mpg.active_products
+        .iter()
+        .filter_map(|p| p.try_to_combo().ok())
+        .any(|combo| combo.has_leg(product_id))
+}
+
+fn to_name_bytes(name: &str) -> Result<[u8; NAME_LEN]> {
+    if name.is_empty() || name.len() > NAME_LEN {
+        bail!(DexError::InvalidProductName);
+    }
+
+    let mut bytes = [0u8; NAME_LEN];
+    bytes[..name.len()].copy_from_slice(name.as_bytes());
+    Ok(bytes)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn new_product_metadata(
+    product_id: ProductId,
+    name: [u8; NAME_LEN],
+    price_index: usize,
+    orderbook_id: MarketId,
+    tick_size: Fractional,
+    base_decimals: u64,
+    price_offset: Fractional,
+    slot: u64,
+) -> ProductMetadata {
+    let mut prices = PriceEwma {
+        ewma_bid: [NO_BID_PRICE; 4],
+        ewma_ask: [NO_ASK_PRICE; 4],
+        slot,
+        bid: NO_BID_PRICE,
+        ask: NO_ASK_PRICE,
+        prev_bid: NO_BID_PRICE,
+        prev_ask: NO_ASK_PRICE,
+    };
+    prices.initialize(slot);
+
+    ProductMetadata {
+        product_id,
+        price_index,
+        name,
+        orderbook_id,
+        tick_size,
+        base_decimals,
+        price_offset,
+        notional_traded_volume: ZERO_FRAC,
+        prices,
+    }
 }
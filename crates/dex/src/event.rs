@@ -1,4 +1,6 @@
-use spicenet_shared::Fractional;
+use sov_modules_api::Spec;
+use spicenet_aaob::OrderId;
+use spicenet_shared::{Fractional, MPGId, OrderbookId, ProductId, TrgId};
 
 #[derive(
     borsh::BorshDeserialize,
@@ -9,6 +11,161 @@ use spicenet_shared::Fractional;
     PartialEq,
     Clone,
 )]
-pub enum Event {
-    TrgCreated { cash_balance: Fractional },
+#[serde(bound = "S::Address: serde::Serialize + serde::de::DeserializeOwned")]
+pub enum Event<S: Spec> {
+    TrgCreated {
+        trg_id: TrgId<S>,
+        mpg_id: MPGId,
+        owner: S::Address,
+        cash_balance: Fractional,
+    },
+    /// `amount` is in base units of the collateral token
+    FundsDeposited {
+        trg_id: TrgId<S>,
+        amount: u64,
+        cash_balance: Fractional,
+    },
+    FundsWithdrawn {
+        trg_id: TrgId<S>,
+        amount: u64,
+        cash_balance: Fractional,
+    },
+    /// Quantities are in AAOB base lots of the product
+    OrderPlaced {
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        order_id: OrderId,
+        client_order_id: u64,
+        filled_base_qty: u64,
+        /// `0` when nothing was left resting on the book
+        posted_base_qty: u64,
+        /// Fees paid by the TRG on its fills, negative for rebates
+        fees: Fractional,
+    },
+    OrderCancelled {
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        order_id: OrderId,
+    },
+    CollateralLimitsUpdated {
+        mpg_id: MPGId,
+        deposit_limit: Fractional,
+        withdraw_limit: Fractional,
+    },
+    /// `amount` is in base units of the collateral token
+    FeesSwept {
+        mpg_id: MPGId,
+        amount: u64,
+        collected_fees: Fractional,
+    },
+    FeeTiersUpdated {
+        mpg_id: MPGId,
+        /// `(min_volume, maker_fee_bps, taker_fee_bps)` of every tier
+        tiers: Vec<(Fractional, i32, i32)>,
+    },
+    ReferrerRegistered {
+        trg_id: TrgId<S>,
+    },
+    ReferrerSet {
+        trg_id: TrgId<S>,
+        referrer: TrgId<S>,
+    },
+    /// `amount` was moved from the referral fees of the TRG into its cash balance
+    ReferralFeesClaimed {
+        trg_id: TrgId<S>,
+        amount: Fractional,
+        cash_balance: Fractional,
+    },
+    PrintTradeInitialized {
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+    },
+    /// `notional` sums the absolute notional of every leg
+    PrintTradeSigned {
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+        notional: Fractional,
+        operator_fees: Fractional,
+    },
+    PrintTradeCancelled {
+        creator: TrgId<S>,
+        counterparty: TrgId<S>,
+        seed: u64,
+        by_creator: bool,
+    },
+    /// `liquidatee` was liquidated, its positions and cash now belong to `liquidator`
+    PositionTransferred {
+        liquidatee: TrgId<S>,
+        liquidator: TrgId<S>,
+        liquidation_price: Fractional,
+        total_social_loss: Fractional,
+    },
+    /// `amount` per unit of position was added to the cumulative funding of the product
+    ProductFundingUpdated {
+        mpg_id: MPGId,
+        product_id: ProductId,
+        amount: Fractional,
+        cumulative_funding_per_share: Fractional,
+    },
+    FundingSettled {
+        trg_id: TrgId<S>,
+        cash_balance: Fractional,
+    },
+    /// The book of an expiring product is empty, its positions can be cleared
+    ProductExpired {
+        mpg_id: MPGId,
+        product_id: ProductId,
+    },
+    ExpiredOrderbookCleared {
+        mpg_id: MPGId,
+        product_id: ProductId,
+        num_cancelled: u64,
+        is_book_empty: bool,
+    },
+    /// `position` was settled at the final payoff of the expired product and closed
+    ExpiredPositionCleared {
+        trg_id: TrgId<S>,
+        product_id: ProductId,
+        position: Fractional,
+        cash_balance: Fractional,
+    },
+    MarketProductGroupCreated {
+        mpg_id: MPGId,
+        name: String,
+        mpg_authority: S::Address,
+    },
+    /// An outright product was listed, trading happens on the AAOB market `orderbook_id`
+    MarketProductInitialized {
+        mpg_id: MPGId,
+        product_id: ProductId,
+        product_index: usize,
+        orderbook_id: OrderbookId,
+    },
+    ComboInitialized {
+        mpg_id: MPGId,
+        product_id: ProductId,
+        product_index: usize,
+        orderbook_id: OrderbookId,
+        /// `(product_id, ratio)` of every leg
+        legs: Vec<(ProductId, i64)>,
+    },
+    MarketProductRemoved {
+        mpg_id: MPGId,
+        product_id: ProductId,
+    },
+    KillSwitchSet {
+        mpg_id: MPGId,
+        on: bool,
+    },
+    AdminModeSet {
+        mpg_id: MPGId,
+        on: bool,
+    },
+    MpgAuthorityUpdated {
+        mpg_id: MPGId,
+        old_authority: S::Address,
+        new_authority: S::Address,
+    },
 }
@@ -1,3 +1,5 @@
+use std::marker::PhantomData;
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sov_modules_api::GenesisState;
@@ -7,13 +9,16 @@ use crate::Dex;
 
 /// Initial configuration for Dex module.
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-pub struct DexConfig<S: Spec> {}
+pub struct DexConfig<S: Spec> {
+    #[serde(skip_serializing, default)]
+    pub _phantom: PhantomData<S>,
+}
 
 impl<S: Spec> Dex<S> {
     pub(crate) fn init_module(
         &self,
-        config: &<Self as sov_modules_api::Module>::Config,
-        state: &mut impl GenesisState<S>,
+        _config: &<Self as sov_modules_api::Module>::Config,
+        _state: &mut impl GenesisState<S>,
     ) -> Result<()> {
         Ok(())
     }
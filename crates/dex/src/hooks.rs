@@ -0,0 +1,31 @@
+use sov_modules_api::{SlotHooks, Spec, StateCheckpoint};
+use sov_state::Storage;
+
+use crate::Dex;
+
+impl<S: Spec> SlotHooks for Dex<S> {
+    type Spec = S;
+
+    fn begin_slot_hook(
+        &self,
+        _visible_hash: &<<Self::Spec as Spec>::Storage as Storage>::Root,
+        state: &mut StateCheckpoint<<Self::Spec as Spec>::Storage>,
+    ) {
+        // the risk module reads the MPGs it refreshes through the dex, so its per-slot mark price
+        // refresh runs from here. A failed refresh leaves the previous mark prices in place, they
+        // can still be updated with `UpdateMarkPrices`
+        match self.risk_engine.refresh_mark_prices(self, state) {
+            Ok(failures) => {
+                for failure in failures {
+                    tracing::warn!(
+                        mpg_id = ?failure.mpg_id,
+                        product_id = ?failure.product_id,
+                        reason = %failure.reason,
+                        "Mark price refresh failed"
+                    );
+                }
+            }
+            Err(err) => tracing::error!(error = %err, "Mark price refresh aborted"),
+        }
+    }
+}
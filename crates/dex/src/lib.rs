@@ -1,6 +1,6 @@
 use sov_modules_api::{
-    CallResponse, Context, Error, GenesisState, Module, ModuleId, ModuleInfo, Spec, StateMap,
-    StateValue, StateVec, TxState,
+    CallResponse, Context, DaSpec, Error, GenesisState, Module, ModuleId, ModuleInfo, Spec,
+    StateMap, StateValue, StateVec, TxState,
 };
 
 use spicenet_shared::dex::{MarketProductGroup, TraderRiskGroup};
@@ -9,17 +9,23 @@ pub use call::*;
 pub use event::*;
 pub use genesis::*;
 pub use rpc::*;
+use sov_bank::Bank;
+use spicenet_aaob::AAOB;
 use spicenet_risk::RiskModule;
 use spicenet_shared::addresses::TrgId;
 use spicenet_shared::MPGId;
+use spicenet_time::TimeModule;
 pub use state::*;
 pub use utils::*;
 
 pub mod call;
 pub mod event;
 pub mod genesis;
+mod hooks;
 mod rpc;
+mod settlement;
 pub mod state;
+mod state_provider;
 pub mod utils;
 
 #[derive(Clone, ModuleInfo, sov_modules_api::macros::ModuleRestApi)]
@@ -30,21 +36,56 @@ pub struct Dex<S: Spec> {
     #[module]
     pub(crate) risk_engine: RiskModule<S>,
 
+    #[module]
+    pub(crate) aaob: AAOB<S>,
+
+    #[module]
+    pub(crate) time_module: TimeModule<S>,
+
+    #[module]
+    pub(crate) bank: Bank<S>,
+
     #[state]
     pub trader_risk_groups: StateMap<TrgId<S>, TraderRiskGroup<S>>,
 
     #[state]
     pub market_product_groups: StateMap<MPGId, MarketProductGroup<S>>,
+
+    #[state]
+    pub collateral_configs: StateMap<MPGId, CollateralConfig>,
+
+    /// TRG owning the AAOB orders tagged with a book id, see [`utils::get_trg_book_id`]
+    #[state]
+    pub trg_book_ids: StateMap<u64, TrgId<S>>,
+
+    #[state]
+    pub fee_schedules: StateMap<MPGId, FeeSchedule>,
+
+    #[state]
+    pub trader_volumes: StateMap<TrgId<S>, TraderVolume>,
+
+    /// Registered referrers and the number of TRGs they referred
+    #[state]
+    pub referrers: StateMap<TrgId<S>, u64>,
+
+    /// Referrer of each referred TRG
+    #[state]
+    pub trg_referrers: StateMap<TrgId<S>, TrgId<S>>,
+
+    #[state]
+    pub print_trades: StateMap<PrintTradeKey<S>, PrintTrade<S>>,
 }
 
 impl<S: Spec> Module for Dex<S> {
     type Spec = S;
     type Config = DexConfig<S>;
     type CallMessage = CallMessage<S>;
-    type Event = Event;
+    type Event = Event<S>;
 
     fn genesis(
         &self,
+        _genesis_rollup_header: &<<S as Spec>::Da as DaSpec>::BlockHeader,
+        _validity_condition: &<<S as Spec>::Da as DaSpec>::ValidityCondition,
         config: &Self::Config,
         state: &mut impl GenesisState<S>,
     ) -> Result<(), Error> {
@@ -56,15 +97,157 @@ impl<S: Spec> Module for Dex<S> {
         msg: Self::CallMessage,
         context: &Context<Self::Spec>,
         state: &mut impl TxState<S>,
-    ) -> Result<CallResponse, Error> {
+    ) -> Result<(), Error> {
         let call_result = match msg {
-            CallMessage::InitTrg {} => self.initialize_trg(),
+            CallMessage::InitializeTrg { mpg_id } => self.initialize_trg(mpg_id, context, state),
+            CallMessage::DepositFunds { trg_id, amount } => {
+                self.deposit_funds(trg_id, amount, context, state)
+            }
+            CallMessage::WithdrawFunds { trg_id, amount } => {
+                self.withdraw_funds(trg_id, amount, context, state)
+            }
+            CallMessage::NewOrder {
+                trg_id,
+                product_id,
+                order,
+            } => self.new_order(trg_id, product_id, order, context, state),
+            CallMessage::CancelOrder {
+                trg_id,
+                product_id,
+                order_id,
+            } => self.cancel_order(trg_id, product_id, order_id, context, state),
             CallMessage::UpdateProductFunding {
+                mpg_id,
+                product_id,
+                amount,
+                new_product_status,
+            } => self.update_product_funding_as_authority(
+                mpg_id,
+                product_id,
                 amount,
                 new_product_status,
-            } => self.update_product_funding(amount, new_product_status, context, state),
+                context,
+                state,
+            ),
+            CallMessage::SettleFunding { trg_id } => self.settle_trg_funding(trg_id, state),
+            CallMessage::CreateMarketProductGroup {
+                name,
+                decimals,
+                collateral_token_id,
+                ewma_windows,
+                max_maker_fee_bps,
+                min_maker_fee_bps,
+                max_taker_fee_bps,
+                min_taker_fee_bps,
+            } => self.create_market_product_group(
+                &(String::from(name)),
+                decimals,
+                collateral_token_id,
+                ewma_windows,
+                max_maker_fee_bps,
+                min_maker_fee_bps,
+                max_taker_fee_bps,
+                min_taker_fee_bps,
+                context,
+                state,
+            ),
+            CallMessage::InitializeMarketProduct { mpg_id, params } => {
+                self.initialize_market_product(mpg_id, params, context, state)
+            }
+            CallMessage::InitializeCombo { mpg_id, params } => {
+                self.initialize_combo(mpg_id, params, context, state)
+            }
+            CallMessage::RemoveMarketProduct { mpg_id, product_id } => {
+                self.remove_market_product(mpg_id, product_id, context, state)
+            }
+            CallMessage::UpdateMpgAuthority {
+                mpg_id,
+                new_authority,
+            } => self.update_mpg_authority(mpg_id, new_authority, context, state),
+            CallMessage::SetKillSwitch { mpg_id, on } => {
+                self.set_kill_switch(mpg_id, on, context, state)
+            }
+            CallMessage::SetAdminMode { mpg_id, on } => {
+                self.set_admin_mode(mpg_id, on, context, state)
+            }
+            CallMessage::SetCollateralLimits {
+                mpg_id,
+                deposit_limit,
+                withdraw_limit,
+            } => self.set_collateral_limits(mpg_id, deposit_limit, withdraw_limit, context, state),
+            CallMessage::SweepFees { mpg_id } => self.sweep_fees(mpg_id, context, state),
+            CallMessage::SetFeeTiers { mpg_id, tiers } => {
+                self.set_fee_tiers(mpg_id, tiers, context, state)
+            }
+            CallMessage::RegisterReferrer { trg_id } => {
+                self.register_referrer(trg_id, context, state)
+            }
+            CallMessage::SetReferrer { trg_id, referrer } => {
+                self.set_referrer(trg_id, referrer, context, state)
+            }
+            CallMessage::ClaimReferralFees { trg_id } => {
+                self.claim_referral_fees(trg_id, context, state)
+            }
+            CallMessage::InitializePrintTrade {
+                creator,
+                counterparty,
+                seed,
+                products,
+                operator,
+                operator_creator_fee_proportion,
+                operator_counterparty_fee_proportion,
+            } => self.initialize_print_trade(
+                creator,
+                counterparty,
+                seed,
+                products,
+                operator,
+                operator_creator_fee_proportion,
+                operator_counterparty_fee_proportion,
+                context,
+                state,
+            ),
+            CallMessage::SignPrintTrade {
+                creator,
+                counterparty,
+                seed,
+                products,
+                operator_creator_fee_proportion,
+                operator_counterparty_fee_proportion,
+            } => self.sign_print_trade(
+                creator,
+                counterparty,
+                seed,
+                products,
+                operator_creator_fee_proportion,
+                operator_counterparty_fee_proportion,
+                context,
+                state,
+            ),
+            CallMessage::CancelPrintTrade {
+                creator,
+                counterparty,
+                seed,
+            } => self.cancel_print_trade(creator, counterparty, seed, context, state),
+            CallMessage::ClearExpiredOrderbook {
+                mpg_id,
+                product_id,
+                num_orders,
+            } => self.clear_expired_orderbook(mpg_id, product_id, num_orders, state),
+            CallMessage::ClearExpiredPosition { trg_id, product_id } => {
+                self.clear_expired_position(trg_id, product_id, state)
+            }
+            CallMessage::Risk { msg } => self
+                .risk_engine
+                .call_with_provider(msg, self, context, state)
+                .map(|_| CallResponse::default()),
+            CallMessage::TransferFullPosition {
+                liquidatee,
+                liquidator,
+            } => self.transfer_full_position(liquidatee, liquidator, context, state),
         };
 
-        Ok(call_result?)
+        call_result?;
+        Ok(())
     }
 }
@@ -1,10 +1,29 @@
-use sov_modules_api::{macros::rpc_gen, ApiStateAccessor, Spec};
+use axum::routing::{get, post};
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::types::error::ErrorCode;
+use jsonrpsee::types::ErrorObjectOwned;
+use sov_modules_api::prelude::axum;
+use sov_modules_api::rest::utils::{errors, ApiResult, Path};
+use sov_modules_api::rest::{ApiState, HasCustomRestApi};
+use sov_modules_api::{macros::rpc_gen, ApiStateAccessor, Spec, StateAccessor};
 
+use spicenet_risk::simulation::{RiskProfileDetails, RiskSimulation};
 use spicenet_shared::addresses::TrgId;
-use spicenet_shared::{MPGId, MarketProductGroup, TraderRiskGroup};
+use spicenet_shared::{DexError, MPGId, MarketProductGroup, TraderRiskGroup};
 
 use crate::Dex;
 
+/// JSON-RPC error code of a TRG or MPG that does not exist, REST endpoints answer 404 instead
+pub const NOT_FOUND_ERROR_CODE: i32 = -32004;
+
+fn not_found(what: &str) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        NOT_FOUND_ERROR_CODE,
+        format!("{what} not found"),
+        None::<()>,
+    )
+}
+
 #[rpc_gen(client, server, namespace = "dex")]
 impl<S: Spec> Dex<S> {
     #[rpc_method(name = "getTRG")]
@@ -12,9 +31,11 @@ impl<S: Spec> Dex<S> {
         &self,
         state: &mut ApiStateAccessor<S>,
         trg_id: &TrgId<S>,
-    ) -> Result<TraderRiskGroup<S>> {
-        let trg = self.trader_risk_groups.get(trg_id, state).unwrap();
-        Ok(trg.unwrap())
+    ) -> RpcResult<TraderRiskGroup<S>> {
+        self.trader_risk_groups
+            .get(trg_id, state)
+            .map_err(|_| ErrorCode::InternalError.into())
+            .and_then(|trg| trg.ok_or_else(|| not_found("Trader risk group")))
     }
 
     #[rpc_method(name = "getMPG")]
@@ -22,8 +43,101 @@ impl<S: Spec> Dex<S> {
         &self,
         state: &mut ApiStateAccessor<S>,
         mpg_id: &MPGId,
-    ) -> Result<MarketProductGroup<S>> {
-        let mpg = self.market_product_groups.get(mpg_id, state).unwrap();
-        Ok(mpg.unwrap())
+    ) -> RpcResult<MarketProductGroup<S>> {
+        self.market_product_groups
+            .get(mpg_id, state)
+            .map_err(|_| ErrorCode::InternalError.into())
+            .and_then(|mpg| mpg.ok_or_else(|| not_found("Market product group")))
+    }
+
+    #[rpc_method(name = "getRiskProfile")]
+    /// Current risk profile of a TRG
+    pub fn get_risk_profile(
+        &self,
+        trg_id: TrgId<S>,
+        state: &mut ApiStateAccessor<S>,
+    ) -> RpcResult<RiskProfileDetails> {
+        self.risk_profile(&RiskSimulation::new(trg_id), state)
+            .map_err(|_| ErrorCode::InternalError.into())
+            .and_then(|result| result.ok_or_else(|| not_found("Trader risk group")))
+    }
+
+    #[rpc_method(name = "simulateOrder")]
+    /// Risk profile a TRG would have after hypothetical orders, fills, deposit and withdrawal
+    pub fn simulate_order(
+        &self,
+        simulation: RiskSimulation<S>,
+        state: &mut ApiStateAccessor<S>,
+    ) -> RpcResult<RiskProfileDetails> {
+        self.risk_profile(&simulation, state)
+            .map_err(|_| ErrorCode::InvalidParams.into())
+            .and_then(|result| result.ok_or_else(|| not_found("Trader risk group")))
+    }
+}
+
+impl<S: Spec> Dex<S> {
+    /// Risk profile the TRG of `simulation` would have after the simulated changes, computed
+    /// without writing to state. `None` if the TRG or its MPG does not exist.
+    pub fn risk_profile(
+        &self,
+        simulation: &RiskSimulation<S>,
+        accessor: &mut impl StateAccessor,
+    ) -> anyhow::Result<Option<RiskProfileDetails>> {
+        match self
+            .risk_engine
+            .simulate_risk_profile(self, simulation, accessor)
+        {
+            Ok(risk_profile) => Ok(Some(risk_profile)),
+            Err(err)
+                if matches!(
+                    err.downcast_ref::<DexError>(),
+                    Some(DexError::TraderRiskGroupDoesNotExist)
+                        | Some(DexError::MarketProductGroupDoesNotExist)
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<S: Spec> HasCustomRestApi for Dex<S> {
+    type Spec = S;
+
+    fn custom_rest_api(&self, state: ApiState<S>) -> axum::Router<()> {
+        axum::Router::new()
+            .route("/risk/profile/:trgId", get(Self::get_risk_profile_rest))
+            .route("/risk/simulate-order", post(Self::simulate_order_rest))
+            .with_state(state.with(self.clone()))
+    }
+}
+
+impl<S: Spec> Dex<S> {
+    async fn get_risk_profile_rest(
+        state: ApiState<S, Self>,
+        mut accessor: ApiStateAccessor<S>,
+        Path(trg_id): Path<TrgId<S>>,
+    ) -> ApiResult<RiskProfileDetails> {
+        let risk_profile = state
+            .risk_profile(&RiskSimulation::new(trg_id.clone()), &mut accessor)
+            .map_err(|err| errors::bad_request_400("Failed to compute the risk profile", err))?
+            .ok_or_else(|| errors::not_found_404("Trader risk group", trg_id))?;
+
+        Ok(risk_profile.into())
+    }
+
+    async fn simulate_order_rest(
+        state: ApiState<S, Self>,
+        mut accessor: ApiStateAccessor<S>,
+        axum::Json(simulation): axum::Json<RiskSimulation<S>>,
+    ) -> ApiResult<RiskProfileDetails> {
+        let trg_id = simulation.trg_id.clone();
+        let risk_profile = state
+            .risk_profile(&simulation, &mut accessor)
+            .map_err(|err| errors::bad_request_400("Failed to simulate the order", err))?
+            .ok_or_else(|| errors::not_found_404("Trader risk group", trg_id))?;
+
+        Ok(risk_profile.into())
     }
 }
@@ -0,0 +1,545 @@
+use anyhow::{bail, Result};
+use sov_modules_api::{Spec, TxState};
+
+use spicenet_aaob::{get_side_from_order_id, OrderId, OrderSummary};
+use spicenet_shared::{
+    DexError, Fractional, IsInitialized, MarketProductGroup, OutrightProduct, Product, Side,
+    TraderRiskGroup, TrgId, REFERRER_FEES_PROPORTION, SENTINEL, ZERO_FRAC,
+};
+
+use crate::state::{
+    fill_fee, FeeSchedule, TraderFees, MILLISECONDS_PER_SECOND, SECONDS_PER_DAY,
+};
+use crate::utils::get_trg_book_id;
+use crate::Dex;
+
+impl<S: Spec> Dex<S> {
+    /// Applies the outcome of an order of `taker` on the book of the product at `product_index`.
+    ///
+    /// Fills move positions and cash between the taker and the makers, orders taken out of the
+    /// book are dropped from the open orders of their TRG and the posted remainder is tracked in
+    /// the open orders of the taker. Maker TRGs are saved, the caller saves the taker and the MPG.
+    ///
+    /// Both sides pay their fee on every fill into the MPG `collected_fees`, the fees paid by the
+    /// taker are returned. The referrer of the taker gets [`REFERRER_FEES_PROPORTION`] of them.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn settle_order(
+        &self,
+        mpg: &mut MarketProductGroup<S>,
+        product_index: usize,
+        taker: &mut TraderRiskGroup<S>,
+        side: Side,
+        client_order_id: u64,
+        summary: &OrderSummary,
+        state: &mut impl TxState<S>,
+    ) -> Result<Fractional> {
+        let taker_book_id = get_trg_book_id::<S>(&taker.id);
+        let base_decimals = mpg.active_products[product_index].base_decimals;
+        let is_combo = matches!(mpg.active_products[product_index], Product::Combo { .. });
+
+        // the time module counts milliseconds, fee validity and volume days are in seconds
+        let now = self.time_module.get_time(state)?.unix_timestamp / MILLISECONDS_PER_SECOND;
+        let schedule = self.fee_schedules.get(&mpg.id, state)?.unwrap_or_default();
+        self.refresh_fees(taker, &schedule, now, state)?;
+        let taker_fee_bps = TraderFees::of_trg(taker, mpg, now).taker_fee_bps(Some(mpg));
+        let referrer_id = self.trg_referrers.get(&taker.id, state)?;
+
+        let mut taker_fees = ZERO_FRAC;
+        let mut taker_volume = ZERO_FRAC;
+        let mut referral_fees = ZERO_FRAC;
+
+        for fill in &summary.fills {
+            let mut maker = self.load_trg_by_book_id(fill.maker_trg_id, state)?;
+            self.refresh_fees(&mut maker, &schedule, now, state)?;
+            let base = Fractional::new(fill.base_qty as i64, base_decimals);
+            let price = from_ticks(&mpg.active_products[product_index], fill.price >> 32)?;
+
+            let notional = settle_fill(mpg, product_index, taker, &mut maker, side, base, price)?;
+
+            let maker_fee_bps = TraderFees::of_trg(&maker, mpg, now).maker_fee_bps(Some(mpg));
+            let taker_fee = fill_fee(notional, taker_fee_bps, is_combo)?;
+            let maker_fee = fill_fee(notional, maker_fee_bps, is_combo)?;
+            let referral_fee = match referrer_id.is_some() && taker_fee > ZERO_FRAC {
+                true => taker_fee.checked_mul(REFERRER_FEES_PROPORTION)?,
+                false => ZERO_FRAC,
+            };
+            taker.cash_balance = taker.cash_balance.checked_sub(taker_fee)?;
+            maker.cash_balance = maker.cash_balance.checked_sub(maker_fee)?;
+            mpg.collected_fees = mpg
+                .collected_fees
+                .checked_add(taker_fee)?
+                .checked_add(maker_fee)?
+                .checked_sub(referral_fee)?;
+            taker_fees = taker_fees.checked_add(taker_fee)?;
+            taker_volume = taker_volume.checked_add(notional.abs())?;
+            referral_fees = referral_fees.checked_add(referral_fee)?;
+            self.record_volume(&maker.id, notional.abs(), now, state)?;
+
+            maker
+                .open_orders
+                .decrement_order_size(product_index, fill.maker_order_id, fill.base_qty)?;
+            maker.decrement_book_size(product_index, side.opposite(), base, base_decimals)?;
+            if fill.maker_order_completed {
+                remove_resting_order(
+                    &mut maker,
+                    product_index,
+                    fill.maker_order_id,
+                    side.opposite(),
+                    base_decimals,
+                )?;
+            }
+
+            self.trader_risk_groups.set(&maker.id.clone(), &maker, state)?;
+        }
+
+        for out in &summary.outs {
+            if out.trg_id == taker_book_id {
+                remove_resting_order(taker, product_index, out.order_id, out.side, base_decimals)?;
+                continue;
+            }
+
+            let mut trg = self.load_trg_by_book_id(out.trg_id, state)?;
+            remove_resting_order(&mut trg, product_index, out.order_id, out.side, base_decimals)?;
+            self.trader_risk_groups.set(&trg.id.clone(), &trg, state)?;
+        }
+
+        if taker_volume > ZERO_FRAC {
+            self.record_volume(&taker.id, taker_volume, now, state)?;
+        }
+
+        // the referrer may have been a maker of this order, it is loaded once makers are saved
+        if let Some(referrer_id) = referrer_id {
+            if taker_volume > ZERO_FRAC {
+                let mut referrer = self.load_trg(&referrer_id, state)?;
+                referrer.referral_fees = referrer.referral_fees.checked_add(referral_fees)?;
+                referrer.referred_takers_notional_volume = referrer
+                    .referred_takers_notional_volume
+                    .checked_add(taker_volume)?;
+                self.trader_risk_groups.set(&referrer_id, &referrer, state)?;
+            }
+        }
+
+        if summary.posted_base_qty > 0 {
+            taker.add_open_order(
+                product_index,
+                summary.order_id,
+                summary.posted_base_qty,
+                client_order_id,
+            )?;
+            taker.adjust_book_qty(
+                product_index,
+                Fractional::new(summary.posted_base_qty as i64, base_decimals),
+                side,
+                base_decimals,
+            )?;
+        }
+
+        Ok(taker_fees)
+    }
+
+    /// Cancels every resting order of `trg` on the AAOB and drops them from its open orders
+    pub(crate) fn cancel_all_orders(
+        &self,
+        mpg: &MarketProductGroup<S>,
+        trg: &mut TraderRiskGroup<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        for (product_index, product) in mpg.active_products.iter().enumerate() {
+            if trg.open_orders.products[product_index].num_open_orders == 0 {
+                continue;
+            }
+
+            let mut order_ids = vec![];
+            let mut i = trg.open_orders.products[product_index].head_index;
+            while i != SENTINEL {
+                let node = &trg.open_orders.orders[i as usize];
+                order_ids.push(node.id);
+                i = node.next;
+            }
+
+            for order_id in order_ids {
+                let side = get_side_from_order_id(order_id);
+                self.aaob
+                    .cancel_delegated_order(product.orderbook_id, order_id, side, state)?;
+                remove_resting_order(trg, product_index, order_id, side, product.base_decimals)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cancels up to `limit` resting orders of the product at `product_index` on the AAOB and
+    /// drops them from the open orders of their TRG. Returns the number of cancelled orders and
+    /// whether the book is empty.
+    pub(crate) fn clear_orderbook(
+        &self,
+        mpg: &MarketProductGroup<S>,
+        product_index: usize,
+        limit: usize,
+        state: &mut impl TxState<S>,
+    ) -> Result<(usize, bool)> {
+        let product = &mpg.active_products[product_index];
+        let cancelled = self
+            .aaob
+            .cancel_delegated_orders(product.orderbook_id, limit, state)?;
+
+        for (order_id, side, order) in &cancelled {
+            let mut trg = self.load_trg_by_book_id(order.trg_id, state)?;
+            remove_resting_order(
+                &mut trg,
+                product_index,
+                *order_id,
+                *side,
+                product.base_decimals,
+            )?;
+            self.trader_risk_groups.set(&trg.id, &trg, state)?;
+        }
+
+        Ok((cancelled.len(), cancelled.len() < limit))
+    }
+
+    /// Caches on `trg` the fees of the tier its rolling volume reaches, until the end of the day
+    fn refresh_fees(
+        &self,
+        trg: &mut TraderRiskGroup<S>,
+        schedule: &FeeSchedule,
+        now: u64,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        if now <= trg.valid_until as u64 {
+            return Ok(());
+        }
+
+        let volume = match self.trader_volumes.get(&trg.id, state)? {
+            Some(volume) => volume.rolling_volume(now)?,
+            None => ZERO_FRAC,
+        };
+        if let Some(tier) = schedule.tier_for(volume) {
+            trg.maker_fee_bps = tier.maker_fee_bps;
+            trg.taker_fee_bps = tier.taker_fee_bps;
+            trg.valid_until = ((now / SECONDS_PER_DAY + 1) * SECONDS_PER_DAY - 1) as u32;
+        }
+
+        Ok(())
+    }
+
+    fn record_volume(
+        &self,
+        trg_id: &TrgId<S>,
+        notional: Fractional,
+        now: u64,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let mut volume = self.trader_volumes.get(trg_id, state)?.unwrap_or_default();
+        volume.record(now, notional)?;
+        self.trader_volumes.set(trg_id, &volume, state)?;
+        Ok(())
+    }
+
+    fn load_trg_by_book_id(
+        &self,
+        book_id: u64,
+        state: &mut impl TxState<S>,
+    ) -> Result<TraderRiskGroup<S>> {
+        match self.trg_book_ids.get(&book_id, state)? {
+            Some(trg_id) => self.load_trg(&trg_id, state),
+            None => bail!(DexError::TraderRiskGroupDoesNotExist),
+        }
+    }
+}
+
+/// Moves `base` of the product at `product_index` from the maker to the taker at `price`.
+///
+/// Combos move every leg by `base * ratio`, cash moves by the notional of the combo itself which
+/// is returned.
+pub(crate) fn settle_fill<S: Spec>(
+    mpg: &mut MarketProductGroup<S>,
+    product_index: usize,
+    taker: &mut TraderRiskGroup<S>,
+    maker: &mut TraderRiskGroup<S>,
+    taker_side: Side,
+    base: Fractional,
+    price: Fractional,
+) -> Result<Fractional> {
+    let notional = price.checked_mul(base)?;
+    let taker_sign: i64 = match taker_side {
+        Side::Bid => 1,
+        Side::Ask => -1,
+    };
+
+    let legs: Vec<(i64, usize)> = mpg.active_products[product_index]
+        .get_ratios_and_product_indexes(product_index)
+        .collect();
+
+    for (ratio, leg_index) in legs {
+        let taker_delta = base.checked_mul(ratio * taker_sign)?;
+        let size = taker_delta.abs();
+        let (buyer, seller) = if taker_delta > ZERO_FRAC {
+            (&mut *taker, &mut *maker)
+        } else {
+            (&mut *maker, &mut *taker)
+        };
+
+        let outright = mpg.active_products[leg_index].try_to_outright_mut()?;
+        trade_outright(outright, leg_index, buyer, seller, size)?;
+    }
+
+    match taker_side {
+        Side::Bid => {
+            taker.cash_balance = taker.cash_balance.checked_sub(notional)?;
+            maker.cash_balance = maker.cash_balance.checked_add(notional)?;
+        }
+        Side::Ask => {
+            taker.cash_balance = taker.cash_balance.checked_add(notional)?;
+            maker.cash_balance = maker.cash_balance.checked_sub(notional)?;
+        }
+    }
+    // combos can trade at a negative price, volume counts the size of the notional
+    let volume = notional.abs();
+    taker.notional_taker_volume = taker.notional_taker_volume.checked_add(volume)?;
+    maker.notional_maker_volume = maker.notional_maker_volume.checked_add(volume)?;
+
+    let product = &mut mpg.active_products[product_index];
+    product.notional_traded_volume = product.notional_traded_volume.checked_add(volume)?;
+
+    Ok(notional)
+}
+
+fn position_of<S: Spec>(trg: &TraderRiskGroup<S>, product_index: usize) -> Fractional {
+    match trg.get_position_index(product_index) {
+        Ok(position_index) if position_index != u8::MAX => {
+            trg.trader_positions[position_index as usize].position
+        }
+        _ => ZERO_FRAC,
+    }
+}
+
+/// Moves `size` of `outright` from `seller` to `buyer`, keeping its open interest up to date
+fn trade_outright<S: Spec>(
+    outright: &mut OutrightProduct,
+    product_index: usize,
+    buyer: &mut TraderRiskGroup<S>,
+    seller: &mut TraderRiskGroup<S>,
+    size: Fractional,
+) -> Result<()> {
+    let buyer_short_position = (-position_of(buyer, product_index)).max(ZERO_FRAC);
+    let seller_long_position = position_of(seller, product_index).max(ZERO_FRAC);
+
+    outright.update_open_interest_change(size, buyer_short_position, seller_long_position)?;
+    change_position(outright, buyer, product_index, size)?;
+    change_position(outright, seller, product_index, -size)?;
+
+    Ok(())
+}
+
+/// Moves every position of `liquidatee` to `liquidator` and closes the positions of the
+/// liquidatee, cash is left untouched
+pub(crate) fn transfer_positions<S: Spec>(
+    mpg: &mut MarketProductGroup<S>,
+    liquidatee: &mut TraderRiskGroup<S>,
+    liquidator: &mut TraderRiskGroup<S>,
+) -> Result<()> {
+    for position_index in 0..liquidatee.trader_positions.len() {
+        let position = liquidatee.trader_positions[position_index].clone();
+        if !position.is_initialized() {
+            continue;
+        }
+
+        let outright = mpg.active_products[position.product_index].try_to_outright_mut()?;
+        let size = position.position.abs();
+        if position.position > ZERO_FRAC {
+            trade_outright(outright, position.product_index, liquidator, liquidatee, size)?;
+        } else if position.position < ZERO_FRAC {
+            trade_outright(outright, position.product_index, liquidatee, liquidator, size)?;
+        }
+
+        liquidatee.clear(&position.product_key)?;
+        outright.num_tracking_risk_states -= 1;
+    }
+
+    Ok(())
+}
+
+/// Charges every position of `trg` the social loss applied to its product since the position
+/// snapshot was taken
+pub(crate) fn realize_social_losses<S: Spec>(
+    mpg: &MarketProductGroup<S>,
+    trg: &mut TraderRiskGroup<S>,
+) -> Result<()> {
+    for position_index in 0..trg.trader_positions.len() {
+        if !trg.trader_positions[position_index].is_initialized() {
+            continue;
+        }
+
+        let product_index = trg.trader_positions[position_index].product_index;
+        let outright = mpg.active_products[product_index].try_to_outright()?;
+        realize_social_loss(outright, trg, position_index)?;
+    }
+
+    Ok(())
+}
+
+/// Moves the funding accrued by every position of `trg` since its snapshot into its cash balance
+pub(crate) fn settle_funding<S: Spec>(
+    mpg: &MarketProductGroup<S>,
+    trg: &mut TraderRiskGroup<S>,
+) -> Result<()> {
+    for position_index in 0..trg.trader_positions.len() {
+        if !trg.trader_positions[position_index].is_initialized() {
+            continue;
+        }
+
+        let product_index = trg.trader_positions[position_index].product_index;
+        let outright = mpg.active_products[product_index].try_to_outright()?;
+        realize_funding(outright, trg, position_index)?;
+    }
+
+    Ok(())
+}
+
+/// Closes the position of `trg` in the expired outright at `product_index`. The final payoff was
+/// applied as funding when the product expired, so realizing funding settles the position and
+/// no cash moves for the position itself. Returns the closed position.
+pub(crate) fn close_expired_position<S: Spec>(
+    mpg: &mut MarketProductGroup<S>,
+    trg: &mut TraderRiskGroup<S>,
+    product_index: usize,
+) -> Result<Fractional> {
+    let outright = mpg.active_products[product_index].try_to_outright_mut()?;
+    let position_index = match trg.find_position_index(&outright.product_id) {
+        Some(position_index) => position_index,
+        None => bail!(DexError::NoOp),
+    };
+    realize_funding(outright, trg, position_index)?;
+    realize_social_loss(outright, trg, position_index)?;
+
+    let position = trg.trader_positions[position_index].position;
+    if position > ZERO_FRAC {
+        outright.open_long_interest = outright.open_long_interest.checked_sub(position)?;
+    } else {
+        outright.open_short_interest = outright.open_short_interest.checked_add(position)?;
+    }
+    trg.clear(&outright.product_id)?;
+    outright.num_tracking_risk_states -= 1;
+
+    Ok(position)
+}
+
+/// Positive funding is paid by shorts to longs, negative funding by longs to shorts
+fn realize_funding<S: Spec>(
+    outright: &OutrightProduct,
+    trg: &mut TraderRiskGroup<S>,
+    position_index: usize,
+) -> Result<()> {
+    let position = &mut trg.trader_positions[position_index];
+    let funding = outright
+        .cumulative_funding_per_share
+        .checked_sub(position.last_cum_funding_snapshot)?
+        .checked_mul(position.position)?;
+    position.last_cum_funding_snapshot = outright.cumulative_funding_per_share;
+
+    trg.cash_balance = trg.cash_balance.checked_add(funding)?;
+    Ok(())
+}
+
+fn realize_social_loss<S: Spec>(
+    outright: &OutrightProduct,
+    trg: &mut TraderRiskGroup<S>,
+    position_index: usize,
+) -> Result<()> {
+    let position = &mut trg.trader_positions[position_index];
+    let social_loss = outright
+        .cumulative_social_loss_per_share
+        .checked_sub(position.last_social_loss_snapshot)?
+        .checked_mul(position.position.abs())?;
+    position.last_social_loss_snapshot = outright.cumulative_social_loss_per_share;
+
+    trg.cash_balance = trg.cash_balance.checked_sub(social_loss)?;
+    Ok(())
+}
+
+/// Adds `delta` to the position of `trg` in `outright`, opening the position if needed. Social
+/// loss and funding are realized at the previous size first.
+fn change_position<S: Spec>(
+    outright: &mut OutrightProduct,
+    trg: &mut TraderRiskGroup<S>,
+    product_index: usize,
+    delta: Fractional,
+) -> Result<()> {
+    let (position_index, opened) = trg.activate_position(
+        product_index,
+        &outright.product_id,
+        outright.cumulative_funding_per_share,
+        outright.cumulative_social_loss_per_share,
+    )?;
+    if opened {
+        outright.num_tracking_risk_states += 1;
+    }
+    realize_social_loss(outright, trg, position_index)?;
+    realize_funding(outright, trg, position_index)?;
+
+    let position = &mut trg.trader_positions[position_index];
+    position.position = position.position.checked_add(delta)?;
+
+    Ok(())
+}
+
+/// Drops a resting order from the open orders of `trg`, orders that are not tracked anymore are
+/// ignored
+pub(crate) fn remove_resting_order<S: Spec>(
+    trg: &mut TraderRiskGroup<S>,
+    product_index: usize,
+    order_id: OrderId,
+    side: Side,
+    base_decimals: u64,
+) -> Result<()> {
+    let order_index = match trg.open_orders.get_order_index(product_index, order_id) {
+        Ok(order_index) => order_index,
+        Err(_) => return Ok(()),
+    };
+
+    let qty = trg.open_orders.orders[order_index].qty;
+    trg.decrement_book_size(
+        product_index,
+        side,
+        Fractional::new(qty as i64, base_decimals),
+        base_decimals,
+    )?;
+    trg.remove_open_order_by_index(product_index, order_index, order_id)?;
+
+    Ok(())
+}
+
+/// Converts a product price to the AAOB tick count it rests at
+pub(crate) fn to_ticks(product: &Product, price: Fractional) -> Result<u64> {
+    let offset_price = price.checked_add(product.price_offset)?;
+    let ticks = offset_price.checked_div(product.tick_size)?.to_int();
+
+    if ticks <= 0 || product.tick_size.checked_mul(ticks)? != offset_price {
+        bail!(DexError::InvalidOrderPrice);
+    }
+
+    Ok(ticks as u64)
+}
+
+/// Converts an AAOB tick count back to a product price
+pub(crate) fn from_ticks(product: &Product, ticks: u64) -> Result<Fractional> {
+    Ok(product
+        .tick_size
+        .checked_mul(ticks as i64)?
+        .checked_sub(product.price_offset)?)
+}
+
+/// Converts a product quantity to AAOB base lots of `base_decimals`
+pub(crate) fn to_lots(product: &Product, qty: Fractional) -> Result<u64> {
+    let lots = match qty.round(product.base_decimals as u32) {
+        Ok(lots) => lots,
+        Err(_) => bail!(DexError::ProductDecimalPrecisionError),
+    };
+
+    if lots.m <= 0 {
+        bail!(DexError::InvalidOrderSize);
+    }
+
+    Ok(lots.m as u64)
+}
@@ -0,0 +1,20 @@
+use sov_bank::TokenId;
+
+use spicenet_shared::Fractional;
+
+/// Collateral accepted by an MPG, cash balances of its TRGs are denominated in it
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
+pub struct CollateralConfig {
+    /// Token transferred through `sov_bank` on deposits and withdrawals
+    pub token_id: TokenId,
+    /// Maximum amount a TRG can deposit over its lifetime, in MPG decimals
+    pub deposit_limit: Fractional,
+    /// Maximum amount a TRG can withdraw over its lifetime, in MPG decimals
+    pub withdraw_limit: Fractional,
+}
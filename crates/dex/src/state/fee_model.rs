@@ -1,5 +1,8 @@
 use sov_modules_api::Spec;
-use spicenet_shared::{dex::MarketProductGroup, Fractional, ProductId, Side};
+use spicenet_shared::{
+    dex::MarketProductGroup, Fractional, MathResult, ProductId, Side, TraderRiskGroup,
+    COMBO_FEE_SCALAR,
+};
 
 #[cfg_attr(
     feature = "native",
@@ -45,6 +48,24 @@ fn within_or_zero(x: i32, max: impl Into<i32>, min: impl Into<i32>) -> i32 {
     }
 }
 
+fn clamp_within(x: i32, max: impl Into<i32>, min: impl Into<i32>) -> i32 {
+    x.max(min.into()).min(max.into())
+}
+
+/// Fee charged on a fill of `notional`, negative for rebates. Combo fills pay
+/// `COMBO_FEE_SCALAR` of the outright rate.
+pub fn fill_fee(
+    notional: Fractional,
+    fee_bps: Fractional,
+    is_combo: bool,
+) -> MathResult<Fractional> {
+    let fee = notional.abs().checked_mul(fee_bps)?;
+    match is_combo {
+        true => fee.checked_mul(COMBO_FEE_SCALAR),
+        false => Ok(fee),
+    }
+}
+
 impl TraderFees {
     pub fn new(maker_fee_bps: i32, taker_fee_bps: i32, valid_until: u32) -> Self {
         // valid_until is timestamp
@@ -55,13 +76,30 @@ impl TraderFees {
         }
     }
 
+    /// Fees cached on `trg`, once `valid_until` is past the TRG pays the MPG maximums
+    pub fn of_trg<S: Spec>(
+        trg: &TraderRiskGroup<S>,
+        market_product_group: &MarketProductGroup<S>,
+        now: u64,
+    ) -> Self {
+        if now <= trg.valid_until as u64 {
+            return Self::new(trg.maker_fee_bps, trg.taker_fee_bps, trg.valid_until);
+        }
+
+        Self::new(
+            market_product_group.max_maker_fee_bps as i32,
+            market_product_group.max_taker_fee_bps as i32,
+            trg.valid_until,
+        )
+    }
+
     pub fn maker_fee_bps<S: Spec>(
         &self,
         market_product_group: Option<&MarketProductGroup<S>>,
     ) -> Fractional {
         let fee = market_product_group
             .map(|mpg| {
-                within_or_zero(
+                clamp_within(
                     self.maker_fee_bps,
                     mpg.max_maker_fee_bps,
                     mpg.min_maker_fee_bps,
@@ -78,7 +116,7 @@ impl TraderFees {
     ) -> Fractional {
         let fee = market_product_group
             .map(|mpg| {
-                within_or_zero(
+                clamp_within(
                     self.taker_fee_bps,
                     mpg.max_taker_fee_bps,
                     mpg.min_taker_fee_bps,
@@ -0,0 +1,92 @@
+use spicenet_shared::{Fractional, MathResult, ZERO_FRAC};
+
+/// Number of days the volume of a TRG is tracked over to pick its fee tier
+pub const VOLUME_WINDOW_DAYS: usize = 30;
+
+pub const MAX_FEE_TIERS: usize = 16;
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+pub const MILLISECONDS_PER_SECOND: u64 = 1_000;
+
+/// Fees paid by TRGs whose rolling volume is at least `min_volume`
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
+pub struct FeeTier {
+    /// Maker plus taker notional volume over the last [`VOLUME_WINDOW_DAYS`], in MPG decimals
+    pub min_volume: Fractional,
+    pub maker_fee_bps: i32,
+    pub taker_fee_bps: i32,
+}
+
+/// Fee tiers of an MPG sorted by increasing `min_volume`, the first tier starts at zero volume
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone, Default)]
+pub struct FeeSchedule {
+    pub tiers: Vec<FeeTier>,
+}
+
+impl FeeSchedule {
+    /// Highest tier reached by `volume`, `None` when the schedule is empty
+    pub fn tier_for(&self, volume: Fractional) -> Option<&FeeTier> {
+        self.tiers
+            .iter()
+            .take_while(|tier| tier.min_volume <= volume)
+            .last()
+    }
+}
+
+/// Notional volume of a TRG over the last [`VOLUME_WINDOW_DAYS`], bucketed per day
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone, Default)]
+pub struct TraderVolume {
+    /// Last day volume was recorded on, in days since the unix epoch
+    pub day: u64,
+    /// Volume of day `d` is stored at `d % VOLUME_WINDOW_DAYS`
+    pub daily_volume: [Fractional; VOLUME_WINDOW_DAYS],
+}
+
+impl TraderVolume {
+    pub fn record(&mut self, now: u64, notional: Fractional) -> MathResult<()> {
+        let day = now / SECONDS_PER_DAY;
+        if day > self.day {
+            // buckets of the days between the last record and today are from a previous window
+            let elapsed = (day - self.day).min(VOLUME_WINDOW_DAYS as u64);
+            for d in day + 1 - elapsed..=day {
+                self.daily_volume[d as usize % VOLUME_WINDOW_DAYS] = ZERO_FRAC;
+            }
+            self.day = day;
+        }
+
+        let bucket = &mut self.daily_volume[self.day as usize % VOLUME_WINDOW_DAYS];
+        *bucket = bucket.checked_add(notional.abs())?;
+        Ok(())
+    }
+
+    pub fn rolling_volume(&self, now: u64) -> MathResult<Fractional> {
+        let day = now / SECONDS_PER_DAY;
+        let window_start = (day + 1).saturating_sub(VOLUME_WINDOW_DAYS as u64);
+        let first_tracked = (self.day + 1).saturating_sub(VOLUME_WINDOW_DAYS as u64);
+
+        let mut volume = ZERO_FRAC;
+        for d in window_start.max(first_tracked)..=self.day {
+            volume = volume.checked_add(self.daily_volume[d as usize % VOLUME_WINDOW_DAYS])?;
+        }
+        Ok(volume)
+    }
+}
@@ -1,5 +1,12 @@
-pub use {fee_model::*, lz4::*, print_trade::*};
+pub use {
+    collateral::*, fee_model::*, fee_tiers::*, lz4::*, order_params::*, print_trade::*,
+    product_params::*,
+};
 
+pub mod collateral;
 pub mod fee_model;
+pub mod fee_tiers;
 pub mod lz4;
+pub mod order_params;
 pub mod print_trade;
+pub mod product_params;
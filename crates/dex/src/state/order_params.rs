@@ -0,0 +1,24 @@
+use spicenet_aaob::{OrderType, SelfTradeHandler};
+use spicenet_shared::{Fractional, Side};
+
+/// Order placed by a TRG through `NewOrder`, prices and sizes are in product units and are
+/// converted to AAOB ticks and lots by the dex
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
+pub struct OrderParams {
+    pub side: Side,
+    /// Must be a multiple of the product tick size once offset by its `price_offset`
+    pub limit_price: Fractional,
+    /// Must be representable with the product `base_decimals`
+    pub max_base_qty: Fractional,
+    pub order_type: OrderType,
+    /// `DecrementTake` is refused, a TRG never trades against itself
+    pub self_trade_behavior: SelfTradeHandler,
+    pub match_limit: u64,
+    pub client_order_id: u64,
+}
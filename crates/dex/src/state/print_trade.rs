@@ -1,8 +1,8 @@
-use borsh::{BorshDeserialize, BorshSerialize};
-use sov_modules_api::{Address, Spec};
+use sov_modules_api::Spec;
 
-use spicenet_shared::{Fractional, MPGId, ProductId, Side};
+use spicenet_shared::{Fractional, MPGId, ProductId, Side, TrgId};
 
+/// Leg of a print trade, `side` is the side taken by the creator
 #[cfg_attr(
     feature = "native",
     derive(serde::Serialize),
@@ -10,9 +10,11 @@ use spicenet_shared::{Fractional, MPGId, ProductId, Side};
     derive(schemars::JsonSchema)
 )]
 #[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
-pub struct PrintTradeProduct<S: Spec> {
-    pub product_key: ProductId, // verify that the product at the given index is this one
-    pub size: Fractional,       // quantity of base (e.g. BTCUSD contract)
+pub struct PrintTradeProduct {
+    pub product_key: ProductId,
+    pub side: Side,
+    pub size: Fractional,  // quantity of base (e.g. BTCUSD contract)
+    pub price: Fractional, // quantity of quote (USD) per base
 }
 
 #[cfg_attr(
@@ -27,42 +29,50 @@ pub struct PrintTradeProductIndex {
     pub size: Fractional, // quantity of base (e.g. BTCUSD contract)
 }
 
-// #[derive(AnchorSerialize, AnchorDeserialize, Default, Debug)] not allowed on arrays
-pub type PrintTradeProducts<S: Spec> = [PrintTradeProduct<S>; PrintTrade::MAX_PRODUCTS_PER_TRADE];
-// #[derive(AnchorSerialize, AnchorDeserialize, Default, Debug)] not allowed on arrays
-pub type PrintTradeProductIndexes = [PrintTradeProductIndex; PrintTrade::MAX_PRODUCTS_PER_TRADE];
+/// Print trades are keyed by creator TRG, counterparty TRG and a seed picked by the creator
+pub type PrintTradeKey<S> = (TrgId<S>, TrgId<S>, u64);
 
+/// Block trade negotiated off-book between two TRGs of an MPG, positions move once the
+/// counterparty signs the exact terms proposed by the creator
 #[cfg_attr(
     feature = "native",
     derive(serde::Serialize),
     derive(serde::Deserialize),
-    derive(schemars::JsonSchema)
+    derive(schemars::JsonSchema),
+    schemars(bound = "S::Address: ::schemars::JsonSchema", rename = "PrintTrade")
 )]
 #[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
 pub struct PrintTrade<S: Spec> {
-    pub is_initialized: bool,
-    pub creator: Address<S>,
-    pub counterparty: Address<S>, // TODO: Unsure what it should be for now, dummy
-    pub seed: Address<S>,         // TODO: Unsure what it should be for now, dummy
-    pub market_product_group: MPGId, // technically might not need to store this
-    pub num_products: usize,
-    pub products: PrintTradeProducts<S>,
-    pub price: Fractional, // quantity of quote (USD) per base
-    pub side: Side,
-    pub operator: Address<S>, // TODO: Unsure what it should be for now, dummy
+    pub creator: TrgId<S>,
+    pub counterparty: TrgId<S>,
+    pub seed: u64,
+    pub market_product_group: MPGId,
+    pub products: Vec<PrintTradeProduct>,
+    /// TRG collecting the operator fees of both sides
+    pub operator: TrgId<S>,
+    /// Share of the notional of the trade paid by the creator to the operator
     pub operator_creator_fee_proportion: Fractional,
+    /// Share of the notional of the trade paid by the counterparty to the operator
     pub operator_counterparty_fee_proportion: Fractional,
     pub is_signed: bool,
     pub is_cancelled: CancelStatus,
-    pub bump: u8,
 }
 
 impl<S: Spec> PrintTrade<S> {
     pub const MAX_PRODUCTS_PER_TRADE: usize = 6;
-    pub const SIZE: usize = std::mem::size_of::<PrintTrade<S>>();
+
+    pub fn is_active(&self) -> bool {
+        !self.is_signed && self.is_cancelled == CancelStatus::Active
+    }
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize, Copy, Clone, Debug, PartialEq)]
 #[repr(u8)]
 pub enum CancelStatus {
     Active,
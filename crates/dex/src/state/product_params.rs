@@ -0,0 +1,56 @@
+use sov_modules_api::SafeString;
+
+use spicenet_shared::{Fractional, ProductId};
+
+/// Parameters of an outright product listed by `InitializeMarketProduct`
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
+pub struct OutrightProductParams {
+    /// Name of the product, at most `NAME_LEN` bytes and unique within the MPG
+    pub name: SafeString,
+    pub price_index: usize,
+    pub tick_size: Fractional,
+    pub base_decimals: u64,
+    pub price_offset: Fractional,
+    /// Minimum order size, in base lots, on the backing AAOB market
+    pub min_base_size: u64,
+    pub mark_price_qualifying_cum_value: Fractional,
+    pub mark_price_max_qualifying_width: Fractional,
+}
+
+/// Leg of a combo, `product_id` must be an active outright of the same MPG
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
+pub struct ComboLegParams {
+    pub product_id: ProductId,
+    /// Signed quantity of the leg per unit of combo, must be non-zero
+    pub ratio: i64,
+}
+
+/// Parameters of a combo product listed by `InitializeCombo`
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone)]
+pub struct ComboProductParams {
+    pub name: SafeString,
+    /// Between 2 and `MAX_LEGS` legs, each outright appearing once
+    pub legs: Vec<ComboLegParams>,
+    pub tick_size: Fractional,
+    pub base_decimals: u64,
+    pub price_offset: Fractional,
+    pub min_base_size: u64,
+}
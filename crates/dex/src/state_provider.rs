@@ -0,0 +1,31 @@
+use anyhow::{bail, Result};
+use sov_modules_api::{Spec, StateAccessor};
+
+use spicenet_shared::risk::DexStateProvider;
+use spicenet_shared::{DexError, MPGId, MarketProductGroup, TraderRiskGroup, TrgId};
+
+use crate::Dex;
+
+impl<S: Spec> DexStateProvider<S> for Dex<S> {
+    fn market_product_group(
+        &self,
+        mpg_id: &MPGId,
+        state: &mut impl StateAccessor,
+    ) -> Result<MarketProductGroup<S>> {
+        match self.market_product_groups.get(mpg_id, state)? {
+            Some(mpg) => Ok(mpg),
+            None => bail!(DexError::MarketProductGroupDoesNotExist),
+        }
+    }
+
+    fn trader_risk_group(
+        &self,
+        trg_id: &TrgId<S>,
+        state: &mut impl StateAccessor,
+    ) -> Result<TraderRiskGroup<S>> {
+        match self.trader_risk_groups.get(trg_id, state)? {
+            Some(trg) => Ok(trg),
+            None => bail!(DexError::TraderRiskGroupDoesNotExist),
+        }
+    }
+}
@@ -2,11 +2,41 @@ use sov_modules_api::digest::Digest;
 use sov_modules_api::CryptoSpec;
 
 use spicenet_shared::addresses::TrgId;
+use spicenet_shared::{MPGId, ProductId};
 
-/// Derives trg ID from `trg_owner`
-pub fn get_trg_id<S: sov_modules_api::Spec>(trg_owner: &str) -> TrgId<S> {
+/// Derives trg ID of `trg_owner` in the MPG `mpg_id`, an owner has at most one TRG per MPG
+pub fn get_trg_id<S: sov_modules_api::Spec>(
+    trg_owner: &S::Address,
+    mpg_id: &MPGId,
+) -> TrgId<S> {
     let mut hasher = <S::CryptoSpec as CryptoSpec>::Hasher::new();
-    hasher.update(trg_owner.as_bytes());
+    hasher.update(trg_owner.as_ref());
+    hasher.update(mpg_id.as_ref());
+    let hash: [u8; 32] = hasher.finalize().into();
+    TrgId::new(&S::Address::from(hash))
+}
+
+/// Id under which the orders of `trg_id` rest on AAOB books, AAOB orders carry a `u64` trg id
+pub fn get_trg_book_id<S: sov_modules_api::Spec>(trg_id: &TrgId<S>) -> u64 {
+    let mut hasher = <S::CryptoSpec as CryptoSpec>::Hasher::new();
+    hasher.update(trg_id.as_ref());
+    let hash: [u8; 32] = hasher.finalize().into();
+    u64::from_le_bytes(hash[..8].try_into().unwrap())
+}
+
+/// Derives MPG ID from `mpg_name`
+pub fn get_mpg_id<S: sov_modules_api::Spec>(mpg_name: &str) -> MPGId {
+    let mut hasher = <S::CryptoSpec as CryptoSpec>::Hasher::new();
+    hasher.update(mpg_name.as_bytes());
+    let hash: [u8; 32] = hasher.finalize().into();
+    hash.into()
+}
+
+/// Derives product ID from `product_name`, names are unique within an MPG
+pub fn get_product_id<S: sov_modules_api::Spec>(mpg_id: &MPGId, product_name: &str) -> ProductId {
+    let mut hasher = <S::CryptoSpec as CryptoSpec>::Hasher::new();
+    hasher.update(mpg_id.as_ref());
+    hasher.update(product_name.as_bytes());
     let hash: [u8; 32] = hasher.finalize().into();
     hash.into()
 }
@@ -0,0 +1,1750 @@
+//! Behaviour of the dex calls, run through a runtime with the modules the dex depends on.
+//!
+//! Every test lists `BTC-PERP` in the `TEST-MPG` market product group with an index price of 100
+//! and a standard deviation of 10. Traders deposit 1000.00 of the gas token, the MPG collateral.
+
+use std::marker::PhantomData;
+
+use sov_bank::{Bank, GAS_TOKEN_ID};
+use sov_modules_api::{Address, ModuleInfo, Spec};
+use sov_test_utils::runtime::genesis::optimistic::HighLevelOptimisticGenesisConfig;
+use sov_test_utils::runtime::TestRunner;
+use sov_test_utils::{
+    generate_optimistic_runtime, AsUser, MockDaSpec, TestUser, TransactionTestCase,
+};
+
+use lut::{LookupTable, LookupTableConfig};
+use spicenet_aaob::{get_order_id, AAOBConfig, OrderType, SelfTradeHandler, AAOB};
+use spicenet_dex::{
+    get_mpg_id, get_product_id, get_trg_id, CallMessage, Dex, DexConfig, Event, FeeTier,
+    OrderParams, OutrightProductParams, PrintTradeProduct,
+};
+use spicenet_risk::call::CallMessage as RiskCallMessage;
+use spicenet_risk::genesis::RiskModuleConfig;
+use spicenet_risk::RiskModule;
+use spicenet_shared::{
+    FastInt, Fractional, MPGId, MarketProductGroup, OutrightProduct, ProductId, ProductStatus,
+    Side, TraderRiskGroup, TrgId, ZERO_FRAC,
+};
+use spicenet_time::{TimeConfig, TimeModule};
+
+pub type S = sov_test_utils::TestSpec;
+
+generate_optimistic_runtime!(
+    TestRuntime <=
+    dex: Dex<S>,
+    risk: RiskModule<S>,
+    aaob: AAOB<S>,
+    time: TimeModule<S>,
+    lut: LookupTable<S>
+);
+
+type Runner = TestRunner<TestRuntime<S, MockDaSpec>, S>;
+
+const MPG_NAME: &str = "TEST-MPG";
+const PRODUCT_NAME: &str = "BTC-PERP";
+const DECIMALS: u64 = 2;
+const INDEX_PRICE: i64 = 100;
+/// Deposited by every trader, 1000.00 in MPG decimals
+const DEPOSIT: u64 = 100_000;
+
+pub struct TestRoles<S: Spec> {
+    /// Authority of the MPG, of the lookup table and of the time module
+    pub admin: TestUser<S>,
+    pub alice: TestUser<S>,
+    pub bob: TestUser<S>,
+    pub carol: TestUser<S>,
+}
+
+fn setup() -> (TestRoles<S>, Runner) {
+    let genesis_config =
+        HighLevelOptimisticGenesisConfig::generate().add_accounts_with_default_balance(4);
+
+    let admin = genesis_config.additional_accounts[0].clone();
+    let alice = genesis_config.additional_accounts[1].clone();
+    let bob = genesis_config.additional_accounts[2].clone();
+    let carol = genesis_config.additional_accounts[3].clone();
+
+    let aaob_config = AAOBConfig::<S> {
+        initial_markets: vec![],
+        admin: admin.address(),
+        _phantom: PhantomData,
+    };
+
+    let time_config = TimeConfig::<S> {
+        sequencer_authority: Address::<S>::from(*admin.address().as_bytes()),
+    };
+
+    let lut_config = LookupTableConfig::<S> {
+        prices: [1u64],
+        aggregate_conf_intervals: [0u32],
+        update_authority: Address::<S>::from(*admin.address().as_bytes()),
+    };
+
+    let genesis_config = GenesisConfig::from_minimal_config(
+        genesis_config.clone().into(),
+        DexConfig::<S> {
+            _phantom: PhantomData,
+        },
+        RiskModuleConfig {
+            mark_price_refresh: None,
+            default_risk_parameters: Default::default(),
+            risk_parameters: vec![],
+        },
+        aaob_config,
+        time_config,
+        lut_config,
+    );
+
+    let mut runner =
+        TestRunner::new_with_genesis(genesis_config.into_genesis_params(), TestRuntime::default());
+
+    // the index price is set before any mark price is initialized from it
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<LookupTable<S>>(lut::CallMessage::MutateAll {
+            prices: [Fractional::new(INDEX_PRICE, 0)],
+            aggregate_conf_intervals: [0],
+        }),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+
+    (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        runner,
+    )
+}
+
+fn mpg_id() -> MPGId {
+    get_mpg_id::<S>(MPG_NAME)
+}
+
+fn product_id() -> ProductId {
+    get_product_id::<S>(&mpg_id(), PRODUCT_NAME)
+}
+
+fn trg_id(user: &TestUser<S>) -> TrgId<S> {
+    get_trg_id::<S>(&user.address(), &mpg_id())
+}
+
+fn cash(amount: u64) -> Fractional {
+    Fractional::new(amount as i64, DECIMALS)
+}
+
+/// Sends `msg` to the dex as `user`, the transaction has to succeed
+fn execute(runner: &mut Runner, user: &TestUser<S>, msg: CallMessage<S>) {
+    runner.execute_transaction(TransactionTestCase {
+        input: user.create_plain_message::<Dex<S>>(msg),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+}
+
+/// Sends `msg` to the dex as `user`, the transaction has to revert
+fn execute_reverted(runner: &mut Runner, user: &TestUser<S>, msg: CallMessage<S>) {
+    runner.execute_transaction(TransactionTestCase {
+        input: user.create_plain_message::<Dex<S>>(msg),
+        assert: Box::new(move |result, _| {
+            assert!(!result.tx_receipt.is_successful());
+        }),
+    });
+}
+
+fn risk(msg: RiskCallMessage<S>) -> CallMessage<S> {
+    CallMessage::Risk { msg }
+}
+
+fn create_mpg_message() -> CallMessage<S> {
+    CallMessage::CreateMarketProductGroup {
+        name: MPG_NAME.try_into().unwrap(),
+        decimals: DECIMALS,
+        collateral_token_id: GAS_TOKEN_ID,
+        ewma_windows: [0; 4],
+        max_maker_fee_bps: 10,
+        min_maker_fee_bps: 0,
+        max_taker_fee_bps: 20,
+        min_taker_fee_bps: 0,
+    }
+}
+
+fn list_product_message() -> CallMessage<S> {
+    CallMessage::InitializeMarketProduct {
+        mpg_id: mpg_id(),
+        params: OutrightProductParams {
+            name: PRODUCT_NAME.try_into().unwrap(),
+            price_index: 0,
+            tick_size: Fractional::new(1, 0),
+            base_decimals: 0,
+            price_offset: ZERO_FRAC,
+            min_base_size: 1,
+            mark_price_qualifying_cum_value: ZERO_FRAC,
+            mark_price_max_qualifying_width: ZERO_FRAC,
+        },
+    }
+}
+
+/// Creates the MPG as `admin`, lists its product and sets up its risk state. Mark prices follow
+/// the index price since no book qualifies.
+fn create_mpg(runner: &mut Runner, admin: &TestUser<S>) {
+    execute(runner, admin, create_mpg_message());
+    execute(runner, admin, list_product_message());
+    execute(
+        runner,
+        admin,
+        risk(RiskCallMessage::InitializeCovarianceMatrix { mpg_id: mpg_id() }),
+    );
+    execute(
+        runner,
+        admin,
+        risk(RiskCallMessage::UpdateCovarianceMatrix {
+            mpg_id: mpg_id(),
+            product_keys: vec![product_id()],
+            standard_deviations: vec![FastInt::from(10)],
+            correlations: vec![vec![FastInt::from(1)]],
+        }),
+    );
+    execute(
+        runner,
+        admin,
+        risk(RiskCallMessage::InitializeMarkPrices {
+            mpg_id: mpg_id(),
+            is_hardcoded_oracle: false,
+            hardcoded_oracle_id: None,
+        }),
+    );
+    execute(
+        runner,
+        admin,
+        risk(RiskCallMessage::UpdateMarkPrices {
+            mpg_id: mpg_id(),
+            product_ids: vec![product_id()],
+        }),
+    );
+}
+
+/// Creates the TRG of `user` and deposits [`DEPOSIT`] into it
+fn open_trg(runner: &mut Runner, user: &TestUser<S>) {
+    execute(
+        runner,
+        user,
+        CallMessage::InitializeTrg { mpg_id: mpg_id() },
+    );
+    execute(
+        runner,
+        user,
+        CallMessage::DepositFunds {
+            trg_id: trg_id(user),
+            amount: DEPOSIT,
+        },
+    );
+}
+
+fn limit_order(trg_id: TrgId<S>, side: Side, price: i64, qty: i64) -> CallMessage<S> {
+    CallMessage::NewOrder {
+        trg_id,
+        product_id: product_id(),
+        order: OrderParams {
+            side,
+            limit_price: Fractional::new(price, 0),
+            max_base_qty: Fractional::new(qty, 0),
+            order_type: OrderType::Limit,
+            self_trade_behavior: SelfTradeHandler::CancelProvide,
+            match_limit: 10,
+            client_order_id: 0,
+        },
+    }
+}
+
+/// Places a limit order of the TRG of `user`
+fn place_order(runner: &mut Runner, user: &TestUser<S>, side: Side, price: i64, qty: i64) {
+    execute(runner, user, limit_order(trg_id(user), side, price, qty));
+}
+
+/// `maker` sells one contract to `taker` at the index price
+fn trade(runner: &mut Runner, maker: &TestUser<S>, taker: &TestUser<S>) {
+    place_order(runner, maker, Side::Ask, INDEX_PRICE, 1);
+    place_order(runner, taker, Side::Bid, INDEX_PRICE, 1);
+}
+
+fn load_trg(runner: &mut Runner, user: &TestUser<S>) -> TraderRiskGroup<S> {
+    let trg_id = trg_id(user);
+    runner.query_state(|state| {
+        Dex::<S>::default()
+            .trader_risk_groups
+            .get(&trg_id, state)
+            .unwrap()
+            .unwrap()
+    })
+}
+
+fn load_mpg(runner: &mut Runner) -> MarketProductGroup<S> {
+    runner.query_state(|state| {
+        Dex::<S>::default()
+            .market_product_groups
+            .get(&mpg_id(), state)
+            .unwrap()
+            .unwrap()
+    })
+}
+
+/// Position of `trg` in the product, zero when it has none
+fn position(trg: &TraderRiskGroup<S>) -> Fractional {
+    match trg.find_position_index(&product_id()) {
+        Some(position_index) => trg.trader_positions[position_index].position,
+        None => ZERO_FRAC,
+    }
+}
+
+fn vault_balance(runner: &mut Runner) -> u64 {
+    let vault: <S as Spec>::Address = Dex::<S>::default().id().to_address();
+    runner.query_state(|state| {
+        Bank::<S>::default()
+            .get_balance_of(&vault, GAS_TOKEN_ID, state)
+            .unwrap()
+            .unwrap_or(0)
+    })
+}
+
+#[test]
+fn create_market_product_group() {
+    let (TestRoles { admin, alice, .. }, mut runner) = setup();
+    let authority = admin.address();
+
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(create_mpg_message()),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result.events.contains(&TestRuntimeEvent::Dex(
+                Event::MarketProductGroupCreated {
+                    mpg_id: mpg_id(),
+                    name: MPG_NAME.to_string(),
+                    mpg_authority: authority.clone(),
+                }
+            )));
+
+            let mpg = Dex::<S>::default()
+                .market_product_groups
+                .get(&mpg_id(), state)
+                .unwrap()
+                .unwrap();
+            assert_eq!(mpg.mpg_authority.get_address(), &authority);
+            assert_eq!(mpg.collected_fees, ZERO_FRAC);
+        }),
+    });
+
+    // the name is taken
+    execute_reverted(&mut runner, &alice, create_mpg_message());
+}
+
+#[test]
+fn only_the_authority_lists_and_removes_products() {
+    let (TestRoles { admin, alice, .. }, mut runner) = setup();
+    execute(&mut runner, &admin, create_mpg_message());
+
+    execute_reverted(&mut runner, &alice, list_product_message());
+
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(list_product_message()),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result.events.iter().any(|event| matches!(
+                event,
+                TestRuntimeEvent::Dex(Event::MarketProductInitialized {
+                    product_id: listed,
+                    product_index: 0,
+                    ..
+                }) if *listed == product_id()
+            )));
+        }),
+    });
+
+    // listed once per name
+    execute_reverted(&mut runner, &admin, list_product_message());
+
+    // a product is only removed once it has expired
+    execute_reverted(
+        &mut runner,
+        &admin,
+        CallMessage::RemoveMarketProduct {
+            mpg_id: mpg_id(),
+            product_id: product_id(),
+        },
+    );
+    assert!(load_mpg(&mut runner)
+        .find_product_index(&product_id())
+        .is_some());
+}
+
+#[test]
+fn update_mpg_authority() {
+    let (TestRoles { admin, alice, .. }, mut runner) = setup();
+    execute(&mut runner, &admin, create_mpg_message());
+
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::UpdateMpgAuthority {
+            mpg_id: mpg_id(),
+            new_authority: alice.address(),
+        },
+    );
+
+    let (old_authority, new_authority) = (admin.address(), alice.address());
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(CallMessage::UpdateMpgAuthority {
+            mpg_id: mpg_id(),
+            new_authority: alice.address(),
+        }),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::MpgAuthorityUpdated {
+                    mpg_id: mpg_id(),
+                    old_authority,
+                    new_authority,
+                })));
+        }),
+    });
+
+    // the previous authority lost its rights
+    execute_reverted(&mut runner, &admin, list_product_message());
+    execute(&mut runner, &alice, list_product_message());
+}
+
+#[test]
+fn deposit_and_withdraw_through_the_vault() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    execute(
+        &mut runner,
+        &alice,
+        CallMessage::InitializeTrg { mpg_id: mpg_id() },
+    );
+
+    let alice_trg_id = trg_id(&alice);
+    runner.execute_transaction(TransactionTestCase {
+        input: alice.create_plain_message::<Dex<S>>(CallMessage::DepositFunds {
+            trg_id: alice_trg_id.clone(),
+            amount: DEPOSIT,
+        }),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::FundsDeposited {
+                    trg_id: alice_trg_id,
+                    amount: DEPOSIT,
+                    cash_balance: cash(DEPOSIT),
+                })));
+        }),
+    });
+    assert_eq!(vault_balance(&mut runner), DEPOSIT);
+
+    // only the owner moves funds of a TRG
+    execute_reverted(
+        &mut runner,
+        &bob,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: 1,
+        },
+    );
+    // more than the cash balance
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: DEPOSIT + 1,
+        },
+    );
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::DepositFunds {
+            trg_id: trg_id(&alice),
+            amount: 0,
+        },
+    );
+
+    execute(
+        &mut runner,
+        &alice,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: 40_000,
+        },
+    );
+
+    assert_eq!(vault_balance(&mut runner), DEPOSIT - 40_000);
+    let trg = load_trg(&mut runner, &alice);
+    assert_eq!(trg.cash_balance, cash(DEPOSIT - 40_000));
+    assert_eq!(trg.total_deposited, cash(DEPOSIT));
+    assert_eq!(trg.total_withdrawn, cash(40_000));
+}
+
+#[test]
+fn collateral_limits_cap_deposits_and_withdrawals() {
+    let (TestRoles { admin, alice, .. }, mut runner) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+
+    let limits = CallMessage::SetCollateralLimits {
+        mpg_id: mpg_id(),
+        deposit_limit: cash(150_000),
+        withdraw_limit: cash(10_000),
+    };
+    execute_reverted(&mut runner, &alice, limits.clone());
+    execute(&mut runner, &admin, limits);
+    execute_reverted(
+        &mut runner,
+        &admin,
+        CallMessage::SetCollateralLimits {
+            mpg_id: mpg_id(),
+            deposit_limit: Fractional::new(-1, 0),
+            withdraw_limit: cash(10_000),
+        },
+    );
+
+    // limits are lifetime totals, the first deposit counts
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::DepositFunds {
+            trg_id: trg_id(&alice),
+            amount: 50_001,
+        },
+    );
+    execute(
+        &mut runner,
+        &alice,
+        CallMessage::DepositFunds {
+            trg_id: trg_id(&alice),
+            amount: 50_000,
+        },
+    );
+
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: 10_001,
+        },
+    );
+    execute(
+        &mut runner,
+        &alice,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: 10_000,
+        },
+    );
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: 1,
+        },
+    );
+
+    assert_eq!(vault_balance(&mut runner), 140_000);
+    assert_eq!(load_trg(&mut runner, &alice).cash_balance, cash(140_000));
+}
+
+#[test]
+fn fill_settles_into_both_trgs() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    place_order(&mut runner, &alice, Side::Ask, 100, 1);
+    assert_eq!(
+        load_trg(&mut runner, &alice).open_orders.total_open_orders,
+        1
+    );
+
+    let bob_trg_id = trg_id(&bob);
+    runner.execute_transaction(TransactionTestCase {
+        input: bob.create_plain_message::<Dex<S>>(limit_order(
+            bob_trg_id.clone(),
+            Side::Bid,
+            100,
+            1,
+        )),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result.events.iter().any(|event| matches!(
+                event,
+                TestRuntimeEvent::Dex(Event::OrderPlaced {
+                    trg_id,
+                    filled_base_qty: 1,
+                    posted_base_qty: 0,
+                    fees,
+                    ..
+                }) if *trg_id == bob_trg_id && *fees > ZERO_FRAC
+            )));
+        }),
+    });
+
+    let alice_trg = load_trg(&mut runner, &alice);
+    let bob_trg = load_trg(&mut runner, &bob);
+    let mpg = load_mpg(&mut runner);
+
+    assert_eq!(position(&alice_trg), Fractional::new(-1, 0));
+    assert_eq!(position(&bob_trg), Fractional::new(1, 0));
+    assert_eq!(alice_trg.open_orders.total_open_orders, 0);
+
+    // the notional moved from the buyer to the seller, fees went to the MPG
+    let notional = Fractional::new(100, 0);
+    assert!(alice_trg.cash_balance < cash(DEPOSIT).checked_add(notional).unwrap());
+    assert!(bob_trg.cash_balance < cash(DEPOSIT).checked_sub(notional).unwrap());
+    assert_eq!(
+        alice_trg
+            .cash_balance
+            .checked_add(bob_trg.cash_balance)
+            .unwrap()
+            .checked_add(mpg.collected_fees)
+            .unwrap(),
+        cash(2 * DEPOSIT)
+    );
+
+    let (_, product) = mpg.find_product_index(&product_id()).unwrap();
+    let outright = product.try_to_outright().unwrap();
+    assert_eq!(outright.open_long_interest, Fractional::new(1, 0));
+    assert_eq!(outright.open_short_interest, Fractional::new(1, 0));
+}
+
+#[test]
+fn cancel_order() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    place_order(&mut runner, &alice, Side::Ask, 105, 2);
+
+    // the first order of the market, its price is in fp32 ticks
+    let order_id = get_order_id(Side::Ask, 105 << 32, 0);
+    let cancel = CallMessage::CancelOrder {
+        trg_id: trg_id(&alice),
+        product_id: product_id(),
+        order_id,
+    };
+
+    execute_reverted(&mut runner, &bob, cancel.clone());
+    execute_reverted(
+        &mut runner,
+        &bob,
+        CallMessage::CancelOrder {
+            trg_id: trg_id(&bob),
+            product_id: product_id(),
+            order_id,
+        },
+    );
+
+    let alice_trg_id = trg_id(&alice);
+    runner.execute_transaction(TransactionTestCase {
+        input: alice.create_plain_message::<Dex<S>>(cancel),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::OrderCancelled {
+                    trg_id: alice_trg_id,
+                    product_id: product_id(),
+                    order_id,
+                })));
+        }),
+    });
+
+    let alice_trg = load_trg(&mut runner, &alice);
+    assert_eq!(alice_trg.open_orders.total_open_orders, 0);
+    assert_eq!(alice_trg.cash_balance, cash(DEPOSIT));
+
+    // nothing rests anymore, a bid at the same price posts
+    place_order(&mut runner, &bob, Side::Bid, 105, 1);
+    assert_eq!(position(&load_trg(&mut runner, &bob)), ZERO_FRAC);
+}
+
+#[test]
+fn fills_pay_the_mpg_maximum_fees_without_fee_tiers() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    trade(&mut runner, &alice, &bob);
+
+    // 10 bps for the maker and 20 bps for the taker on a notional of 100
+    assert_eq!(load_trg(&mut runner, &alice).cash_balance, cash(109_990));
+    assert_eq!(load_trg(&mut runner, &bob).cash_balance, cash(89_980));
+    assert_eq!(load_mpg(&mut runner).collected_fees, cash(30));
+}
+
+#[test]
+fn fills_on_both_sides_record_positive_volume() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    // alice makes a sell to bob, then bob makes a sell to alice
+    trade(&mut runner, &alice, &bob);
+    trade(&mut runner, &bob, &alice);
+
+    let notional = Fractional::new(INDEX_PRICE, 0);
+    for user in [&alice, &bob] {
+        let trg = load_trg(&mut runner, user);
+        assert_eq!(position(&trg), ZERO_FRAC);
+        assert_eq!(trg.notional_maker_volume, notional);
+        assert_eq!(trg.notional_taker_volume, notional);
+
+        let trg_id = trg_id(user);
+        let volume = runner.query_state(|state| {
+            Dex::<S>::default()
+                .trader_volumes
+                .get(&trg_id, state)
+                .unwrap()
+                .unwrap()
+        });
+        let total = volume
+            .daily_volume
+            .iter()
+            .fold(ZERO_FRAC, |total, day| total.checked_add(*day).unwrap());
+        assert_eq!(total, Fractional::new(2 * INDEX_PRICE, 0));
+    }
+
+    let mpg = load_mpg(&mut runner);
+    let (_, product) = mpg.find_product_index(&product_id()).unwrap();
+    assert_eq!(
+        product.notional_traded_volume,
+        Fractional::new(2 * INDEX_PRICE, 0)
+    );
+}
+
+#[test]
+fn fee_bounds_of_a_new_mpg() {
+    let (TestRoles { admin, .. }, mut runner) = setup();
+
+    let with_fees = |min_maker_fee_bps: i16, max_maker_fee_bps: i16, min_taker_fee_bps: i16| {
+        CallMessage::<S>::CreateMarketProductGroup {
+            name: MPG_NAME.try_into().unwrap(),
+            decimals: DECIMALS,
+            collateral_token_id: GAS_TOKEN_ID,
+            ewma_windows: [0; 4],
+            max_maker_fee_bps,
+            min_maker_fee_bps,
+            max_taker_fee_bps: 20,
+            min_taker_fee_bps,
+        }
+    };
+
+    // min above max
+    execute_reverted(&mut runner, &admin, with_fees(11, 10, 0));
+    // maker rebates larger than the taker fees
+    execute_reverted(&mut runner, &admin, with_fees(-5, 10, 4));
+    execute(&mut runner, &admin, with_fees(-5, 10, 5));
+}
+
+#[test]
+fn sweep_fees() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    // nothing collected yet
+    execute_reverted(
+        &mut runner,
+        &admin,
+        CallMessage::SweepFees { mpg_id: mpg_id() },
+    );
+
+    trade(&mut runner, &alice, &bob);
+
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::SweepFees { mpg_id: mpg_id() },
+    );
+
+    let vault_before = vault_balance(&mut runner);
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(CallMessage::SweepFees { mpg_id: mpg_id() }),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::FeesSwept {
+                    mpg_id: mpg_id(),
+                    amount: 30,
+                    collected_fees: ZERO_FRAC,
+                })));
+        }),
+    });
+
+    assert_eq!(vault_balance(&mut runner), vault_before - 30);
+    assert_eq!(load_mpg(&mut runner).collected_fees, ZERO_FRAC);
+}
+
+#[test]
+fn fee_tiers() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    let tier = |min_volume: i64, maker_fee_bps: i32, taker_fee_bps: i32| FeeTier {
+        min_volume: Fractional::new(min_volume, 0),
+        maker_fee_bps,
+        taker_fee_bps,
+    };
+    let set_tiers = |tiers: Vec<FeeTier>| CallMessage::<S>::SetFeeTiers {
+        mpg_id: mpg_id(),
+        tiers,
+    };
+
+    execute_reverted(&mut runner, &alice, set_tiers(vec![tier(0, 0, 10)]));
+    // the first tier starts at zero volume
+    execute_reverted(&mut runner, &admin, set_tiers(vec![tier(1, 0, 10)]));
+    // volumes strictly increase
+    execute_reverted(
+        &mut runner,
+        &admin,
+        set_tiers(vec![tier(0, 0, 10), tier(0, 0, 5)]),
+    );
+    // fees stay within the MPG bounds
+    execute_reverted(&mut runner, &admin, set_tiers(vec![tier(0, 0, 21)]));
+    execute_reverted(&mut runner, &admin, set_tiers(vec![tier(0, -1, 10)]));
+
+    runner.execute_transaction(TransactionTestCase {
+        input: admin
+            .create_plain_message::<Dex<S>>(set_tiers(vec![tier(0, 0, 10), tier(1_000, 0, 5)])),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::FeeTiersUpdated {
+                    mpg_id: mpg_id(),
+                    tiers: vec![
+                        (Fractional::new(0, 0), 0, 10),
+                        (Fractional::new(1_000, 0), 0, 5),
+                    ],
+                })));
+        }),
+    });
+
+    trade(&mut runner, &alice, &bob);
+
+    // both traders are in the first tier
+    assert_eq!(load_trg(&mut runner, &alice).cash_balance, cash(110_000));
+    assert_eq!(load_trg(&mut runner, &bob).cash_balance, cash(89_990));
+    assert_eq!(load_mpg(&mut runner).collected_fees, cash(10));
+}
+
+#[test]
+fn referral_fees() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+    open_trg(&mut runner, &carol);
+
+    let register = CallMessage::RegisterReferrer {
+        trg_id: trg_id(&carol),
+    };
+    execute(&mut runner, &carol, register.clone());
+    execute_reverted(&mut runner, &carol, register);
+
+    // alice is not a referrer
+    execute_reverted(
+        &mut runner,
+        &bob,
+        CallMessage::SetReferrer {
+            trg_id: trg_id(&bob),
+            referrer: trg_id(&alice),
+        },
+    );
+    let set_referrer = CallMessage::SetReferrer {
+        trg_id: trg_id(&bob),
+        referrer: trg_id(&carol),
+    };
+    execute(&mut runner, &bob, set_referrer.clone());
+    // the referrer is set once
+    execute_reverted(&mut runner, &bob, set_referrer);
+
+    trade(&mut runner, &alice, &bob);
+
+    // a tenth of the 0.20 taker fee of bob
+    assert_eq!(load_trg(&mut runner, &carol).referral_fees, cash(2));
+    assert_eq!(load_mpg(&mut runner).collected_fees, cash(28));
+
+    let claim = CallMessage::ClaimReferralFees {
+        trg_id: trg_id(&carol),
+    };
+    execute_reverted(&mut runner, &bob, claim.clone());
+    execute(&mut runner, &carol, claim.clone());
+    execute_reverted(&mut runner, &carol, claim);
+
+    let carol_trg = load_trg(&mut runner, &carol);
+    assert_eq!(carol_trg.referral_fees, ZERO_FRAC);
+    assert_eq!(carol_trg.cash_balance, cash(DEPOSIT + 2));
+}
+
+/// The creator buys `size` contracts at 100 from the counterparty
+fn print_trade_products(size: i64) -> Vec<PrintTradeProduct> {
+    vec![PrintTradeProduct {
+        product_key: product_id(),
+        side: Side::Bid,
+        size: Fractional::new(size, 0),
+        price: Fractional::new(INDEX_PRICE, 0),
+    }]
+}
+
+fn initialize_print_trade(creator: &TestUser<S>, counterparty: &TestUser<S>) -> CallMessage<S> {
+    CallMessage::InitializePrintTrade {
+        creator: trg_id(creator),
+        counterparty: trg_id(counterparty),
+        seed: 7,
+        products: print_trade_products(2),
+        operator: trg_id(creator),
+        operator_creator_fee_proportion: ZERO_FRAC,
+        operator_counterparty_fee_proportion: Fractional::new(1, 2),
+    }
+}
+
+fn sign_print_trade(
+    creator: &TestUser<S>,
+    counterparty: &TestUser<S>,
+    products: Vec<PrintTradeProduct>,
+) -> CallMessage<S> {
+    CallMessage::SignPrintTrade {
+        creator: trg_id(creator),
+        counterparty: trg_id(counterparty),
+        seed: 7,
+        products,
+        operator_creator_fee_proportion: ZERO_FRAC,
+        operator_counterparty_fee_proportion: Fractional::new(1, 2),
+    }
+}
+
+#[test]
+fn print_trade_is_signed_by_the_counterparty() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    execute_reverted(&mut runner, &bob, initialize_print_trade(&alice, &bob));
+    execute(&mut runner, &alice, initialize_print_trade(&alice, &bob));
+    // the seed is taken
+    execute_reverted(&mut runner, &alice, initialize_print_trade(&alice, &bob));
+
+    // only the counterparty signs, and only the proposed terms
+    execute_reverted(
+        &mut runner,
+        &alice,
+        sign_print_trade(&alice, &bob, print_trade_products(2)),
+    );
+    execute_reverted(
+        &mut runner,
+        &bob,
+        sign_print_trade(&alice, &bob, print_trade_products(3)),
+    );
+
+    let (creator, counterparty) = (trg_id(&alice), trg_id(&bob));
+    runner.execute_transaction(TransactionTestCase {
+        input: bob.create_plain_message::<Dex<S>>(sign_print_trade(
+            &alice,
+            &bob,
+            print_trade_products(2),
+        )),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::PrintTradeSigned {
+                    creator,
+                    counterparty,
+                    seed: 7,
+                    notional: Fractional::new(200, 0),
+                    operator_fees: Fractional::new(2, 0),
+                })));
+        }),
+    });
+
+    // the creator bought, the counterparty paid 1% of the notional to the creator as operator
+    let alice_trg = load_trg(&mut runner, &alice);
+    let bob_trg = load_trg(&mut runner, &bob);
+    assert_eq!(position(&alice_trg), Fractional::new(2, 0));
+    assert_eq!(position(&bob_trg), Fractional::new(-2, 0));
+    assert_eq!(alice_trg.cash_balance, cash(DEPOSIT - 20_000 + 200));
+    assert_eq!(bob_trg.cash_balance, cash(DEPOSIT + 20_000 - 200));
+
+    // a signed print trade is not active anymore
+    execute_reverted(
+        &mut runner,
+        &bob,
+        sign_print_trade(&alice, &bob, print_trade_products(2)),
+    );
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::CancelPrintTrade {
+            creator: trg_id(&alice),
+            counterparty: trg_id(&bob),
+            seed: 7,
+        },
+    );
+}
+
+#[test]
+fn cancelled_print_trade_can_not_be_signed() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    execute(&mut runner, &alice, initialize_print_trade(&alice, &bob));
+
+    let cancel = CallMessage::CancelPrintTrade {
+        creator: trg_id(&alice),
+        counterparty: trg_id(&bob),
+        seed: 7,
+    };
+    execute_reverted(&mut runner, &carol, cancel.clone());
+
+    let (creator, counterparty) = (trg_id(&alice), trg_id(&bob));
+    runner.execute_transaction(TransactionTestCase {
+        input: bob.create_plain_message::<Dex<S>>(cancel.clone()),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::PrintTradeCancelled {
+                    creator,
+                    counterparty,
+                    seed: 7,
+                    by_creator: false,
+                })));
+        }),
+    });
+
+    execute_reverted(&mut runner, &alice, cancel);
+    execute_reverted(
+        &mut runner,
+        &bob,
+        sign_print_trade(&alice, &bob, print_trade_products(2)),
+    );
+
+    assert_eq!(position(&load_trg(&mut runner, &alice)), ZERO_FRAC);
+    assert_eq!(load_trg(&mut runner, &bob).cash_balance, cash(DEPOSIT));
+}
+
+#[test]
+fn healthy_trgs_are_not_liquidated() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            carol,
+            ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &carol);
+
+    let transfer = CallMessage::TransferFullPosition {
+        liquidatee: trg_id(&alice),
+        liquidator: trg_id(&carol),
+    };
+    // the liquidator is owned by the sender
+    execute_reverted(&mut runner, &alice, transfer.clone());
+    execute_reverted(&mut runner, &carol, transfer);
+
+    assert_eq!(load_trg(&mut runner, &alice).cash_balance, cash(DEPOSIT));
+    assert_eq!(load_trg(&mut runner, &carol).cash_balance, cash(DEPOSIT));
+}
+
+#[test]
+fn liquidation_with_social_loss() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+    open_trg(&mut runner, &carol);
+
+    trade(&mut runner, &alice, &bob);
+
+    let transfer = |liquidatee: &TestUser<S>| CallMessage::TransferFullPosition {
+        liquidatee: trg_id(liquidatee),
+        liquidator: trg_id(&carol),
+    };
+
+    // healthy TRGs are not liquidatable
+    execute_reverted(&mut runner, &carol, transfer(&alice));
+
+    // funding wipes out the cash of the short, its portfolio is worth less than nothing
+    execute(
+        &mut runner,
+        &admin,
+        CallMessage::UpdateProductFunding {
+            mpg_id: mpg_id(),
+            product_id: product_id(),
+            amount: Fractional::new(1_100, 0),
+            new_product_status: ProductStatus::Initialized,
+        },
+    );
+    let funding = Fractional::new(1_100, 0);
+    let alice_cash = load_trg(&mut runner, &alice)
+        .cash_balance
+        .checked_sub(funding)
+        .unwrap();
+    let bob_cash = load_trg(&mut runner, &bob).cash_balance;
+    assert!(alice_cash < ZERO_FRAC);
+
+    // the liquidator is owned by the sender
+    execute_reverted(&mut runner, &bob, transfer(&alice));
+
+    let (liquidatee, liquidator) = (trg_id(&alice), trg_id(&carol));
+    runner.execute_transaction(TransactionTestCase {
+        input: carol.create_plain_message::<Dex<S>>(transfer(&alice)),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result.events.iter().any(|event| matches!(
+                event,
+                TestRuntimeEvent::Dex(Event::PositionTransferred {
+                    liquidatee: transferred,
+                    liquidator: receiver,
+                    liquidation_price,
+                    total_social_loss,
+                }) if *transferred == liquidatee
+                    && *receiver == liquidator
+                    && *total_social_loss > ZERO_FRAC
+                    && *liquidation_price == -*total_social_loss
+            )));
+        }),
+    });
+
+    let alice_trg = load_trg(&mut runner, &alice);
+    let carol_trg = load_trg(&mut runner, &carol);
+    assert_eq!(position(&alice_trg), ZERO_FRAC);
+    assert_eq!(alice_trg.cash_balance, ZERO_FRAC);
+    assert_eq!(position(&carol_trg), Fractional::new(-1, 0));
+    // carol took over the cash of alice and was paid the social loss
+    let social_loss = carol_trg
+        .cash_balance
+        .checked_sub(cash(DEPOSIT))
+        .unwrap()
+        .checked_sub(alice_cash)
+        .unwrap();
+    assert!(social_loss > ZERO_FRAC);
+
+    // the loss is shared by the open interest of the product
+    let mpg = load_mpg(&mut runner);
+    let (_, product) = mpg.find_product_index(&product_id()).unwrap();
+    let outright = product.try_to_outright().unwrap();
+    let loss_per_share = outright.cumulative_social_loss_per_share;
+    assert!(loss_per_share > ZERO_FRAC);
+    assert_eq!(outright.open_long_interest, Fractional::new(1, 0));
+    assert_eq!(outright.open_short_interest, Fractional::new(1, 0));
+
+    // bob realizes its funding and its share of the loss on settlement
+    execute(
+        &mut runner,
+        &alice,
+        CallMessage::SettleFunding {
+            trg_id: trg_id(&bob),
+        },
+    );
+    assert_eq!(
+        load_trg(&mut runner, &bob).cash_balance,
+        bob_cash
+            .checked_add(funding)
+            .unwrap()
+            .checked_sub(loss_per_share)
+            .unwrap()
+    );
+}
+
+/// Cash of `trg` once the funding and social loss accrued by its position in `outright` are
+/// realized
+fn settled_cash(trg: &TraderRiskGroup<S>, outright: &OutrightProduct) -> Fractional {
+    let position_index = trg.find_position_index(&product_id()).unwrap();
+    let position = &trg.trader_positions[position_index];
+    let funding = outright
+        .cumulative_funding_per_share
+        .checked_sub(position.last_cum_funding_snapshot)
+        .unwrap()
+        .checked_mul(position.position)
+        .unwrap();
+    let social_loss = outright
+        .cumulative_social_loss_per_share
+        .checked_sub(position.last_social_loss_snapshot)
+        .unwrap()
+        .checked_mul(position.position.abs())
+        .unwrap();
+
+    trg.cash_balance
+        .checked_add(funding)
+        .unwrap()
+        .checked_sub(social_loss)
+        .unwrap()
+}
+
+#[test]
+fn position_transfer_realizes_the_social_loss_of_both_trgs() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+    open_trg(&mut runner, &carol);
+
+    trade(&mut runner, &alice, &bob);
+    let funding = |amount: i64| CallMessage::UpdateProductFunding {
+        mpg_id: mpg_id(),
+        product_id: product_id(),
+        amount: Fractional::new(amount, 0),
+        new_product_status: ProductStatus::Initialized,
+    };
+    let transfer = |liquidatee: &TestUser<S>| CallMessage::TransferFullPosition {
+        liquidatee: trg_id(liquidatee),
+        liquidator: trg_id(&carol),
+    };
+
+    // carol takes over the short of alice, the social loss of the liquidation is left unrealized
+    // by both bob and carol
+    execute(&mut runner, &admin, funding(1_100));
+    execute(&mut runner, &carol, transfer(&alice));
+    let mpg = load_mpg(&mut runner);
+    let (_, product) = mpg.find_product_index(&product_id()).unwrap();
+    let loss_per_share = product
+        .try_to_outright()
+        .unwrap()
+        .cumulative_social_loss_per_share;
+    assert!(loss_per_share > ZERO_FRAC);
+    for user in [&bob, &carol] {
+        let trg = load_trg(&mut runner, user);
+        let position_index = trg.find_position_index(&product_id()).unwrap();
+        assert!(trg.trader_positions[position_index].last_social_loss_snapshot < loss_per_share);
+    }
+
+    // funding now wipes out the cash of the long
+    execute(&mut runner, &admin, funding(-2_300));
+    let mpg = load_mpg(&mut runner);
+    let (_, product) = mpg.find_product_index(&product_id()).unwrap();
+    let outright = product.try_to_outright().unwrap();
+    let bob_cash = settled_cash(&load_trg(&mut runner, &bob), outright);
+    let carol_cash = settled_cash(&load_trg(&mut runner, &carol), outright);
+    assert!(bob_cash < ZERO_FRAC);
+
+    let (liquidatee, liquidator) = (trg_id(&bob), trg_id(&carol));
+    runner.execute_transaction(TransactionTestCase {
+        input: carol.create_plain_message::<Dex<S>>(transfer(&bob)),
+        assert: Box::new(move |result, state| {
+            assert!(result.tx_receipt.is_successful());
+            let liquidation_price = result
+                .events
+                .iter()
+                .find_map(|event| match event {
+                    TestRuntimeEvent::Dex(Event::PositionTransferred {
+                        liquidation_price, ..
+                    }) => Some(*liquidation_price),
+                    _ => None,
+                })
+                .unwrap();
+
+            // both TRGs realized the loss before bob's cash moved to carol
+            let dex = Dex::<S>::default();
+            let bob_trg = dex
+                .trader_risk_groups
+                .get(&liquidatee, state)
+                .unwrap()
+                .unwrap();
+            let carol_trg = dex
+                .trader_risk_groups
+                .get(&liquidator, state)
+                .unwrap()
+                .unwrap();
+            assert_eq!(position(&bob_trg), ZERO_FRAC);
+            assert_eq!(position(&carol_trg), ZERO_FRAC);
+            assert_eq!(bob_trg.cash_balance, liquidation_price.max(ZERO_FRAC));
+            assert_eq!(
+                carol_trg.cash_balance,
+                carol_cash
+                    .checked_add(bob_cash)
+                    .unwrap()
+                    .checked_sub(liquidation_price)
+                    .unwrap()
+            );
+        }),
+    });
+}
+
+#[test]
+fn funding_is_paid_by_shorts_to_longs() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    trade(&mut runner, &alice, &bob);
+    let alice_cash = load_trg(&mut runner, &alice).cash_balance;
+    let bob_cash = load_trg(&mut runner, &bob).cash_balance;
+
+    let update_funding = |amount: Fractional| CallMessage::UpdateProductFunding {
+        mpg_id: mpg_id(),
+        product_id: product_id(),
+        amount,
+        new_product_status: ProductStatus::Initialized,
+    };
+    execute_reverted(&mut runner, &alice, update_funding(Fractional::new(2, 0)));
+
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(update_funding(Fractional::new(2, 0))),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::ProductFundingUpdated {
+                    mpg_id: mpg_id(),
+                    product_id: product_id(),
+                    amount: Fractional::new(2, 0),
+                    cumulative_funding_per_share: Fractional::new(2, 0),
+                })));
+        }),
+    });
+
+    // anyone settles funding
+    let alice_trg_id = trg_id(&alice);
+    let expected_cash = alice_cash.checked_sub(Fractional::new(2, 0)).unwrap();
+    runner.execute_transaction(TransactionTestCase {
+        input: carol.create_plain_message::<Dex<S>>(CallMessage::SettleFunding {
+            trg_id: alice_trg_id.clone(),
+        }),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::FundingSettled {
+                    trg_id: alice_trg_id,
+                    cash_balance: expected_cash,
+                })));
+        }),
+    });
+    execute(
+        &mut runner,
+        &carol,
+        CallMessage::SettleFunding {
+            trg_id: trg_id(&bob),
+        },
+    );
+    assert_eq!(
+        load_trg(&mut runner, &bob).cash_balance,
+        bob_cash.checked_add(Fractional::new(2, 0)).unwrap()
+    );
+
+    // negative funding is paid by longs to shorts, settled again it is only counted once
+    execute(&mut runner, &admin, update_funding(Fractional::new(-3, 0)));
+    for user in [&alice, &bob, &alice] {
+        execute(
+            &mut runner,
+            &carol,
+            CallMessage::SettleFunding {
+                trg_id: trg_id(user),
+            },
+        );
+    }
+    assert_eq!(
+        load_trg(&mut runner, &alice).cash_balance,
+        alice_cash.checked_add(Fractional::new(1, 0)).unwrap()
+    );
+    assert_eq!(
+        load_trg(&mut runner, &bob).cash_balance,
+        bob_cash.checked_sub(Fractional::new(1, 0)).unwrap()
+    );
+}
+
+#[test]
+fn expired_product_is_cleared_and_removed() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    trade(&mut runner, &alice, &bob);
+    place_order(&mut runner, &alice, Side::Ask, 105, 1);
+    let alice_cash = load_trg(&mut runner, &alice).cash_balance;
+    let bob_cash = load_trg(&mut runner, &bob).cash_balance;
+
+    let clear_orderbook = CallMessage::ClearExpiredOrderbook {
+        mpg_id: mpg_id(),
+        product_id: product_id(),
+        num_orders: 10,
+    };
+    let clear_position = |user: &TestUser<S>| CallMessage::ClearExpiredPosition {
+        trg_id: trg_id(user),
+        product_id: product_id(),
+    };
+
+    execute_reverted(&mut runner, &carol, clear_orderbook.clone());
+
+    // the final payoff of 5 per contract is paid as funding
+    execute(
+        &mut runner,
+        &admin,
+        CallMessage::UpdateProductFunding {
+            mpg_id: mpg_id(),
+            product_id: product_id(),
+            amount: Fractional::new(5, 0),
+            new_product_status: ProductStatus::Expiring,
+        },
+    );
+
+    // positions are cleared once the book is empty
+    execute_reverted(&mut runner, &carol, clear_position(&alice));
+
+    runner.execute_transaction(TransactionTestCase {
+        input: carol.create_plain_message::<Dex<S>>(clear_orderbook),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::ProductExpired {
+                    mpg_id: mpg_id(),
+                    product_id: product_id(),
+                })));
+            assert!(result.events.contains(&TestRuntimeEvent::Dex(
+                Event::ExpiredOrderbookCleared {
+                    mpg_id: mpg_id(),
+                    product_id: product_id(),
+                    num_cancelled: 1,
+                    is_book_empty: true,
+                }
+            )));
+        }),
+    });
+    assert_eq!(
+        load_trg(&mut runner, &alice).open_orders.total_open_orders,
+        0
+    );
+
+    // open interest is left
+    execute_reverted(
+        &mut runner,
+        &admin,
+        CallMessage::RemoveMarketProduct {
+            mpg_id: mpg_id(),
+            product_id: product_id(),
+        },
+    );
+
+    let alice_trg_id = trg_id(&alice);
+    let alice_expected_cash = alice_cash.checked_sub(Fractional::new(5, 0)).unwrap();
+    runner.execute_transaction(TransactionTestCase {
+        input: carol.create_plain_message::<Dex<S>>(clear_position(&alice)),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result.events.contains(&TestRuntimeEvent::Dex(
+                Event::ExpiredPositionCleared {
+                    trg_id: alice_trg_id,
+                    product_id: product_id(),
+                    position: Fractional::new(-1, 0),
+                    cash_balance: alice_expected_cash,
+                }
+            )));
+        }),
+    });
+
+    // the last position cleared delists the product
+    runner.execute_transaction(TransactionTestCase {
+        input: carol.create_plain_message::<Dex<S>>(clear_position(&bob)),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::MarketProductRemoved {
+                    mpg_id: mpg_id(),
+                    product_id: product_id(),
+                })));
+        }),
+    });
+
+    let bob_trg = load_trg(&mut runner, &bob);
+    assert_eq!(position(&bob_trg), ZERO_FRAC);
+    assert_eq!(
+        bob_trg.cash_balance,
+        bob_cash.checked_add(Fractional::new(5, 0)).unwrap()
+    );
+    assert!(load_mpg(&mut runner)
+        .find_product_index(&product_id())
+        .is_none());
+}
+
+#[test]
+fn kill_switch_leaves_only_withdrawals_and_cancels() {
+    let (
+        TestRoles {
+            admin,
+            alice,
+            bob,
+            carol,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+    place_order(&mut runner, &bob, Side::Bid, 95, 1);
+
+    let kill_switch = |on: bool| CallMessage::SetKillSwitch {
+        mpg_id: mpg_id(),
+        on,
+    };
+    execute_reverted(&mut runner, &alice, kill_switch(true));
+
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(kill_switch(true)),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::KillSwitchSet {
+                    mpg_id: mpg_id(),
+                    on: true,
+                })));
+        }),
+    });
+    assert!(load_mpg(&mut runner).is_mpg_killed);
+
+    execute_reverted(
+        &mut runner,
+        &carol,
+        CallMessage::InitializeTrg { mpg_id: mpg_id() },
+    );
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::DepositFunds {
+            trg_id: trg_id(&alice),
+            amount: 1,
+        },
+    );
+    execute_reverted(
+        &mut runner,
+        &alice,
+        limit_order(trg_id(&alice), Side::Ask, 95, 1),
+    );
+    execute_reverted(&mut runner, &alice, initialize_print_trade(&alice, &bob));
+    execute_reverted(&mut runner, &admin, list_product_message());
+
+    // traders can still get out
+    execute(
+        &mut runner,
+        &bob,
+        CallMessage::CancelOrder {
+            trg_id: trg_id(&bob),
+            product_id: product_id(),
+            order_id: get_order_id(Side::Bid, 95 << 32, 0),
+        },
+    );
+    execute(
+        &mut runner,
+        &alice,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: 50_000,
+        },
+    );
+
+    execute(&mut runner, &admin, kill_switch(false));
+    execute(
+        &mut runner,
+        &alice,
+        CallMessage::DepositFunds {
+            trg_id: trg_id(&alice),
+            amount: 50_000,
+        },
+    );
+    assert_eq!(load_trg(&mut runner, &alice).cash_balance, cash(DEPOSIT));
+}
+
+#[test]
+fn admin_mode_restricts_traders_to_the_authority() {
+    let (
+        TestRoles {
+            admin, alice, bob, ..
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &alice);
+    open_trg(&mut runner, &bob);
+
+    let admin_mode = |on: bool| CallMessage::SetAdminMode {
+        mpg_id: mpg_id(),
+        on,
+    };
+    execute_reverted(&mut runner, &alice, admin_mode(true));
+
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(admin_mode(true)),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Dex(Event::AdminModeSet {
+                    mpg_id: mpg_id(),
+                    on: true,
+                })));
+        }),
+    });
+    assert!(load_mpg(&mut runner).in_admin_mode);
+
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::DepositFunds {
+            trg_id: trg_id(&alice),
+            amount: 1,
+        },
+    );
+    execute_reverted(
+        &mut runner,
+        &alice,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&alice),
+            amount: 1,
+        },
+    );
+    execute_reverted(
+        &mut runner,
+        &alice,
+        limit_order(trg_id(&alice), Side::Ask, INDEX_PRICE, 1),
+    );
+    execute_reverted(&mut runner, &alice, initialize_print_trade(&alice, &bob));
+
+    // the authority keeps trading with its own TRG
+    open_trg(&mut runner, &admin);
+    execute(
+        &mut runner,
+        &admin,
+        CallMessage::WithdrawFunds {
+            trg_id: trg_id(&admin),
+            amount: 1,
+        },
+    );
+    place_order(&mut runner, &admin, Side::Ask, INDEX_PRICE, 1);
+
+    execute(&mut runner, &admin, admin_mode(false));
+    place_order(&mut runner, &bob, Side::Bid, INDEX_PRICE, 1);
+    assert_eq!(
+        position(&load_trg(&mut runner, &bob)),
+        Fractional::new(1, 0)
+    );
+    assert_eq!(
+        position(&load_trg(&mut runner, &admin)),
+        Fractional::new(-1, 0)
+    );
+}
@@ -1,6 +1,7 @@
 use sov_modules_api::{Address, CallResponse, Context, EventEmitter, Spec, TxState};
 
-use spicenet_shared::{Fractional, MPGId, UtilError, ZERO_FRAC};
+use spicenet_dex::get_product_id;
+use spicenet_shared::{Fractional, MPGId, ProductStatus, UtilError, ZERO_FRAC};
 
 use spicenet_shared::dex::constants::{NO_ASK_PRICE, NO_BID_PRICE};
 use spicenet_shared::dex::{DexError, Product};
@@ -143,15 +144,31 @@ impl<S: Spec> Instruments<S> {
             .unwrap()
             .unwrap();
 
-        let funding_amount = &self.get_funding_amount(
+        let funding_amount = self.get_funding_amount(
             &mut derivative_metadata,
             params.index_settle_recurring_derivative,
             context,
             state,
-        );
+        )
+        .map_err(|_| DexError::FundingPrecisionError)?;
 
+        let new_product_status = match derivative_metadata.expiration_status {
+            ExpirationStatus::Expiring => ProductStatus::Expiring,
+            _ => ProductStatus::Initialized,
+        };
+        let product_id = get_product_id::<S>(
+            &derivative_metadata.market_product_group,
+            &derivative_metadata.derivative_name,
+        );
         self.dex
-            .update_product_funding_in_dex(&derivative_metadata, funding_amount, state)?;
+            .update_product_funding(
+                derivative_metadata.market_product_group,
+                &product_id,
+                funding_amount,
+                new_product_status,
+                state,
+            )
+            .map_err(|_| DexError::TransactionAborted)?;
 
         self.derivative_metadata
             .set(&derivative_id, &derivative_metadata, state);
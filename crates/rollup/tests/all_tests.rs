@@ -1,3 +1,4 @@
 mod bank;
+mod dex;
 // Add additional tests here
 mod test_helpers;
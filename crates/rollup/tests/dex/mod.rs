@@ -0,0 +1,118 @@
+use super::test_helpers::{read_private_keys, start_rollup};
+use anyhow::Context;
+use futures::StreamExt;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use sov_cli::NodeClient;
+use sov_mock_da::{BlockProducingConfig, MockAddress, MockDaConfig, MockDaSpec};
+use sov_mock_zkvm::MockZkvm;
+use sov_modules_api::execution_mode::Native;
+use sov_modules_api::macros::config_value;
+use sov_modules_api::transaction::{PriorityFeeBips, Transaction, UnsignedTransaction};
+use sov_stf_runner::processes::RollupProverConfig;
+use spicenet_dex::{get_mpg_id, DexRpcClient};
+use spicenet_stf::chain_hash::CHAIN_HASH;
+use spicenet_stf::genesis_config::GenesisPaths;
+use spicenet_stf::RuntimeCall;
+
+const MPG_NAME: &str = "RUNTIME-MPG";
+const MAX_TX_FEE: u64 = 100_000_000;
+
+type TestSpec = sov_modules_api::default_spec::DefaultSpec<MockDaSpec, MockZkvm, MockZkvm, Native>;
+
+/// The dex is part of the runtime: its calls are dispatched and its state is served over RPC
+#[tokio::test(flavor = "multi_thread")]
+async fn dex_tx_tests() -> Result<(), anyhow::Error> {
+    let (rpc_port_tx, rpc_port_rx) = tokio::sync::oneshot::channel();
+    let (rest_port_tx, rest_port_rx) = tokio::sync::oneshot::channel();
+
+    let rollup_task = tokio::spawn(async {
+        start_rollup(
+            rpc_port_tx,
+            rest_port_tx,
+            GenesisPaths::from_dir("../../test-data/genesis/mock/"),
+            RollupProverConfig::Skip,
+            MockDaConfig {
+                connection_string: "sqlite::memory:".to_string(),
+                sender_address: MockAddress::new([0; 32]),
+                finalization_blocks: 3,
+                block_producing: BlockProducingConfig::OnBatchSubmit,
+                block_time_ms: 100_000,
+            },
+        )
+        .await;
+    });
+    let rpc_port = rpc_port_rx.await?.port();
+    let rest_port = rest_port_rx.await?.port();
+    let client = NodeClient::new_at_localhost(rest_port).await?;
+    let rpc = HttpClientBuilder::default().build(format!("http://127.0.0.1:{rpc_port}"))?;
+
+    // If the rollup throws an error, return it and stop trying to send the transaction
+    tokio::select! {
+        err = rollup_task => err?,
+        res = send_test_create_mpg_tx(&client, &rpc) => res?,
+    }
+    Ok(())
+}
+
+async fn send_test_create_mpg_tx(
+    client: &NodeClient,
+    rpc: &HttpClient,
+) -> Result<(), anyhow::Error> {
+    let key_and_address = read_private_keys::<TestSpec>("tx_signer_private_key.json");
+    let key = key_and_address.private_key;
+
+    let msg = RuntimeCall::<TestSpec>::Dex(
+        spicenet_dex::CallMessage::<TestSpec>::CreateMarketProductGroup {
+            name: MPG_NAME.try_into().unwrap(),
+            decimals: 2,
+            collateral_token_id: sov_bank::GAS_TOKEN_ID,
+            ewma_windows: [0; 4],
+            max_maker_fee_bps: 10,
+            min_maker_fee_bps: 0,
+            max_taker_fee_bps: 20,
+            min_taker_fee_bps: 0,
+        },
+    );
+    let chain_id = config_value!("CHAIN_ID");
+    let nonce = 0;
+    let max_priority_fee = PriorityFeeBips::ZERO;
+    let gas_limit = None;
+    let tx = Transaction::<TestSpec>::new_signed_tx(
+        &key,
+        &CHAIN_HASH,
+        UnsignedTransaction::new(
+            borsh::to_vec(&msg).unwrap(),
+            chain_id,
+            max_priority_fee,
+            MAX_TX_FEE,
+            nonce,
+            gas_limit,
+        ),
+    );
+
+    let mut slot_subscription = client
+        .client
+        .subscribe_slots()
+        .await
+        .context("Failed to subscribe to slots!")?;
+
+    client
+        .client
+        .publish_batch_with_serialized_txs(&[tx])
+        .await?;
+
+    // Wait until the rollup has processed the next slot
+    let _slot_number = slot_subscription
+        .next()
+        .await
+        .transpose()?
+        .map(|slot| slot.number)
+        .unwrap_or_default();
+
+    let mpg = DexRpcClient::<TestSpec>::get_mpg(rpc, get_mpg_id::<TestSpec>(MPG_NAME)).await?;
+    assert_eq!(mpg.id, get_mpg_id::<TestSpec>(MPG_NAME));
+    assert_eq!(mpg.decimals, 2);
+    assert_eq!(mpg.max_taker_fee_bps, 20);
+
+    Ok(())
+}
@@ -1 +1,12 @@
-pub type OrderbookId = u64;
+use sov_modules_api::impl_hash32_type;
+
+impl_hash32_type!(MarketId, MarketIdBech32, "market");
+
+impl PartialEq<MarketId> for &MarketId {
+    fn eq(&self, other: &MarketId) -> bool {
+        self.0 == other.0
+    }
+}
+
+/// Id of the AAOB market backing a product, books are keyed by it in the AAOB module
+pub type OrderbookId = MarketId;
@@ -54,3 +54,6 @@ pub const SLOTS_15_MIN: u64 = 2250;
 pub const SLOTS_60_MIN: u64 = 9000;
 
 pub const COMBO_FEE_SCALAR: Fractional = Fractional { m: 125, exp: 3 };
+
+/// Share of the taker fees of a referred TRG paid to its referrer
+pub const REFERRER_FEES_PROPORTION: Fractional = Fractional { m: 1, exp: 1 };
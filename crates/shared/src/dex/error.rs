@@ -150,6 +150,36 @@ pub enum DexError {
     MarketProductGroupDoesNotExist,
     #[error("Trader risk group does not exist")]
     TraderRiskGroupDoesNotExist,
+    #[error("Signer is not the market product group authority")]
+    InvalidMpgAuthority,
+    #[error("Market product group already exists")]
+    MarketProductGroupAlreadyExists,
+    #[error("Combo legs are invalid")]
+    InvalidComboLegs,
+    #[error("Product name is empty or longer than NAME_LEN")]
+    InvalidProductName,
+    #[error("Signer is not the trader risk group owner")]
+    InvalidTrgOwner,
+    #[error("Limit price is not a positive multiple of the product tick size")]
+    InvalidOrderPrice,
+    #[error("Order size must be positive")]
+    InvalidOrderSize,
+    #[error("Fee tiers must start at zero volume, increase and stay within the MPG fee bounds")]
+    InvalidFeeSchedule,
+    #[error("Referrer is not registered in the market product group")]
+    ReferrerNotRegistered,
+    #[error("Referrer is already registered")]
+    ReferrerAlreadyRegistered,
+    #[error("Referrer of the trader risk group is already set")]
+    ReferrerAlreadySet,
+    #[error("Print trade is already signed or cancelled")]
+    PrintTradeNotActive,
+    #[error("Print trade creator and counterparty must be distinct TRGs of the same MPG")]
+    InvalidPrintTradeParticipants,
+    #[error("Liquidator must be another trader risk group of the liquidatee's MPG")]
+    InvalidLiquidator,
+    #[error("Trader risk group is not liquidatable")]
+    NotLiquidatable,
 }
 
 #[derive(Debug, Error, Clone, PartialEq)]
@@ -7,7 +7,7 @@ use crate::{Fractional, MPGId, ProductId};
 
 use crate::dex::{
     ComboProduct, DexError, DexResult, MpgAuthority, OutrightProduct, PriceEwma, Product,
-    ProductStatus, ProductsArray, NAME_LEN,
+    ProductStatus, ProductTrait, ProductsArray, MAX_PRODUCTS, NAME_LEN,
 };
 use crate::BitPair;
 
@@ -305,8 +305,6 @@ impl<S: Spec> MarketProductGroup<S> {
     pub fn remove_product(&mut self, product_key: &ProductId) -> DexResult {
         let (idx, _) = self.find_product_index_among_all(&product_key)?;
         if let Ok(outright) = self.active_products[idx].try_to_outright_mut() {
-            assert_eq!(outright.is_expired(), true);
-            // do we need this?
             if !outright.is_expired() {
                 return Err(DexError::ContractIsNotExpired.into());
             }
@@ -315,26 +313,38 @@ impl<S: Spec> MarketProductGroup<S> {
         Ok(())
     }
 
-    /// We first check if there is a product existing and if so, return a `DexError`, otherwise, we fetch the product whose status is uninitialized, and return its `idx`, thereby adding a new product.
-    pub fn add_product(&mut self, product: Product) -> DexResult {
-        let p = self.get_active_products().find(|(_, p)| *p == &product);
-        if p.is_some() {
+    /// We first check if a product with the same key is already listed and if so, return a `DexError`. Otherwise, the product takes the first
+    /// slot whose status is uninitialized, or is appended while the MPG holds less than `MAX_PRODUCTS` products. Returns the `idx` of the new product.
+    pub fn add_product(&mut self, product: Product) -> DexResult<usize> {
+        if self
+            .find_product_index_among_all((&product).get_product_key())
+            .is_ok()
+        {
             return Err(DexError::DuplicateProductNameError.into());
         }
 
-        let index = match self
+        let free_slot = self
             .active_products
             .iter()
             .enumerate()
             .find(|(_idx, product)| {
                 product.get_product_status(self) == ProductStatus::Uninitialized
-            }) {
-            Some((idx, _product)) => idx,
+            })
+            .map(|(idx, _product)| idx);
+
+        let index = match free_slot {
+            Some(idx) => {
+                self.active_products[idx] = product;
+                idx
+            }
+            None if self.active_products.len() < MAX_PRODUCTS => {
+                self.active_products.push(product);
+                self.active_products.len() - 1
+            }
             None => return Err(DexError::FullMarketProductGroup.into()),
         };
 
-        self.active_products[index] = product;
-        Ok(())
+        Ok(index)
     }
 
     pub fn get_product_prices(&mut self, idx: usize) -> &mut PriceEwma {
@@ -76,6 +76,30 @@ pub struct OpenOrders {
 // }
 
 impl OpenOrders {
+    /// Returns an initialized, empty, list of open orders
+    pub fn new() -> Self {
+        let mut open_orders = OpenOrders {
+            free_list_head: 0,
+            total_open_orders: 0,
+            max_open_orders: 0,
+            products: std::array::from_fn(|_| OpenOrdersMetadata {
+                ask_qty_in_book: 0,
+                bid_qty_in_book: 0,
+                head_index: 0,
+                num_open_orders: 0,
+            }),
+            orders: std::array::from_fn(|_| OpenOrdersNode {
+                id: 0,
+                qty: 0,
+                client_id: 0,
+                prev: 0,
+                next: 0,
+            }),
+        };
+        open_orders.initialize();
+        open_orders
+    }
+
     pub fn initialize(&mut self) {
         self.free_list_head = 1;
         self.max_open_orders = MAX_OPEN_ORDERS as u16;
@@ -1,4 +1,4 @@
-use sov_modules_api::{Address, Spec};
+use sov_modules_api::Spec;
 
 use crate::IsInitialized;
 use crate::{addresses::TrgId, Fractional, MPGId, ProductId, Side, ZERO_FRAC};
@@ -21,6 +21,8 @@ pub struct TraderRiskGroup<S: Spec> {
     pub tag: AccountTag,
     pub market_product_group: MPGId,
     pub id: TrgId<S>,
+    /// Account allowed to trade, deposit and withdraw on behalf of the TRG
+    pub owner: S::Address,
     // Default value is 255 (max int) which corresponds to no position for the product at the corresponding index
     #[cfg_attr(
         feature = "native",
@@ -42,7 +44,7 @@ pub struct TraderRiskGroup<S: Spec> {
     pub taker_fee_bps: i32,
     pub trader_positions: [TraderPosition; MAX_TRADER_POSITIONS],
     // pub risk_state_account: StateMap<TrgId<S>, VarianceCache<S>>,
-    pub fee_state_account: Address<S>, // TODO: Dummy placeholder
+    pub fee_state_account: S::Address, // TODO: Dummy placeholder
     pub locked_collateral: [LockedCollateral; MAX_TRADER_POSITIONS], // in one-to-one mapping with trader_positions
     pub notional_maker_volume: Fractional,
     pub notional_taker_volume: Fractional,
@@ -68,10 +70,47 @@ impl<S: Spec> IsInitialized for TraderRiskGroup<S> {
 }
 
 impl<S: Spec> TraderRiskGroup<S> {
+    /// Creates an empty TRG of `owner` in `market_product_group`, no position nor cash
+    pub fn new(id: TrgId<S>, market_product_group: MPGId, owner: &S::Address) -> Self {
+        TraderRiskGroup {
+            tag: AccountTag::TraderRiskGroup,
+            market_product_group,
+            id,
+            owner: owner.clone(),
+            active_products: [u8::MAX; MAX_OUTRIGHTS],
+            total_deposited: ZERO_FRAC,
+            total_withdrawn: ZERO_FRAC,
+            cash_balance: ZERO_FRAC,
+            pending_cash_balance: ZERO_FRAC,
+            pending_fees: ZERO_FRAC,
+            valid_until: 0,
+            maker_fee_bps: 0,
+            taker_fee_bps: 0,
+            trader_positions: std::array::from_fn(|_| TraderPosition {
+                tag: AccountTag::Uninitialized,
+                product_key: [0u8; 32].into(),
+                position: ZERO_FRAC,
+                pending_position: ZERO_FRAC,
+                product_index: 0,
+                last_cum_funding_snapshot: ZERO_FRAC,
+                last_social_loss_snapshot: ZERO_FRAC,
+            }),
+            fee_state_account: owner.clone(),
+            locked_collateral: std::array::from_fn(|_| LockedCollateral::default()),
+            notional_maker_volume: ZERO_FRAC,
+            notional_taker_volume: ZERO_FRAC,
+            referred_takers_notional_volume: ZERO_FRAC,
+            referral_fees: ZERO_FRAC,
+            allocated_for_future_use: [0; 256],
+            open_orders: OpenOrders::new(),
+        }
+    }
+
     pub fn find_position_index(&self, product_id: &ProductId) -> Option<usize> {
         self.trader_positions
             .iter()
-            .position(|pk| pk.product_key == *product_id) // Product id is the key stored in position
+            // Product id is the key stored in position, cleared positions keep their key
+            .position(|pk| pk.is_initialized() && pk.product_key == *product_id)
     }
 
     // Positions have is_initialized impl above, if it's true, adding it to total active positions
@@ -82,6 +121,68 @@ impl<S: Spec> TraderRiskGroup<S> {
             .count()
     }
 
+    /// Returns the index of the trader position tracking the outright at `product_index`, opening
+    /// one with the given funding and social loss snapshots if there is none yet. The flag is
+    /// `true` when a position was opened.
+    pub fn activate_position(
+        &mut self,
+        product_index: usize,
+        product_key: &ProductId,
+        cum_funding_snapshot: Fractional,
+        social_loss_snapshot: Fractional,
+    ) -> DexResult<(usize, bool)> {
+        if self.is_active_product(product_index)? {
+            return Ok((self.active_products[product_index] as usize, false));
+        }
+
+        let position_index = match self
+            .trader_positions
+            .iter()
+            .position(|position| !position.is_initialized())
+        {
+            Some(i) => i,
+            None => return Err(DexError::UserAccountFull),
+        };
+
+        self.trader_positions[position_index] = TraderPosition {
+            tag: AccountTag::TraderPosition,
+            product_key: *product_key,
+            position: ZERO_FRAC,
+            pending_position: ZERO_FRAC,
+            product_index,
+            last_cum_funding_snapshot: cum_funding_snapshot,
+            last_social_loss_snapshot: social_loss_snapshot,
+        };
+        self.locked_collateral[position_index].tag = AccountTag::LockedCollateral;
+        self.active_products[product_index] = position_index as u8;
+
+        Ok((position_index, true))
+    }
+
+    /// Tracks a new resting order of the product at `product_index`
+    pub fn add_open_order(
+        &mut self,
+        product_index: usize,
+        order_id: u128,
+        qty: u64,
+        client_id: u64,
+    ) -> DexResult {
+        if self.open_orders.get_next_index() >= self.open_orders.max_open_orders as usize {
+            return Err(DexError::TooManyOpenOrdersError);
+        }
+
+        self.open_orders
+            .add_open_order(product_index, order_id, qty, client_id)?;
+        self.open_orders.products[product_index].num_open_orders += 1;
+        self.open_orders.total_open_orders += 1;
+        Ok(())
+    }
+
+    pub fn remove_open_order(&mut self, product_index: usize, order_id: u128) -> DexResult {
+        let order_index = self.open_orders.get_order_index(product_index, order_id)?;
+        self.remove_open_order_by_index(product_index, order_index, order_id)
+    }
+
     // This may NOT work right now and would need rewrite after sokoban implementation
     pub fn remove_open_order_by_index(
         &mut self,
@@ -130,4 +130,10 @@ pub enum RiskError {
 
     #[error("VarianceCacheNotInitialized")]
     VarianceCacheNotInitialized,
+
+    #[error("Risk calls have to be sent through the dex, which provides the MPG and TRG state")]
+    CallNotRoutedThroughDex,
+
+    #[error("InvalidRiskParameters")]
+    InvalidRiskParameters,
 }
@@ -1,12 +1,13 @@
 pub use {
     constants::*, covariance_metadata::*, error::*, health_status::*, risk_output::*,
-    variance_cache::*,
+    state_provider::*, variance_cache::*,
 };
 
 pub mod covariance_metadata;
 pub mod error;
 pub mod health_status;
 pub mod risk_output;
+pub mod state_provider;
 pub mod variance_cache;
 // risk engine constants
 pub mod constants;
@@ -22,6 +22,19 @@ pub struct RiskEngineOutput {
     pub health_output: HealthOutput,
 }
 
+impl Default for RiskEngineOutput {
+    fn default() -> Self {
+        Self {
+            health_output: HealthOutput::Healthy {
+                health_status: HealthTracker {
+                    health_status: HealthStatus::Healthy,
+                    action_status: ActionStatus::Approved,
+                },
+            },
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(
     feature = "native",
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use sov_modules_api::{Spec, StateAccessor};
+
+use crate::dex::{MarketProductGroup, TraderRiskGroup};
+use crate::{MPGId, TrgId};
+
+/// Canonical market product groups and trader risk groups, as stored by the dex.
+///
+/// The risk engine resolves the ids of its call messages through this trait instead of trusting
+/// MPGs and TRGs supplied by the sender. It is implemented by `spicenet_dex::Dex`, which keeps the
+/// risk crate free of a dependency on the dex.
+pub trait DexStateProvider<S: Spec> {
+    fn market_product_group(
+        &self,
+        mpg_id: &MPGId,
+        state: &mut impl StateAccessor,
+    ) -> Result<MarketProductGroup<S>>;
+
+    fn trader_risk_group(
+        &self,
+        trg_id: &TrgId<S>,
+        state: &mut impl StateAccessor,
+    ) -> Result<TraderRiskGroup<S>>;
+}
@@ -1,19 +1,22 @@
 use anyhow::Result;
 use sov_modules_api::{Context, EventEmitter, Spec, TxState};
 
-use spicenet_shared::dex::MarketProductGroup;
+use spicenet_shared::MPGId;
 
 use crate::event::Event;
 use crate::RiskModule;
-use spicenet_shared::risk::RiskError;
+use spicenet_shared::risk::{DexStateProvider, RiskError};
 
 impl<S: Spec> RiskModule<S> {
     pub(crate) fn delete_mark_prices(
         &self,
-        mpg: &MarketProductGroup<S>,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: MPGId,
         context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
+        let mpg = provider.market_product_group(&mpg_id, state)?;
+
         if context.sender().as_ref() != mpg.mpg_authority.as_ref() {
             return Err(RiskError::InvalidAuthority.into());
         }
@@ -1,25 +1,25 @@
 use anyhow::Result;
 use sov_modules_api::{Context, EventEmitter, Spec, TxState};
 
-use spicenet_shared::dex::MarketProductGroup;
+use spicenet_shared::{MPGId, ProductId, ZERO_FAST_INT};
 
 use crate::event::Event;
 use crate::state::{CorrelationMatrix, CovarianceMatrix};
 use crate::RiskModule;
 use spicenet_shared::risk::covariance_metadata::CovarianceMetadata;
-use spicenet_shared::risk::RiskError;
+use spicenet_shared::risk::{
+    DexStateProvider, RiskError, MAX_CORRELATION_SIZE, MAX_OUTRIGHTS, MAX_PRODUCTS,
+};
 
 impl<S: Spec> RiskModule<S> {
     pub(crate) fn initialize_covariance_matrix(
         &self,
-        mpg: &MarketProductGroup<S>,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: MPGId,
         context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
-        // let mpg = self
-        //     .dex
-        //     .get_mpg(state, &mpg_id)
-        //     .or_else(|_| Err(DexError::MarketProductGroupDoesNotExist.into()))?;
+        let mpg = provider.market_product_group(&mpg_id, state)?;
 
         if context.sender().as_ref() != mpg.mpg_authority.as_ref() {
             return Err(RiskError::InvalidAuthority.into());
@@ -37,14 +37,16 @@ impl<S: Spec> RiskModule<S> {
                     covariance_metadata: CovarianceMetadata {
                         update_slot: 0, // 0 indicates never updated, should be ok
                         num_active_products: 0,
-                        product_keys: Vec::new(),
-                        standard_deviations: Vec::new(),
+                        // sized like the fixed size arrays they replace, entries past
+                        // `num_active_products` are unused
+                        product_keys: vec![ProductId::from([0u8; 32]); MAX_OUTRIGHTS],
+                        standard_deviations: vec![ZERO_FAST_INT; MAX_OUTRIGHTS],
                     },
                     correlations: CorrelationMatrix {
                         num_active_products: 0,
-                        possible_correlations: Vec::new(),
+                        possible_correlations: vec![0; MAX_CORRELATION_SIZE],
                     },
-                    mappings: Vec::new(),
+                    mappings: vec![u16::MAX; MAX_PRODUCTS],
                 },
                 state,
             )
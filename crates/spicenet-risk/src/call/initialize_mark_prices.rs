@@ -1,27 +1,25 @@
 use anyhow::Result;
 use sov_modules_api::{Address, Context, EventEmitter, Spec, TxState};
 
-use spicenet_shared::dex::MarketProductGroup;
+use spicenet_shared::MPGId;
 
 use crate::event::Event;
 use crate::state::MarkPricesArray;
 use crate::RiskModule;
-use spicenet_shared::risk::RiskError;
+use spicenet_shared::risk::{DexStateProvider, RiskError};
 
 impl<S: Spec> RiskModule<S> {
     pub(crate) fn initialize_mark_prices(
         &self,
-        mpg: &MarketProductGroup<S>,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: MPGId,
         is_hardcoded_oracle: bool,
         hardcoded_oracle_id: Option<Address<S>>, // TODO(!oracle): change to something like OracleId once oracle is done
         // hardcoded_oracle_type: u8, // TODO(!oracle): change to OracleType once oracle is done
         context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
-        // let mpg = self
-        //     .dex
-        //     .get_mpg(state, &mpg_id)
-        //     .or_else(|_| Err(DexError::MarketProductGroupDoesNotExist.into()))?;
+        let mpg = provider.market_product_group(&mpg_id, state)?;
 
         if context.sender().as_ref() != mpg.mpg_authority.as_ref() {
             return Err(RiskError::InvalidAuthority.into());
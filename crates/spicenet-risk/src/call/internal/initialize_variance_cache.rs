@@ -6,7 +6,7 @@ use spicenet_shared::FastInt;
 
 use crate::event::Event;
 use crate::RiskModule;
-use spicenet_shared::risk::{RiskError, VarianceCache};
+use spicenet_shared::risk::{RiskError, VarianceCache, MAX_TRADER_POSITIONS};
 
 impl<S: Spec> RiskModule<S> {
     pub fn initialize_variance_cache(
@@ -33,9 +33,10 @@ impl<S: Spec> RiskModule<S> {
                     open_order_variance: FastInt { value: 0 },
                     total_liquidity_buffer: FastInt { value: 0 },
                     total_variance_traded: FastInt { value: 0 },
-                    positions: Vec::new(),
-                    product_indexes: Vec::new(),
-                    sigma_position: Vec::new(),
+                    // the risk engine indexes the cache as the fixed size arrays it replaces
+                    positions: vec![FastInt { value: 0 }; 2 * MAX_TRADER_POSITIONS],
+                    product_indexes: vec![usize::MAX; 2 * MAX_TRADER_POSITIONS],
+                    sigma_position: vec![FastInt { value: 0 }; 2 * MAX_TRADER_POSITIONS],
                 },
                 state,
             )
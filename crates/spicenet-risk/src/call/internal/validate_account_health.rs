@@ -4,7 +4,7 @@ use sov_modules_api::{Context, EventEmitter, Spec, TxState};
 use spicenet_shared::dex::{MarketProductGroup, TraderRiskGroup};
 use spicenet_shared::risk::RiskError;
 use spicenet_shared::Side;
-use spicenet_shared::{FastInt, Fractional, ZERO_FAST_INT};
+use spicenet_shared::{Fractional, ZERO_FAST_INT};
 
 use crate::event::Event;
 use crate::state::RiskProfile;
@@ -14,20 +14,6 @@ use spicenet_shared::risk::{
     ActionStatus, HealthOutput, HealthTracker, RiskEngineOpCodes, RiskInfo,
 };
 
-/// ATTENTION: Update MIN_ESCAPABALE_PRICE when changing PRICE_BAND_PROPORTION.
-/// We could probably do this at compile time with constexprs somehow
-/// but I don't want to take the time to figure that out right now.
-pub const PRICE_BAND_PROPORTION: Fractional = Fractional { m: 15, exp: 2 };
-pub const IS_PRICE_BANDS_ENABLED: bool = true;
-
-/// ATTENTION: change this value when changing PRICE_BAND_PROPORTIO     N
-/// This value is calculated to be the minimum positive price such that
-/// a trader can place an order with a _different_ price without violating
-/// price bands. See that if the mark price is positive and less than this number,
-/// then any price != mark price will be more than 15% away and thus a price band
-/// violation.
-pub const MIN_ESCAPABLE_PRICE: FastInt = FastInt { value: 000_0006 };
-
 impl<S: Spec> RiskModule<S> {
     pub fn validate_account_health(
         &self,
@@ -57,6 +43,7 @@ impl<S: Spec> RiskModule<S> {
             .get(&mpg.id, state)
             .unwrap()
             .ok_or::<Error>(RiskError::MarkPricesNotInitialized.into())?;
+        let risk_parameters = self.get_risk_parameters(&mpg.id, state)?;
 
         let metadata = &covariance_matrix.covariance_metadata;
         let mut variance_cache = self
@@ -65,8 +52,10 @@ impl<S: Spec> RiskModule<S> {
             .unwrap()
             .ok_or::<Error>(RiskError::VarianceCacheNotInitialized.into())?;
 
-        if IS_PRICE_BANDS_ENABLED && params.op_type == RiskEngineOpCodes::NewOrder {
-            if params.num_orders > 0 {
+        if let (Some(price_band_proportion), RiskEngineOpCodes::NewOrder) =
+            (risk_parameters.price_band_proportion, &params.op_type)
+        {
+            if params.num_orders as usize > params.orders.len() {
                 return Err(RiskError::InvalidRiskCheckParameters.into());
             }
 
@@ -79,14 +68,15 @@ impl<S: Spec> RiskModule<S> {
                     &mpg,
                     product,
                     self.time_module.get_slot(state).unwrap().slot,
+                    risk_parameters.max_mark_price_staleness,
                 )?;
 
-                if product_mark_price > MIN_ESCAPABLE_PRICE {
+                if product_mark_price > risk_parameters.min_escapable_price() {
                     let lower_band = Fractional::from(1)
-                        .checked_sub(PRICE_BAND_PROPORTION)?
+                        .checked_sub(price_band_proportion)?
                         .checked_mul(product_mark_price.to_frac()?)?;
                     let upper_band = Fractional::from(1)
-                        .checked_add(PRICE_BAND_PROPORTION)?
+                        .checked_add(price_band_proportion)?
                         .checked_mul(product_mark_price.to_frac()?)?;
                     if (order.side == Side::Ask && order.order_price < lower_band)
                         || (order.side == Side::Bid && order.order_price > upper_band)
@@ -122,6 +112,7 @@ impl<S: Spec> RiskModule<S> {
             &trg,
             &covariance_matrix,
             &mut variance_cache,
+            &risk_parameters,
             self.time_module.get_slot(state).unwrap().slot,
         )?;
 
@@ -139,7 +130,7 @@ impl<S: Spec> RiskModule<S> {
             block_withdrawal = true;
         }
 
-        let health_output = match risk_profile.get_health_status() {
+        let health_output = match risk_profile.get_health_status(&risk_parameters) {
             HealthStatus::Liquidatable => HealthOutput::Healthy {
                 health_status: HealthTracker {
                     health_status: HealthStatus::Liquidatable,
@@ -37,6 +37,7 @@ impl<S: Spec> RiskModule<S> {
             .get(&mpg.id, state)
             .unwrap()
             .ok_or::<anyhow::Error>(RiskError::MarkPricesNotInitialized.into())?;
+        let risk_parameters = self.get_risk_parameters(&mpg.id, state)?;
 
         let metadata = &covariance_matrix.covariance_metadata;
         let mut variance_cache = self
@@ -60,13 +61,14 @@ impl<S: Spec> RiskModule<S> {
             &trg,
             &covariance_matrix,
             &mut variance_cache,
+            &risk_parameters,
             self.time_module.get_slot(state).unwrap().slot,
         )?;
 
         let liquidation_status = Self::calculate_liquidation_status(
             &risk_profile,
             &trg,
-            risk_profile.get_health_status(),
+            risk_profile.get_health_status(&risk_parameters),
         )?;
 
         let health_output = HealthOutput::Liquidatable { liquidation_status };
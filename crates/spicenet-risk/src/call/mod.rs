@@ -1,8 +1,8 @@
 use sov_modules_api::{Address, Spec};
 
-use spicenet_shared::dex::{MarketProductGroup, TraderRiskGroup};
-use spicenet_shared::{FastInt, ProductId};
-use update_mark_prices::ProductMarkPriceUpdate;
+use spicenet_shared::{FastInt, MPGId, ProductId, TrgId};
+
+use crate::state::RiskParameters;
 
 pub mod collect_mark_prices_garbage;
 pub mod delete_mark_prices;
@@ -12,6 +12,7 @@ pub mod internal;
 pub mod remove_market_product_index_from_variance_cache;
 pub mod update_covariance_matrix;
 pub mod update_mark_prices;
+pub mod update_risk_parameters;
 // pub mod update_risk_authority;
 
 #[cfg_attr(
@@ -25,33 +26,40 @@ pub mod update_mark_prices;
 #[derive(borsh::BorshDeserialize, borsh::BorshSerialize, Debug, PartialEq, Clone, Eq)]
 pub enum CallMessage<S: Spec> {
     InitializeCovarianceMatrix {
-        mpg: MarketProductGroup<S>,
+        mpg_id: MPGId,
     },
     InitializeMarkPrices {
-        mpg: MarketProductGroup<S>,
+        mpg_id: MPGId,
         is_hardcoded_oracle: bool,
         hardcoded_oracle_id: Option<Address<S>>,
     },
     UpdateCovarianceMatrix {
-        mpg: MarketProductGroup<S>,
+        mpg_id: MPGId,
         product_keys: Vec<ProductId>,
         standard_deviations: Vec<FastInt>,
         correlations: Vec<Vec<FastInt>>,
     },
+    /// Permissionless crank refreshing the mark prices of `product_ids` from their AAOB books,
+    /// products already updated in the current slot are skipped
     UpdateMarkPrices {
-        mpg: MarketProductGroup<S>,
-        products_to_update: Vec<ProductMarkPriceUpdate>,
+        mpg_id: MPGId,
+        product_ids: Vec<ProductId>,
     },
     CollectMarkPricesGarbage {
-        mpg: MarketProductGroup<S>,
+        mpg_id: MPGId,
         max_products_to_examine: u8,
     },
     RemoveMarketProductIndexFromVarianceCache {
-        mpg: MarketProductGroup<S>,
-        trg: TraderRiskGroup<S>,
+        mpg_id: MPGId,
+        trg_id: TrgId<S>,
         market_product_index: usize,
     },
     DeleteMarkPrices {
-        mpg: MarketProductGroup<S>,
+        mpg_id: MPGId,
+    },
+    /// Replaces the risk parameters of an MPG, only its authority can send it
+    UpdateRiskParameters {
+        mpg_id: MPGId,
+        risk_parameters: RiskParameters,
     },
 }
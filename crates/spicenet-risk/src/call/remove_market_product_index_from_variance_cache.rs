@@ -1,27 +1,27 @@
 use anyhow::Result;
 use sov_modules_api::prelude::anyhow;
 use sov_modules_api::{Context, EventEmitter, Spec, TxState};
-use spicenet_shared::dex::{MarketProductGroup, TraderRiskGroup};
-
-use spicenet_shared::ZERO_FAST_INT;
+use spicenet_shared::{MPGId, TrgId, ZERO_FAST_INT};
 
 use crate::event::Event;
 use crate::RiskModule;
-use spicenet_shared::risk::RiskError;
+use spicenet_shared::risk::{DexStateProvider, RiskError};
 
 impl<S: Spec> RiskModule<S> {
     pub(crate) fn remove_market_product_index_from_variance_cache(
         &self,
-        mpg: &MarketProductGroup<S>,
-        trg: &TraderRiskGroup<S>,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: MPGId,
+        trg_id: TrgId<S>,
         market_product_index: usize,
         context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
-        // let mpg = self
-        //     .dex
-        //     .get_mpg(state, &mpg_id)
-        //     .or_else(|_| Err(DexError::MarketProductGroupDoesNotExist.into()))?;
+        let mpg = provider.market_product_group(&mpg_id, state)?;
+        let trg = provider.trader_risk_group(&trg_id, state)?;
+        if trg.market_product_group != mpg.id {
+            return Err(RiskError::MismatchedRiskStateAccount.into());
+        }
 
         if context.sender().as_ref() != mpg.mpg_authority.as_ref() {
             return Err(RiskError::InvalidAuthority.into());
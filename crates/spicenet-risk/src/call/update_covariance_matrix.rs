@@ -1,28 +1,25 @@
 use anyhow::{Error, Result};
 use sov_modules_api::{Context, EventEmitter, Spec, TxState};
 
-use spicenet_shared::dex::MarketProductGroup;
-use spicenet_shared::{FastInt, ProductId};
+use spicenet_shared::{FastInt, MPGId, ProductId};
 
 use crate::event::Event;
 use crate::state::MutableCovarianceMatrix;
 use crate::RiskModule;
-use spicenet_shared::risk::RiskError;
+use spicenet_shared::risk::{DexStateProvider, RiskError};
 
 impl<S: Spec> RiskModule<S> {
     pub(crate) fn update_covariance_matrix(
         &self,
-        mpg: &MarketProductGroup<S>,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: MPGId,
         product_keys: Vec<ProductId>,
         standard_deviations: Vec<FastInt>,
         correlations: Vec<Vec<FastInt>>,
         context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
-        // let mpg = self
-        //     .dex
-        //     .get_mpg(state, &mpg_id)
-        //     .or_else(|_| Err(DexError::MarketProductGroupDoesNotExist.into()))?;
+        let mpg = provider.market_product_group(&mpg_id, state)?;
 
         if context.sender().as_ref() != mpg.mpg_authority.as_ref() {
             return Err(RiskError::InvalidAuthority.into());
@@ -1,59 +1,116 @@
-use anyhow::{Error, Result};
-use borsh::{BorshDeserialize, BorshSerialize};
-use sov_modules_api::{Context, Spec, TxState};
-use spicenet_aaob::Slab;
+use anyhow::{bail, Result};
+use sov_modules_api::{Context, EventEmitter, Spec, StateAccessor, TxState};
 
+use crate::event::Event;
+use crate::state::MarkPricesArray;
 use crate::RiskModule;
-use spicenet_shared::{FastInt, MPGId, MarketProductGroup, OutrightProduct, ProductId, RiskError};
-
-#[cfg_attr(
-    feature = "native",
-    derive(serde::Serialize),
-    derive(serde::Deserialize),
-    derive(schemars::JsonSchema),
-    derive(sov_modules_api::macros::UniversalWallet),
-)]
-#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
-pub struct ProductMarkPriceUpdate {
-    product_id: ProductId,
-    asks: Slab,
-    bids: Slab,
-}
+use spicenet_shared::dex::OutrightProduct;
+use spicenet_shared::risk::{DexStateProvider, RiskError};
+use spicenet_shared::time::Slot;
+use spicenet_shared::{DexError, FastInt, MPGId, ProductId, Side};
 
 impl<S: Spec> RiskModule<S> {
-    // TODO(!aaob, !oracle): dependent on orderbook logic being complete for getting asks and bids and oracle being done to get oracle price
+    /// Refreshes the mark price of each outright in `product_ids` from its index price and its
+    /// AAOB book.
+    ///
+    /// Products whose mark price was already updated in the current slot are skipped.
     pub(crate) fn update_mark_prices(
         &self,
-        mpg: &MarketProductGroup<S>,
-        products_to_update: Vec<ProductMarkPriceUpdate>,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: MPGId,
+        product_ids: Vec<ProductId>,
         _context: &Context<S>,
         state: &mut impl TxState<S>,
     ) -> Result<()> {
-        let mut mark_prices_array = self
-            .mark_prices
-            .get(&mpg.id, state)
-            .unwrap()
-            .ok_or::<Error>(RiskError::MarkPricesNotInitialized.into())?;
+        let mpg = provider.market_product_group(&mpg_id, state)?;
+
+        let mut mark_prices = match self.mark_prices.get(&mpg.id, state).unwrap() {
+            Some(mark_prices) => mark_prices,
+            None => return Err(RiskError::MarkPricesNotInitialized.into()),
+        };
 
-        for product in products_to_update {
-            let (product_idx, outright) = mpg.find_outright(&product.product_id)?;
+        let alpha = self.get_risk_parameters(&mpg.id, state)?.mark_price_ema_alpha;
+        let current_slot = self.time_module.get_slot(state)?.slot;
+        let mut updated_product_ids = Vec::with_capacity(product_ids.len());
 
-            let index_price = self
-                .lut
-                .get_price(outright.metadata.price_index, state)?
-                .price;
+        for product_id in product_ids {
+            let outright = match mpg.find_product_index(&product_id) {
+                Some((_, product)) => product.try_to_outright()?,
+                None => bail!(DexError::MissingMarketProduct),
+            };
 
-            mark_prices_array.update_outright_price_with_slab(
+            if self.refresh_outright_mark_price(
+                &mut mark_prices,
                 outright,
-                index_price.into(),
-                product_idx,
-                self.time_module.get_slot(state)?.slot,
-                // TODO: change to reference to avoid cloning
-                product.bids.clone(),
-                product.asks.clone(),
-            )?;
+                current_slot,
+                alpha,
+                state,
+            )? {
+                updated_product_ids.push(product_id);
+            }
         }
 
+        self.mark_prices.set(&mpg.id, &mark_prices, state)?;
+
+        self.emit_event(
+            state,
+            Event::MarkPricesUpdated {
+                mpg_id: mpg.id,
+                product_ids: updated_product_ids,
+            },
+        );
+
         Ok(())
     }
+
+    /// Updates the mark price of `outright` from its index price and its AAOB book, initializing
+    /// it if needed. Returns false without updating if it was already updated in `current_slot`.
+    pub(crate) fn refresh_outright_mark_price(
+        &self,
+        mark_prices: &mut MarkPricesArray<S>,
+        outright: &OutrightProduct,
+        current_slot: Slot,
+        alpha: FastInt,
+        state: &mut impl StateAccessor,
+    ) -> Result<bool> {
+        let index_price = self
+            .lut
+            .get_price(outright.metadata.price_index, state)?
+            .price;
+
+        let mark_price_index = match mark_prices.get_product_index(&outright.product_id) {
+            // at most one update per product and slot
+            Ok(index) if mark_prices.array[index].update_slot >= current_slot => return Ok(false),
+            Ok(index) => index,
+            Err(_) => mark_prices.initialize_outright_price(outright, index_price.into())?,
+        };
+
+        let (bids, asks) = match self.aaob.load_book(outright.metadata.orderbook_id, state)? {
+            Some(mut book) => (
+                book.depth(
+                    Side::Bid,
+                    MarkPricesArray::<S>::MAX_BOOK_LEVELS_FOR_MARK_PRICE,
+                    state,
+                )?,
+                book.depth(
+                    Side::Ask,
+                    MarkPricesArray::<S>::MAX_BOOK_LEVELS_FOR_MARK_PRICE,
+                    state,
+                )?,
+            ),
+            None => (vec![], vec![]),
+        };
+
+        mark_prices.update_outright_price(
+            outright,
+            index_price.into(),
+            mark_price_index,
+            current_slot,
+            &bids,
+            &asks,
+            alpha,
+        )?;
+
+        Ok(true)
+    }
 }
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use sov_modules_api::{Context, EventEmitter, Spec, TxState};
+
+use spicenet_shared::MPGId;
+
+use crate::event::Event;
+use crate::state::RiskParameters;
+use crate::RiskModule;
+use spicenet_shared::risk::{DexStateProvider, RiskError};
+
+impl<S: Spec> RiskModule<S> {
+    pub(crate) fn update_risk_parameters(
+        &self,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: MPGId,
+        risk_parameters: RiskParameters,
+        context: &Context<S>,
+        state: &mut impl TxState<S>,
+    ) -> Result<()> {
+        let mpg = provider.market_product_group(&mpg_id, state)?;
+
+        if context.sender().as_ref() != mpg.mpg_authority.as_ref() {
+            return Err(RiskError::InvalidAuthority.into());
+        }
+
+        risk_parameters.validate()?;
+
+        self.risk_parameters.set(&mpg.id, &risk_parameters, state)?;
+
+        self.emit_event(
+            state,
+            Event::RiskParametersUpdated {
+                mpg_id: mpg.id,
+                risk_parameters,
+            },
+        );
+
+        Ok(())
+    }
+}
@@ -3,6 +3,8 @@ use sov_modules_api::{Address, Spec};
 use spicenet_shared::risk::{HealthOutput, RiskInfo};
 use spicenet_shared::{FastInt, MPGId, ProductId, TrgId};
 
+use crate::state::RiskParameters;
+
 #[derive(
     borsh::BorshDeserialize,
     borsh::BorshSerialize,
@@ -38,6 +40,10 @@ pub enum Event<S: Spec> {
     MarkPricesDeleted {
         mpg_id: MPGId,
     },
+    MarkPricesUpdated {
+        mpg_id: MPGId,
+        product_ids: Vec<ProductId>,
+    },
     MarkPricesGarbageCollected {
         mpg_id: MPGId,
         max_products_to_examine: u8,
@@ -61,4 +67,8 @@ pub enum Event<S: Spec> {
     VarianceCacheDeleted {
         trg_id: TrgId<S>,
     },
+    RiskParametersUpdated {
+        mpg_id: MPGId,
+        risk_parameters: RiskParameters,
+    },
 }
@@ -1,19 +1,44 @@
 use serde::{Deserialize, Serialize};
-use sov_modules_api::{Error, GenesisState, Spec};
+use sov_modules_api::{GenesisState, Spec};
 
 use anyhow::Result;
 
+use spicenet_shared::MPGId;
+
 use super::RiskModule;
+use crate::state::{MarkPriceRefreshConfig, RiskParameters};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-pub struct RiskModuleConfig {}
+pub struct RiskModuleConfig {
+    /// Enables the per-slot mark price refresh, mark prices are only updated by
+    /// `UpdateMarkPrices` when unset
+    #[serde(default)]
+    pub mark_price_refresh: Option<MarkPriceRefreshConfig>,
+    /// Parameters of every MPG without its own entry in `risk_parameters`
+    #[serde(default)]
+    pub default_risk_parameters: RiskParameters,
+    #[serde(default)]
+    pub risk_parameters: Vec<(MPGId, RiskParameters)>,
+}
 
 impl<S: Spec> RiskModule<S> {
     pub(crate) fn init_module(
         &self,
-        _config: &<Self as sov_modules_api::Module>::Config,
-        _state: &mut impl GenesisState<S>,
-    ) -> Result<(), Error> {
+        config: &<Self as sov_modules_api::Module>::Config,
+        state: &mut impl GenesisState<S>,
+    ) -> Result<()> {
+        if let Some(mark_price_refresh) = &config.mark_price_refresh {
+            self.mark_price_refresh_config.set(mark_price_refresh, state)?;
+        }
+
+        config.default_risk_parameters.validate()?;
+        self.default_risk_parameters.set(&config.default_risk_parameters, state)?;
+
+        for (mpg_id, risk_parameters) in &config.risk_parameters {
+            risk_parameters.validate()?;
+            self.risk_parameters.set(mpg_id, risk_parameters, state)?;
+        }
+
         Ok(())
     }
 }
@@ -5,6 +5,7 @@ use spicenet_shared::dex::{MarketProductGroup, TraderRiskGroup};
 use spicenet_shared::{IsInitialized, ProductId, RiskError};
 
 use crate::RiskModule;
+use spicenet_shared::risk::{MAX_OUTRIGHTS, MAX_PRODUCTS};
 use spicenet_shared::{CovarianceMetadata, VarianceCache};
 
 impl<S: Spec> RiskModule<S> {
@@ -111,8 +112,8 @@ impl<S: Spec> RiskModule<S> {
         metadata: &CovarianceMetadata,
         trader_risk_group: &TraderRiskGroup<S>,
     ) -> Result<Vec<u16>> {
-        let mut mappings = Vec::new();
-        let mut matched_products = Vec::new();
+        let mut mappings = vec![u16::MAX; MAX_PRODUCTS];
+        let mut matched_products = vec![false; MAX_OUTRIGHTS];
 
         Self::__map_traded_indexes(
             metadata,
@@ -130,8 +131,8 @@ impl<S: Spec> RiskModule<S> {
         trader_risk_group: &TraderRiskGroup<S>,
         variance_cache: &VarianceCache,
     ) -> Result<Vec<u16>> {
-        let mut mappings = Vec::new();
-        let mut matched_products = Vec::new();
+        let mut mappings = vec![u16::MAX; MAX_PRODUCTS];
+        let mut matched_products = vec![false; MAX_OUTRIGHTS];
 
         Self::__map_traded_indexes(
             metadata,
@@ -9,12 +9,12 @@ use spicenet_shared::{
 };
 
 use crate::RiskModule;
-use crate::{CovarianceMatrix, MarkPricesArray, RiskProfile};
+use crate::{CovarianceMatrix, MarkPricesArray, RiskParameters, RiskProfile};
 use spicenet_shared::risk::health_status::HealthStatus;
 use spicenet_shared::risk::{
     ActionStatus, LiquidationStatus, SocialLossInfo, VarianceCache, MAX_TRADER_POSITIONS,
 };
-use spicenet_shared::risk::{RiskError, MAX_OUTRIGHTS};
+use spicenet_shared::risk::{RiskError, MAX_OUTRIGHTS, MAX_PRODUCTS};
 
 struct TraderPositionMetadata {
     // the index of this product in the products array
@@ -38,10 +38,11 @@ impl<S: Spec> RiskModule<S> {
         trader_risk_group: &TraderRiskGroup<S>,
         covariance_matrix: &CovarianceMatrix,
         cache: &mut VarianceCache,
+        risk_parameters: &RiskParameters,
         slot: Slot,
     ) -> Result<RiskProfile> {
-        // let mut abs_position_value = [ZERO_FAST_INT; MAX_TRADER_POSITIONS];
-        let mut abs_position_value = Vec::with_capacity(MAX_TRADER_POSITIONS);
+        // indexed by product index, like the mappings of the covariance matrix
+        let mut abs_position_value = vec![ZERO_FAST_INT; MAX_PRODUCTS];
         let mut total_abs_position_value = ZERO_FAST_INT;
 
         let is_force_rebuild =
@@ -57,6 +58,7 @@ impl<S: Spec> RiskModule<S> {
                     cache,
                     &mut abs_position_value,
                     &mut total_abs_position_value,
+                    risk_parameters,
                     slot,
                 )?;
                 false // do not force rebuild a second time
@@ -70,6 +72,7 @@ impl<S: Spec> RiskModule<S> {
                     cache,
                     &mut abs_position_value,
                     &mut total_abs_position_value,
+                    risk_parameters,
                     slot,
                 ) {
                     Ok(_) => false, // do not force rebuild, calculating risk from cache succeeded
@@ -98,6 +101,7 @@ impl<S: Spec> RiskModule<S> {
                 cache,
                 &mut abs_position_value,
                 &mut total_abs_position_value,
+                risk_parameters,
                 slot,
             )?;
         }
@@ -126,6 +130,7 @@ impl<S: Spec> RiskModule<S> {
         // abs_position_value: &mut [FastInt; MAX_TRADER_POSITIONS],
         abs_position_value: &mut Vec<FastInt>,
         total_abs_position_value: &mut FastInt,
+        risk_parameters: &RiskParameters,
         current_slot: Slot,
     ) -> Result<()> {
         let mut q: Vec<TraderPositionMetadata> = Vec::with_capacity(MAX_TRADER_POSITIONS);
@@ -192,8 +197,12 @@ impl<S: Spec> RiskModule<S> {
             if position != ZERO_FAST_INT {
                 // calculate price of this derivative
                 let product = &market_product_group.active_products[product_index];
-                let price =
-                    mark_prices.calculate_price(market_product_group, product, current_slot)?;
+                let price = mark_prices.calculate_price(
+                    market_product_group,
+                    product,
+                    current_slot,
+                    risk_parameters.max_mark_price_staleness,
+                )?;
 
                 let this_position_value = price.mul_clamp_to_tick(position);
                 let this_position_value_abs = this_position_value.abs();
@@ -319,6 +328,7 @@ impl<S: Spec> RiskModule<S> {
         // abs_position_value: &mut [FastInt; MAX_TRADER_POSITIONS],
         abs_position_value: &mut Vec<FastInt>,
         total_abs_position_value: &mut FastInt,
+        risk_parameters: &RiskParameters,
         current_slot: Slot,
     ) -> Result<()> {
         // TODO: can possibly short-circuit a lot of the position calculation if position_change == 0,
@@ -406,8 +416,12 @@ impl<S: Spec> RiskModule<S> {
             if position != ZERO_FAST_INT {
                 // calculate price of this derivative
                 let product = &market_product_group.active_products[product_index];
-                let price =
-                    mark_prices.calculate_price(market_product_group, product, current_slot)?;
+                let price = mark_prices.calculate_price(
+                    market_product_group,
+                    product,
+                    current_slot,
+                    risk_parameters.max_mark_price_staleness,
+                )?;
 
                 let this_position_value = price.mul_clamp_to_tick(position);
                 let this_position_value_abs = this_position_value.abs();
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use sov_modules_api::{Spec, StateAccessor};
+
+use spicenet_shared::risk::{DexStateProvider, RiskError};
+use spicenet_shared::time::Slot;
+use spicenet_shared::{MPGId, Product};
+
+use crate::state::{MarkPriceRefreshCursor, MarkPriceRefreshFailure};
+use crate::RiskModule;
+
+impl<S: Spec> RiskModule<S> {
+    /// Refreshes the mark prices of the active outrights of the MPGs configured at genesis, at
+    /// most `max_products_per_slot` of them, resuming where the previous call stopped.
+    ///
+    /// Meant to run once per slot from the slot hooks of the module owning the MPGs, which passes
+    /// itself as `provider`. Does nothing unless a
+    /// [`MarkPriceRefreshConfig`](crate::state::MarkPriceRefreshConfig) was set at genesis.
+    ///
+    /// MPGs and products that cannot be refreshed do not stop the others, they are returned and
+    /// kept until the next refresh, see [`RiskModule::mark_price_refresh_failures`].
+    pub fn refresh_mark_prices(
+        &self,
+        provider: &impl DexStateProvider<S>,
+        state: &mut impl StateAccessor,
+    ) -> Result<Vec<MarkPriceRefreshFailure>> {
+        let config = match self.mark_price_refresh_config.get(state)? {
+            Some(config) if !config.mpg_ids.is_empty() => config,
+            _ => return Ok(vec![]),
+        };
+        let num_mpgs = config.mpg_ids.len() as u32;
+
+        let current_slot = self.time_module.get_slot(state)?.slot;
+        let mut cursor = self.mark_price_refresh_cursor.get(state)?.unwrap_or_default();
+        let mut budget = config.max_products_per_slot;
+        let mut failures = vec![];
+
+        // visit each configured MPG at most once per call
+        for _ in 0..num_mpgs {
+            if budget == 0 {
+                break;
+            }
+
+            let mpg_id = config.mpg_ids[(cursor.mpg_position % num_mpgs) as usize];
+
+            // an MPG that cannot be refreshed, e.g. one without mark prices, is skipped rather
+            // than holding back the ones after it
+            let is_done = match self.refresh_mpg_mark_prices(
+                provider,
+                &mpg_id,
+                &mut cursor.product_index,
+                &mut budget,
+                current_slot,
+                &mut failures,
+                state,
+            ) {
+                Ok(is_done) => is_done,
+                Err(err) => {
+                    failures.push(MarkPriceRefreshFailure {
+                        mpg_id,
+                        product_id: None,
+                        reason: err.to_string(),
+                    });
+                    true
+                }
+            };
+
+            if !is_done {
+                break;
+            }
+
+            cursor = MarkPriceRefreshCursor {
+                mpg_position: (cursor.mpg_position + 1) % num_mpgs,
+                product_index: 0,
+            };
+        }
+
+        self.mark_price_refresh_cursor.set(&cursor, state)?;
+        self.mark_price_refresh_failures.set(&failures, state)?;
+
+        Ok(failures)
+    }
+
+    /// Refreshes the active outrights of `mpg_id` from `product_index` on while `budget` lasts.
+    /// Returns false, with `product_index` set to the next outright to refresh, if the budget ran
+    /// out before the end of the MPG. Products that cannot be refreshed are added to `failures`.
+    #[allow(clippy::too_many_arguments)]
+    fn refresh_mpg_mark_prices(
+        &self,
+        provider: &impl DexStateProvider<S>,
+        mpg_id: &MPGId,
+        product_index: &mut u32,
+        budget: &mut u32,
+        current_slot: Slot,
+        failures: &mut Vec<MarkPriceRefreshFailure>,
+        state: &mut impl StateAccessor,
+    ) -> Result<bool> {
+        let mpg = provider.market_product_group(mpg_id, state)?;
+
+        if mpg.is_mpg_killed {
+            return Ok(true);
+        }
+
+        let mut mark_prices = match self.mark_prices.get(&mpg.id, state)? {
+            Some(mark_prices) => mark_prices,
+            None => return Err(RiskError::MarkPricesNotInitialized.into()),
+        };
+
+        let alpha = self.get_risk_parameters(&mpg.id, state)?.mark_price_ema_alpha;
+        let mut is_done = true;
+
+        for (index, product) in mpg.get_active_products() {
+            if index < *product_index as usize {
+                continue;
+            }
+
+            // combos are valued from the mark prices of their legs
+            let outright = match product {
+                Product::Outright { outright_product } => outright_product,
+                Product::Combo { .. } => continue,
+            };
+
+            if *budget == 0 {
+                *product_index = index as u32;
+                is_done = false;
+                break;
+            }
+
+            match self.refresh_outright_mark_price(
+                &mut mark_prices,
+                outright,
+                current_slot,
+                alpha,
+                state,
+            ) {
+                Ok(true) => *budget -= 1,
+                Ok(false) => {}
+                // a product that cannot be refreshed, e.g. one without an index price, must not
+                // discard the prices refreshed before it
+                Err(err) => failures.push(MarkPriceRefreshFailure {
+                    mpg_id: mpg.id,
+                    product_id: Some(outright.product_id),
+                    reason: err.to_string(),
+                }),
+            }
+        }
+
+        self.mark_prices.set(&mpg.id, &mark_prices, state)?;
+
+        Ok(is_done)
+    }
+}
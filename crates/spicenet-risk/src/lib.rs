@@ -1,24 +1,32 @@
 use sov_modules_api::{
-    Context, Error, GenesisState, Module, ModuleId, ModuleInfo, Spec, StateMap,
-    TxState, DaSpec,
+    Context, Error, GenesisState, Module, ModuleId, ModuleInfo, Spec, StateAccessor, StateMap,
+    StateValue, TxState, DaSpec,
 };
 
 use lut::LookupTable;
+use spicenet_aaob::AAOB;
 use spicenet_shared::{MPGId, ProductId, TrgId};
 use spicenet_time::TimeModule;
 
 use crate::call::CallMessage;
 // use crate::event::Event;
-use crate::state::{CovarianceMatrix, MarkPricesArray, RiskProfile};
+use crate::state::{
+    CovarianceMatrix, MarkPriceRefreshConfig, MarkPriceRefreshCursor, MarkPriceRefreshFailure,
+    MarkPricesArray, RiskParameters, RiskProfile,
+};
 
-use spicenet_shared::risk::{RiskEngineOutput, SocialLossInfo, VarianceCache};
+use spicenet_shared::risk::{
+    DexStateProvider, RiskEngineOutput, RiskError, SocialLossInfo, VarianceCache,
+};
 
 pub mod bitpair;
 pub mod call;
 pub mod event;
 pub mod genesis;
 pub mod helpers;
+pub mod hooks;
 pub mod rpc;
+pub mod simulation;
 pub mod state;
 mod two_iterators;
 pub mod utils;
@@ -37,11 +45,32 @@ pub struct RiskModule<S: Spec> {
     #[state]
     variance_caches: StateMap<TrgId<S>, VarianceCache>,
 
+    /// Parameters of the MPGs that do not use the defaults
+    #[state]
+    risk_parameters: StateMap<MPGId, RiskParameters>,
+
+    /// Parameters of every MPG without its own entry in `risk_parameters`
+    #[state]
+    default_risk_parameters: StateValue<RiskParameters>,
+
+    #[state]
+    mark_price_refresh_config: StateValue<MarkPriceRefreshConfig>,
+
+    #[state]
+    mark_price_refresh_cursor: StateValue<MarkPriceRefreshCursor>,
+
+    /// Mark prices the refresh of the last slot could not update
+    #[state]
+    mark_price_refresh_failures: StateValue<Vec<MarkPriceRefreshFailure>>,
+
     #[module]
     time_module: TimeModule<S>,
 
     #[module]
     lut: LookupTable<S>,
+
+    #[module]
+    aaob: AAOB<S>,
 }
 
 impl<S: Spec> Module for RiskModule<S> {
@@ -61,22 +90,38 @@ impl<S: Spec> Module for RiskModule<S> {
     }
 
     fn call(
+        &self,
+        _msg: CallMessage<S>,
+        _context: &Context<Self::Spec>,
+        _state: &mut impl TxState<S>,
+    ) -> Result<(), Error> {
+        // the MPG and TRG state lives in the dex, which dispatches risk calls with itself as
+        // the state provider, see `RiskModule::call_with_provider`
+        Err(anyhow::Error::from(RiskError::CallNotRoutedThroughDex).into())
+    }
+}
+
+impl<S: Spec> RiskModule<S> {
+    /// Executes a risk call message, resolving the MPGs and TRGs it refers to through `provider`
+    pub fn call_with_provider(
         &self,
         msg: CallMessage<S>,
-        context: &Context<Self::Spec>,
+        provider: &impl DexStateProvider<S>,
+        context: &Context<S>,
         state: &mut impl TxState<S>,
-    ) -> Result<(), Error> {
-        let call_result = match msg {
-            CallMessage::InitializeCovarianceMatrix { mpg } => {
-                self.initialize_covariance_matrix(&mpg, context, state)
+    ) -> anyhow::Result<()> {
+        match msg {
+            CallMessage::InitializeCovarianceMatrix { mpg_id } => {
+                self.initialize_covariance_matrix(provider, mpg_id, context, state)
             }
 
             CallMessage::InitializeMarkPrices {
-                mpg,
+                mpg_id,
                 hardcoded_oracle_id,
                 is_hardcoded_oracle,
             } => self.initialize_mark_prices(
-                &mpg,
+                provider,
+                mpg_id,
                 is_hardcoded_oracle,
                 hardcoded_oracle_id,
                 context,
@@ -84,12 +129,13 @@ impl<S: Spec> Module for RiskModule<S> {
             ),
 
             CallMessage::UpdateCovarianceMatrix {
-                mpg,
+                mpg_id,
                 correlations,
                 product_keys,
                 standard_deviations,
             } => self.update_covariance_matrix(
-                &mpg,
+                provider,
+                mpg_id,
                 product_keys,
                 standard_deviations,
                 correlations,
@@ -98,30 +144,67 @@ impl<S: Spec> Module for RiskModule<S> {
             ),
 
             CallMessage::UpdateMarkPrices {
-                mpg,
-                products_to_update,
-            } => self.update_mark_prices(&mpg, products_to_update, context, state),
+                mpg_id,
+                product_ids,
+            } => self.update_mark_prices(provider, mpg_id, product_ids, context, state),
 
             CallMessage::CollectMarkPricesGarbage {
-                mpg,
+                mpg_id,
                 max_products_to_examine,
-            } => self.collect_mark_prices_garbage(&mpg, max_products_to_examine, context, state),
+            } => self.collect_mark_prices_garbage(
+                provider,
+                mpg_id,
+                max_products_to_examine,
+                context,
+                state,
+            ),
 
             CallMessage::RemoveMarketProductIndexFromVarianceCache {
-                trg,
-                mpg,
+                trg_id,
+                mpg_id,
                 market_product_index,
             } => self.remove_market_product_index_from_variance_cache(
-                &mpg,
-                &trg,
+                provider,
+                mpg_id,
+                trg_id,
                 market_product_index,
                 context,
                 state,
             ),
 
-            CallMessage::DeleteMarkPrices { mpg } => self.delete_mark_prices(&mpg, context, state),
-        };
+            CallMessage::DeleteMarkPrices { mpg_id } => {
+                self.delete_mark_prices(provider, mpg_id, context, state)
+            }
+
+            CallMessage::UpdateRiskParameters {
+                mpg_id,
+                risk_parameters,
+            } => self.update_risk_parameters(provider, mpg_id, risk_parameters, context, state),
+        }
+    }
 
-        Ok(call_result?)
+    /// Risk parameters of `mpg_id`, the defaults set at genesis if it has none of its own
+    pub fn get_risk_parameters(
+        &self,
+        mpg_id: &MPGId,
+        state: &mut impl StateAccessor,
+    ) -> anyhow::Result<RiskParameters> {
+        if let Some(risk_parameters) = self.risk_parameters.get(mpg_id, state)? {
+            return Ok(risk_parameters);
+        }
+
+        Ok(self.default_risk_parameters.get(state)?.unwrap_or_default())
+    }
+
+    /// Mark prices the refresh of the last slot could not update, see
+    /// [`RiskModule::refresh_mark_prices`]
+    pub fn mark_price_refresh_failures(
+        &self,
+        state: &mut impl StateAccessor,
+    ) -> anyhow::Result<Vec<MarkPriceRefreshFailure>> {
+        Ok(self
+            .mark_price_refresh_failures
+            .get(state)?
+            .unwrap_or_default())
     }
 }
@@ -0,0 +1,228 @@
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+use sov_modules_api::{Spec, StateAccessor};
+
+use spicenet_shared::dex::{MarketProductGroup, TraderRiskGroup};
+use spicenet_shared::risk::health_status::HealthStatus;
+use spicenet_shared::risk::{DexStateProvider, RiskError};
+use spicenet_shared::{
+    DexError, FastInt, Fractional, ProductId, Side, TrgId, ZERO_FAST_INT, ZERO_FRAC,
+};
+
+use crate::state::{RiskParameters, RiskProfile};
+use crate::RiskModule;
+
+/// Order assumed to rest on the book, it adds to the open order variance of the TRG
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HypotheticalOrder {
+    pub product_id: ProductId,
+    pub side: Side,
+    pub size: Fractional,
+}
+
+/// Trade assumed to be filled at `price`, fees are not accounted for
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HypotheticalFill {
+    pub product_id: ProductId,
+    pub side: Side,
+    pub price: Fractional,
+    pub size: Fractional,
+}
+
+/// Changes applied to a copy of a TRG before computing its risk profile, see
+/// [`RiskModule::simulate_risk_profile`]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "TrgId<S>: serde::Serialize + serde::de::DeserializeOwned")]
+pub struct RiskSimulation<S: Spec> {
+    pub trg_id: TrgId<S>,
+    #[serde(default)]
+    pub orders: Vec<HypotheticalOrder>,
+    #[serde(default)]
+    pub fills: Vec<HypotheticalFill>,
+    #[serde(default)]
+    pub deposit: Option<Fractional>,
+    #[serde(default)]
+    pub withdrawal: Option<Fractional>,
+}
+
+impl<S: Spec> RiskSimulation<S> {
+    /// A simulation of `trg_id` as it currently is
+    pub fn new(trg_id: TrgId<S>) -> Self {
+        Self {
+            trg_id,
+            orders: vec![],
+            fills: vec![],
+            deposit: None,
+            withdrawal: None,
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "native",
+    derive(serde::Serialize),
+    derive(serde::Deserialize),
+    derive(schemars::JsonSchema)
+)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct RiskProfileDetails {
+    pub risk_profile: RiskProfile,
+    pub liquidation_threshold: FastInt,
+    pub order_placement_threshold: FastInt,
+    /// `None` when the portfolio value is zero
+    pub risk_ratio: Option<FastInt>,
+    pub health_status: HealthStatus,
+}
+
+impl RiskProfileDetails {
+    pub fn new(risk_profile: RiskProfile, risk_parameters: &RiskParameters) -> Self {
+        Self {
+            liquidation_threshold: risk_profile.get_liquidation_threshold(risk_parameters),
+            order_placement_threshold: risk_profile
+                .get_order_placement_threshold(risk_parameters),
+            risk_ratio: if risk_profile.portfolio_value == ZERO_FAST_INT {
+                None
+            } else {
+                Some(risk_profile.get_risk_ratio(risk_parameters))
+            },
+            health_status: risk_profile.get_health_status(risk_parameters),
+            risk_profile,
+        }
+    }
+}
+
+impl<S: Spec> RiskModule<S> {
+    /// Computes the risk profile the TRG of `simulation` would have after the simulated orders,
+    /// fills, deposit and withdrawal.
+    ///
+    /// Works on copies of the TRG and of its variance cache, nothing is written to state.
+    pub fn simulate_risk_profile(
+        &self,
+        provider: &impl DexStateProvider<S>,
+        simulation: &RiskSimulation<S>,
+        state: &mut impl StateAccessor,
+    ) -> Result<RiskProfileDetails> {
+        let mut trg = provider.trader_risk_group(&simulation.trg_id, state)?;
+        let mpg = provider.market_product_group(&trg.market_product_group, state)?;
+
+        let mut covariance_matrix = self
+            .covariance_matrix
+            .get(&mpg.id, state)?
+            .ok_or::<Error>(RiskError::CovarianceMatrixNotInitialized.into())?;
+        let mark_prices = self
+            .mark_prices
+            .get(&mpg.id, state)?
+            .ok_or::<Error>(RiskError::MarkPricesNotInitialized.into())?;
+        let mut variance_cache = self
+            .variance_caches
+            .get(&trg.id, state)?
+            .ok_or::<Error>(RiskError::VarianceCacheNotInitialized.into())?;
+        let risk_parameters = self.get_risk_parameters(&mpg.id, state)?;
+
+        apply_simulation(&mpg, &mut trg, simulation)?;
+
+        for variance_cache_index in 0..variance_cache.product_indexes.len() {
+            variance_cache.product_indexes[variance_cache_index] = usize::MAX;
+            variance_cache.positions[variance_cache_index] = ZERO_FAST_INT;
+            variance_cache.sigma_position[variance_cache_index] = ZERO_FAST_INT;
+        }
+
+        covariance_matrix.mappings = Self::map_all_indexes(
+            &covariance_matrix.covariance_metadata,
+            &mpg,
+            &trg,
+            &variance_cache,
+        )?;
+
+        let risk_profile = Self::calculate_risk_profile_cached(
+            &mpg,
+            &mark_prices,
+            &trg,
+            &covariance_matrix,
+            &mut variance_cache,
+            &risk_parameters,
+            self.time_module.get_slot(state)?.slot,
+        )?;
+
+        Ok(RiskProfileDetails::new(risk_profile, &risk_parameters))
+    }
+}
+
+fn apply_simulation<S: Spec>(
+    mpg: &MarketProductGroup<S>,
+    trg: &mut TraderRiskGroup<S>,
+    simulation: &RiskSimulation<S>,
+) -> Result<()> {
+    for order in &simulation.orders {
+        if order.size <= ZERO_FRAC {
+            bail!(RiskError::InvalidRiskCheckParameters);
+        }
+
+        let (product_index, base_decimals) = activate_position(mpg, trg, &order.product_id)?;
+        trg.adjust_book_qty(
+            product_index,
+            order.size.round(base_decimals as u32)?,
+            order.side,
+            base_decimals,
+        )?;
+    }
+
+    for fill in &simulation.fills {
+        if fill.size <= ZERO_FRAC {
+            bail!(RiskError::InvalidRiskCheckParameters);
+        }
+
+        let (product_index, _) = activate_position(mpg, trg, &fill.product_id)?;
+        let delta = match fill.side {
+            Side::Bid => fill.size,
+            Side::Ask => -fill.size,
+        };
+
+        let position_index = trg.active_products[product_index] as usize;
+        let position = &mut trg.trader_positions[position_index];
+        position.position = position.position.checked_add(delta)?;
+        trg.cash_balance = trg
+            .cash_balance
+            .checked_sub(delta.checked_mul(fill.price)?)?;
+    }
+
+    if let Some(deposit) = simulation.deposit {
+        if deposit <= ZERO_FRAC {
+            bail!(RiskError::InvalidRiskCheckParameters);
+        }
+        trg.cash_balance = trg.cash_balance.checked_add(deposit)?;
+        trg.total_deposited = trg.total_deposited.checked_add(deposit)?;
+    }
+
+    if let Some(withdrawal) = simulation.withdrawal {
+        if withdrawal <= ZERO_FRAC {
+            bail!(RiskError::InvalidRiskCheckParameters);
+        }
+        trg.cash_balance = trg.cash_balance.checked_sub(withdrawal)?;
+        trg.total_withdrawn = trg.total_withdrawn.checked_add(withdrawal)?;
+    }
+
+    Ok(())
+}
+
+/// Opens a position of `trg` in the outright `product_id` if it has none, returning the index of
+/// the outright in the MPG and its base decimals
+fn activate_position<S: Spec>(
+    mpg: &MarketProductGroup<S>,
+    trg: &mut TraderRiskGroup<S>,
+    product_id: &ProductId,
+) -> Result<(usize, u64)> {
+    let (product_index, outright) = match mpg.find_product_index(product_id) {
+        Some((product_index, product)) => (product_index, product.try_to_outright()?),
+        None => bail!(DexError::MissingMarketProduct),
+    };
+
+    trg.activate_position(
+        product_index,
+        product_id,
+        outright.cumulative_funding_per_share,
+        outright.cumulative_social_loss_per_share,
+    )?;
+
+    Ok((product_index, outright.metadata.base_decimals))
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use spicenet_shared::{MPGId, ProductId};
+
+/// MPGs whose outright mark prices are refreshed at the beginning of every slot, see
+/// [`crate::RiskModule::refresh_mark_prices`]
+#[derive(
+    borsh::BorshDeserialize,
+    borsh::BorshSerialize,
+    Serialize,
+    Deserialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+)]
+pub struct MarkPriceRefreshConfig {
+    pub mpg_ids: Vec<MPGId>,
+    /// Maximum number of mark prices updated per slot across all of `mpg_ids`
+    pub max_products_per_slot: u32,
+}
+
+/// Where the next slot's refresh resumes, products are refreshed round robin so that a budget
+/// smaller than the number of products does not starve the last ones
+#[derive(
+    borsh::BorshDeserialize, borsh::BorshSerialize, Debug, Default, PartialEq, Eq, Clone, Copy,
+)]
+pub struct MarkPriceRefreshCursor {
+    /// Position in [`MarkPriceRefreshConfig::mpg_ids`]
+    pub mpg_position: u32,
+    /// Index in the MPG's `active_products`
+    pub product_index: u32,
+}
+
+/// A mark price the last refresh could not update, it keeps its previous value until a later
+/// refresh or an `UpdateMarkPrices` call succeeds
+#[derive(
+    borsh::BorshDeserialize,
+    borsh::BorshSerialize,
+    Serialize,
+    Deserialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+)]
+pub struct MarkPriceRefreshFailure {
+    pub mpg_id: MPGId,
+    /// `None` when none of the products of the MPG could be refreshed, e.g. if it has no mark
+    /// prices
+    pub product_id: Option<ProductId>,
+    pub reason: String,
+}
@@ -1,8 +1,7 @@
 use std::ops::{Deref, DerefMut};
 
-use sokoban::critbit::CritbitIterator;
 use sov_modules_api::{Address, Spec};
-use spicenet_aaob::{Order, Slab, MAX_SIZE, NUM_NODES};
+use spicenet_aaob::BookLevel;
 use spicenet_shared::dex::{ComboProduct, OutrightProduct};
 use spicenet_shared::risk::RiskError;
 use spicenet_shared::time::Slot;
@@ -50,18 +49,22 @@ fn lossy_div(a: Fractional, b: Fractional) -> Fractional {
 impl<S: Spec> MarkPricesArray<S> {
     pub const MAX_BOOK_SPREAD_FOR_VALID_MARK_PRICE: FastInt = FastInt { value: 30_000_i128 };
 
+    /// Levels of each side of the book read to find the qualifying bid and ask prices
+    pub const MAX_BOOK_LEVELS_FOR_MARK_PRICE: usize = 32;
+
     pub fn calculate_price(
         &self,
         mpg_min: &MarketProductGroup<S>,
         product: &Product,
         curr_time: u64,
+        max_staleness: Slot,
     ) -> Result<FastInt, RiskError> {
         match product {
             Product::Outright { outright_product } => {
-                self.get_outright_price(outright_product, curr_time)
+                self.get_outright_price(outright_product, curr_time, max_staleness)
             }
             Product::Combo { combo_product } => {
-                self.calculate_combo_price(mpg_min, combo_product, curr_time)
+                self.calculate_combo_price(mpg_min, combo_product, curr_time, max_staleness)
             }
         }
     }
@@ -71,6 +74,7 @@ impl<S: Spec> MarkPricesArray<S> {
         market_product_group: &MarketProductGroup<S>,
         combo: &ComboProduct,
         current_slot: Slot,
+        max_staleness: Slot,
     ) -> Result<FastInt, RiskError> {
         let mut price = ZERO_FAST_INT;
         for leg_index in 0..combo.num_legs {
@@ -85,7 +89,7 @@ impl<S: Spec> MarkPricesArray<S> {
                         // is the same product the leg thinks it is referring to
                         assert_eq!(leg.product_key, outright_product.product_id,);
 
-                        self.get_outright_price(&outright_product, current_slot)?
+                        self.get_outright_price(&outright_product, current_slot, max_staleness)?
                     }
                     _ => return Err(RiskError::UnexpectedProductType.into()),
                 };
@@ -98,10 +102,11 @@ impl<S: Spec> MarkPricesArray<S> {
         &self,
         outright: &OutrightProduct,
         current_slot: Slot,
+        max_staleness: Slot,
     ) -> Result<FastInt, RiskError> {
         let index = self.get_product_index(&outright.product_id)?;
         let update_slot = self.array[index].update_slot;
-        if update_slot + 15 < current_slot {
+        if update_slot + max_staleness < current_slot {
             // msg!(
             //     "error: mark prices out of date. (update_slot {} current_slot {})",
             //     update_slot,
@@ -148,20 +153,22 @@ impl<S: Spec> MarkPricesArray<S> {
         }
     }
 
+    /// size weighted price of the best `qualifying_cum_qty` of a side, `levels` are best first
     fn calculate_weighted_best_price(
         &self,
         outright: &OutrightProduct,
-        slab_iter: CritbitIterator<Order, NUM_NODES, MAX_SIZE>,
+        levels: &[BookLevel],
         qualifying_cum_qty: Fractional,
     ) -> Option<Fractional> {
-        //TODO(!aaob, @sahilpabale): cross check the logic to make sure it is compatible with our tree implementation
         let mut cum_weighted_price = ZERO_FRAC;
         let mut cum_qty = ZERO_FRAC;
 
-        for (_, node) in slab_iter {
-            let price = Fractional::new((node.price >> 32) as i64, 0) * outright.metadata.tick_size
+        for level in levels {
+            // book prices are fp32 tick counts
+            let price = Fractional::new((level.price >> 32) as i64, 0)
+                * outright.metadata.tick_size
                 - outright.metadata.price_offset;
-            let qty = Fractional::new(node.base_qty as i64, outright.base_decimals);
+            let qty = Fractional::new(level.base_qty as i64, outright.base_decimals);
 
             if cum_qty + qty > qualifying_cum_qty {
                 let remaining_qty = qualifying_cum_qty - cum_qty;
@@ -186,10 +193,9 @@ impl<S: Spec> MarkPricesArray<S> {
         outright: &OutrightProduct,
         index_price: Fractional,
         product_index: usize,
-        bids: Slab, // TODO(!aaob): type depends on aaob
-        asks: Slab, // TODO(!aaob): type depends on aaob
+        bids: &[BookLevel],
+        asks: &[BookLevel],
     ) -> Option<FastInt> {
-        //TODO(!aaob, @sahilpabale): cross check the logic to make sure it is compatible with our tree implementation
         if outright.mark_price_qualifying_cum_value == ZERO_FRAC {
             return None;
         }
@@ -197,9 +203,9 @@ impl<S: Spec> MarkPricesArray<S> {
         let qualifying_cum_qty = lossy_div(outright.mark_price_qualifying_cum_value, index_price);
 
         let qualifying_bid_price =
-            self.calculate_weighted_best_price(outright, bids.tree.into_iter(), qualifying_cum_qty);
+            self.calculate_weighted_best_price(outright, bids, qualifying_cum_qty);
         let qualifying_ask_price =
-            self.calculate_weighted_best_price(outright, asks.tree.into_iter(), qualifying_cum_qty);
+            self.calculate_weighted_best_price(outright, asks, qualifying_cum_qty);
 
         self.array[product_index].qualifying_bid_price = Some(qualifying_bid_price?);
         self.array[product_index].qualifying_ask_price = Some(qualifying_ask_price?);
@@ -269,7 +275,7 @@ impl<S: Spec> MarkPricesArray<S> {
         // get the next available index
         let product_index = self.array.len();
 
-        self.array[product_index] = MarkPrice {
+        self.array.push(MarkPrice {
             // product_key: outright.product_key,
             product_id: outright.product_id,
             mark_price: index_price,
@@ -278,7 +284,7 @@ impl<S: Spec> MarkPricesArray<S> {
             update_slot: 0,
             qualifying_bid_price: None,
             qualifying_ask_price: None,
-        };
+        });
         Ok(product_index)
     }
 
@@ -328,6 +334,7 @@ impl<S: Spec> MarkPricesArray<S> {
         index_price: FastInt,
         product_index: usize,
         current_slot: Slot,
+        alpha: FastInt,
     ) -> Result<FastInt, RiskError> {
         // EMA formula:
         // We want to come up with the statistic s_t associated to time t
@@ -337,9 +344,6 @@ impl<S: Spec> MarkPricesArray<S> {
         //     = s_[t-1] + alpha*(x_t - s_[t-1])
         //
         // where alpha is the "smoothing factor" and 0 <= alpha <= 1
-        const ALPHA: FastInt = FastInt {
-            value: 100_000_i128, // ALPHA = 0.1
-        };
         if self.array[product_index].update_slot < current_slot {
             // This if statement basically says: if we haven't yet updated *this slot*, then
             // store the current EMA value so repeated calls to update within one slot will
@@ -351,7 +355,7 @@ impl<S: Spec> MarkPricesArray<S> {
         };
         let prev_s_t = self.array[product_index].prev_oracle_minus_book_ewma;
         let s_t = match book_price {
-            Some(book_px) => prev_s_t + ALPHA.mul_zero_okay((index_price - book_px) - prev_s_t),
+            Some(book_px) => prev_s_t + alpha.mul_zero_okay((index_price - book_px) - prev_s_t),
             None => prev_s_t,
         };
         self.array[product_index].oracle_minus_book_ewma = s_t;
@@ -367,16 +371,18 @@ impl<S: Spec> MarkPricesArray<S> {
         Ok(self.array[product_index].mark_price)
     }
 
-    pub fn update_outright_price_with_slab(
+    /// Updates the mark price at `product_index` from the index price and the levels of the
+    /// outright's book, `bids` and `asks` are best first
+    pub fn update_outright_price(
         &mut self,
         outright: &OutrightProduct,
         index_price: FastInt,
         product_index: usize,
         current_slot: Slot,
-        bids: Slab, // TODO(!aaob): type depends on aaob
-        asks: Slab, // TODO(!aaob): type depends on aaob
+        bids: &[BookLevel],
+        asks: &[BookLevel],
+        alpha: FastInt,
     ) -> Result<FastInt, RiskError> {
-        //TODO(!aaob, @sahilpabale): cross check the logic to make sure it is compatible with our tree implementation
         let book_price = self.calculate_outright_book_price_with_slab(
             outright,
             index_price.to_frac().unwrap(),
@@ -391,7 +397,7 @@ impl<S: Spec> MarkPricesArray<S> {
             //     px
             // );
         }
-        self.update_mark_price(book_price, index_price, product_index, current_slot)
+        self.update_mark_price(book_price, index_price, product_index, current_slot, alpha)
     }
 }
 
@@ -1,13 +1,16 @@
 pub use {
     correlation_index_lookup_table::*, correlation_lookup_table::*, correlation_matrix::*,
-    covariance_matrix::*, mark_prices::*, risk_profile::*,
+    covariance_matrix::*, mark_price_refresh::*, mark_prices::*, risk_parameters::*,
+    risk_profile::*,
 };
 
 pub mod correlation_index_lookup_table;
 pub mod correlation_lookup_table;
 pub mod correlation_matrix;
 pub mod covariance_matrix;
+pub mod mark_price_refresh;
 pub mod mark_prices;
+pub mod risk_parameters;
 pub mod risk_profile;
 
 pub trait IsInitialized {
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+use spicenet_shared::time::Slot;
+use spicenet_shared::{FastInt, Fractional, RiskError, FAST_INT_CONVERSION, ZERO_FAST_INT};
+
+/// Tunable parameters of the risk checks of an MPG
+#[cfg_attr(
+    feature = "native",
+    derive(schemars::JsonSchema),
+    derive(sov_modules_api::macros::UniversalWallet)
+)]
+#[derive(
+    borsh::BorshDeserialize,
+    borsh::BorshSerialize,
+    Serialize,
+    Deserialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+)]
+pub struct RiskParameters {
+    /// Standard deviations of the portfolio value under which a TRG can be liquidated
+    pub liquidation_sds: FastInt,
+    /// Divides the open order standard deviation into the threshold under which a TRG cannot
+    /// place new orders
+    pub order_placement_sds: FastInt,
+    /// Maximum distance of a limit price from the mark price, as a proportion of the mark price.
+    /// Price bands are not enforced when unset
+    pub price_band_proportion: Option<Fractional>,
+    /// Smoothing factor of the oracle minus book price EMA of the mark prices, between 0 and 1
+    pub mark_price_ema_alpha: FastInt,
+    /// Slots after its last update in which a mark price can still be used
+    pub max_mark_price_staleness: Slot,
+}
+
+impl Default for RiskParameters {
+    fn default() -> Self {
+        Self {
+            liquidation_sds: FastInt {
+                value: 1_500_000_i128,
+            },
+            order_placement_sds: FastInt {
+                value: 3_000_000_i128,
+            },
+            price_band_proportion: Some(Fractional { m: 15, exp: 2 }),
+            mark_price_ema_alpha: FastInt {
+                value: 100_000_i128,
+            },
+            max_mark_price_staleness: 15,
+        }
+    }
+}
+
+impl RiskParameters {
+    /// Rejects non-positive multipliers, band widths outside of (0, 1) and alphas outside of (0, 1]
+    pub fn validate(&self) -> Result<(), RiskError> {
+        let one = FastInt {
+            value: FAST_INT_CONVERSION,
+        };
+
+        let is_band_valid = match self.price_band_proportion {
+            Some(band) => FastInt::from(band) > ZERO_FAST_INT && FastInt::from(band) < one,
+            None => true,
+        };
+
+        if self.liquidation_sds <= ZERO_FAST_INT
+            || self.order_placement_sds <= ZERO_FAST_INT
+            || !is_band_valid
+            || self.mark_price_ema_alpha <= ZERO_FAST_INT
+            || self.mark_price_ema_alpha > one
+        {
+            return Err(RiskError::InvalidRiskParameters);
+        }
+
+        Ok(())
+    }
+
+    /// The minimum positive mark price at which a trader can place an order with a _different_
+    /// price without violating price bands. If the mark price is positive and less than this
+    /// number, any other price is more than the band away from it, so price bands are ignored.
+    pub fn min_escapable_price(&self) -> FastInt {
+        match self.price_band_proportion {
+            Some(band) => FastInt {
+                value: FAST_INT_CONVERSION / FastInt::from(band).value,
+            },
+            None => ZERO_FAST_INT,
+        }
+    }
+}
@@ -5,18 +5,9 @@ use spicenet_shared::{FastInt, ZERO_FAST_INT};
 use spicenet_shared::dex::TraderRiskGroup;
 use spicenet_shared::risk::{HealthStatus, VarianceCache, MAX_TRADER_POSITIONS};
 
+use super::RiskParameters;
 use crate::utils::babylonian_sqrt;
 
-/// The minimum threshold required to place open orders
-pub const ORDER_PLACEMENT_SDS: FastInt = FastInt {
-    value: 3_000_000_i128,
-};
-
-/// The minimum threshold to be safe from liquidation
-pub const LIQUIDATION_SDS: FastInt = FastInt {
-    value: 1_500_000_i128,
-};
-
 /// The liquidation price is set to LIQUIDATION_PRICE_PROPORTION * portfolio_value
 pub const LIQUIDATION_PRICE_PROPORTION: FastInt = FastInt {
     value: 333_333_i128,
@@ -44,23 +35,23 @@ pub struct RiskProfile {
 
 impl RiskProfile {
     /// The threshold, which when broken, can cause the account to be deemed liquidatable
-    pub fn get_liquidation_threshold(&self) -> FastInt {
-        self.portfolio_std_dev * LIQUIDATION_SDS
+    pub fn get_liquidation_threshold(&self, risk_parameters: &RiskParameters) -> FastInt {
+        self.portfolio_std_dev * risk_parameters.liquidation_sds
     }
 
     /// Calculates liquidation threshold as a % of portfolio value. Higher the ratio, higher the risk.
-    pub fn get_risk_ratio(&self) -> FastInt {
-        self.get_liquidation_threshold() / self.portfolio_value
+    pub fn get_risk_ratio(&self, risk_parameters: &RiskParameters) -> FastInt {
+        self.get_liquidation_threshold(risk_parameters) / self.portfolio_value
     }
 
     /// The threshold until which open orders can be allowed
-    pub fn get_order_placement_threshold(&self) -> FastInt {
-        self.portfolio_open_order_std_dev / ORDER_PLACEMENT_SDS
+    pub fn get_order_placement_threshold(&self, risk_parameters: &RiskParameters) -> FastInt {
+        self.portfolio_open_order_std_dev / risk_parameters.order_placement_sds
     }
 
-    pub fn get_health_status(&self) -> HealthStatus {
-        let liquidation_value = self.get_liquidation_threshold();
-        let health_value = self.get_order_placement_threshold();
+    pub fn get_health_status(&self, risk_parameters: &RiskParameters) -> HealthStatus {
+        let liquidation_value = self.get_liquidation_threshold(risk_parameters);
+        let health_value = self.get_order_placement_threshold(risk_parameters);
 
         if self.portfolio_value < liquidation_value {
             HealthStatus::Liquidatable
@@ -1,8 +1,10 @@
 #![allow(dead_code)]
 use std::convert::Infallible;
+use std::marker::PhantomData;
 
 use spicenet_risk::{call::CallMessage, event::Event, RiskModule};
 
+use sov_bank::GAS_TOKEN_ID;
 use sov_modules_api::prelude::UnwrapInfallible;
 use sov_modules_api::test_utils::generate_address as gen_addr;
 use sov_modules_api::Address;
@@ -15,12 +17,29 @@ use sov_test_utils::runtime::{assert_tx_reverted_with_reason, TestRunner};
 use sov_test_utils::{
     generate_optimistic_runtime, AsUser, MockDaSpec, TestStorageSpec, TestUser, TransactionTestCase,
 };
+use spicenet_aaob::{AAOBConfig, AAOB};
+use spicenet_dex::{get_mpg_id, get_product_id, get_trg_id, Dex, DexConfig, OutrightProductParams};
 use spicenet_risk::genesis::RiskModuleConfig;
+use spicenet_risk::simulation::{HypotheticalFill, RiskSimulation};
+use spicenet_risk::state::{MarkPriceRefreshConfig, RiskParameters};
+use spicenet_shared::risk::health_status::HealthStatus;
+use spicenet_shared::{
+    FastInt, Fractional, MPGId, ProductId, Side, TraderRiskGroup, TrgId, ZERO_FAST_INT, ZERO_FRAC,
+};
 use spicenet_time::{TimeConfig, TimeModule};
 
 pub type S = sov_test_utils::TestSpec;
 pub type Storage = ProverStorage<TestStorageSpec>;
 
+type Runner = TestRunner<TestRuntime<S, MockDaSpec>, S>;
+
+const MPG_NAME: &str = "TEST-MPG";
+const PRODUCT_NAME: &str = "BTC-PERP";
+const DECIMALS: u64 = 2;
+const INDEX_PRICE: i64 = 100;
+/// Deposited by every trader, 1000.00 in MPG decimals
+const DEPOSIT: u64 = 100_000;
+
 fn generate_address() -> Address<S> {
     Address::new([0; 32])
 }
@@ -36,15 +55,32 @@ where
     return Address::<S>::from(*bytes);
 }
 
-generate_optimistic_runtime!(TestRuntime <= registry: RiskModule<S>, time: TimeModule<S>, lut: lut::LookupTable<S>);
+// the risk module only takes calls routed through the dex, which reads its MPGs and TRGs
+generate_optimistic_runtime!(
+    TestRuntime <=
+    dex: Dex<S>,
+    risk: RiskModule<S>,
+    aaob: AAOB<S>,
+    time: TimeModule<S>,
+    lut: lut::LookupTable<S>
+);
 
 pub struct TestRoles<S: Spec> {
+    /// Authority of the MPG, of the lookup table and of the time module
     pub admin: TestUser<S>,
     pub wallet1: TestUser<S>,
     pub wallet2: TestUser<S>,
 }
 
-fn setup() -> (TestRoles<S>, TestRunner<TestRuntime<S, MockDaSpec>, S>) {
+fn setup() -> (TestRoles<S>, Runner) {
+    setup_with(RiskModuleConfig {
+        mark_price_refresh: None,
+        default_risk_parameters: Default::default(),
+        risk_parameters: vec![],
+    })
+}
+
+fn setup_with(riskConfig: RiskModuleConfig) -> (TestRoles<S>, Runner) {
     let genesis_config =
         HighLevelOptimisticGenesisConfig::generate().add_accounts_with_default_balance(3);
 
@@ -54,7 +90,11 @@ fn setup() -> (TestRoles<S>, TestRunner<TestRuntime<S, MockDaSpec>, S>) {
 
     let wallet2 = genesis_config.additional_accounts[2].clone();
 
-    let riskConfig = RiskModuleConfig {};
+    let aaobConfig = AAOBConfig::<S> {
+        initial_markets: vec![],
+        admin: admin.address(),
+        _phantom: PhantomData,
+    };
 
     let timeConfig = TimeConfig::<S> {
         sequencer_authority: generate_address_from_bytes(admin.address().as_bytes()),
@@ -63,18 +103,34 @@ fn setup() -> (TestRoles<S>, TestRunner<TestRuntime<S, MockDaSpec>, S>) {
     let lutConfig = lut::LookupTableConfig {
         prices: [34],
         aggregate_conf_intervals: [12],
+        update_authority: generate_address_from_bytes(admin.address().as_bytes()),
     };
 
     let genesis_config = GenesisConfig::from_minimal_config(
         genesis_config.clone().into(),
+        DexConfig::<S> {
+            _phantom: PhantomData,
+        },
         riskConfig.clone(),
+        aaobConfig,
         timeConfig.clone(),
         lutConfig.clone(),
     );
 
-    let runner =
+    let mut runner =
         TestRunner::new_with_genesis(genesis_config.into_genesis_params(), TestRuntime::default());
 
+    // the index price is set before any mark price is initialized from it
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<lut::LookupTable<S>>(lut::CallMessage::MutateAll {
+            prices: [Fractional::new(INDEX_PRICE, 0)],
+            aggregate_conf_intervals: [0],
+        }),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+
     (
         TestRoles {
             admin,
@@ -85,6 +141,150 @@ fn setup() -> (TestRoles<S>, TestRunner<TestRuntime<S, MockDaSpec>, S>) {
     )
 }
 
+fn mpg_id() -> MPGId {
+    get_mpg_id::<S>(MPG_NAME)
+}
+
+fn product_id() -> ProductId {
+    get_product_id::<S>(&mpg_id(), PRODUCT_NAME)
+}
+
+fn trg_id(user: &TestUser<S>) -> TrgId<S> {
+    get_trg_id::<S>(&user.address(), &mpg_id())
+}
+
+fn cash(amount: u64) -> Fractional {
+    Fractional::new(amount as i64, DECIMALS)
+}
+
+fn risk(msg: CallMessage<S>) -> spicenet_dex::CallMessage<S> {
+    spicenet_dex::CallMessage::Risk { msg }
+}
+
+/// Sends `msg` to the dex as `user`, the transaction has to succeed
+fn execute(runner: &mut Runner, user: &TestUser<S>, msg: spicenet_dex::CallMessage<S>) {
+    runner.execute_transaction(TransactionTestCase {
+        input: user.create_plain_message::<Dex<S>>(msg),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+}
+
+/// Sends `msg` to the dex as `user`, the transaction has to revert
+fn execute_reverted(runner: &mut Runner, user: &TestUser<S>, msg: spicenet_dex::CallMessage<S>) {
+    runner.execute_transaction(TransactionTestCase {
+        input: user.create_plain_message::<Dex<S>>(msg),
+        assert: Box::new(move |result, _| {
+            assert!(!result.tx_receipt.is_successful());
+        }),
+    });
+}
+
+fn create_mpg_message(name: &str) -> spicenet_dex::CallMessage<S> {
+    spicenet_dex::CallMessage::CreateMarketProductGroup {
+        name: name.try_into().unwrap(),
+        decimals: DECIMALS,
+        collateral_token_id: GAS_TOKEN_ID,
+        ewma_windows: [0; 4],
+        max_maker_fee_bps: 10,
+        min_maker_fee_bps: 0,
+        max_taker_fee_bps: 20,
+        min_taker_fee_bps: 0,
+    }
+}
+
+/// Creates the MPG as `admin` with one product at the index price and a standard deviation of 10,
+/// and sets up its risk state. Mark prices follow the index price since no book qualifies.
+fn create_mpg(runner: &mut Runner, admin: &TestUser<S>) {
+    execute(runner, admin, create_mpg_message(MPG_NAME));
+    execute(
+        runner,
+        admin,
+        spicenet_dex::CallMessage::InitializeMarketProduct {
+            mpg_id: mpg_id(),
+            params: OutrightProductParams {
+                name: PRODUCT_NAME.try_into().unwrap(),
+                price_index: 0,
+                tick_size: Fractional::new(1, 0),
+                base_decimals: 0,
+                price_offset: ZERO_FRAC,
+                min_base_size: 1,
+                mark_price_qualifying_cum_value: ZERO_FRAC,
+                mark_price_max_qualifying_width: ZERO_FRAC,
+            },
+        },
+    );
+    execute(
+        runner,
+        admin,
+        risk(CallMessage::InitializeCovarianceMatrix { mpg_id: mpg_id() }),
+    );
+    execute(
+        runner,
+        admin,
+        risk(CallMessage::UpdateCovarianceMatrix {
+            mpg_id: mpg_id(),
+            product_keys: vec![product_id()],
+            standard_deviations: vec![FastInt::from(10)],
+            correlations: vec![vec![FastInt::from(1)]],
+        }),
+    );
+    execute(
+        runner,
+        admin,
+        risk(CallMessage::InitializeMarkPrices {
+            mpg_id: mpg_id(),
+            is_hardcoded_oracle: false,
+            hardcoded_oracle_id: None,
+        }),
+    );
+    execute(
+        runner,
+        admin,
+        risk(CallMessage::UpdateMarkPrices {
+            mpg_id: mpg_id(),
+            product_ids: vec![product_id()],
+        }),
+    );
+}
+
+/// Creates the TRG of `user` and deposits [`DEPOSIT`] into it
+fn open_trg(runner: &mut Runner, user: &TestUser<S>) {
+    execute(
+        runner,
+        user,
+        spicenet_dex::CallMessage::InitializeTrg { mpg_id: mpg_id() },
+    );
+    execute(
+        runner,
+        user,
+        spicenet_dex::CallMessage::DepositFunds {
+            trg_id: trg_id(user),
+            amount: DEPOSIT,
+        },
+    );
+}
+
+fn load_trg(runner: &mut Runner, user: &TestUser<S>) -> TraderRiskGroup<S> {
+    let trg_id = trg_id(user);
+    runner.query_state(|state| {
+        Dex::<S>::default()
+            .trader_risk_groups
+            .get(&trg_id, state)
+            .unwrap()
+            .unwrap()
+    })
+}
+
+/// Position of `trg` in the product, zero when it has none
+fn position(trg: &TraderRiskGroup<S>) -> Fractional {
+    match trg.find_position_index(&product_id()) {
+        Some(position_index) => trg.trader_positions[position_index].position,
+        None => ZERO_FRAC,
+    }
+}
+
 #[test]
 fn checkBankBalances() {
     let (
@@ -96,3 +296,301 @@ fn checkBankBalances() {
         mut runner,
     ) = setup();
 }
+
+#[test]
+fn risk_calls_check_the_authority_of_the_stored_mpg() {
+    let (TestRoles { admin, wallet1, .. }, mut runner) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &wallet1);
+
+    let update_covariance = || {
+        risk(CallMessage::UpdateCovarianceMatrix {
+            mpg_id: mpg_id(),
+            product_keys: vec![product_id()],
+            standard_deviations: vec![FastInt::from(1)],
+            correlations: vec![vec![FastInt::from(1)]],
+        })
+    };
+    execute_reverted(&mut runner, &wallet1, update_covariance());
+    execute_reverted(
+        &mut runner,
+        &wallet1,
+        risk(CallMessage::DeleteMarkPrices { mpg_id: mpg_id() }),
+    );
+
+    // an MPG created by wallet1 does not give it rights over the risk state of another one
+    execute(&mut runner, &wallet1, create_mpg_message("WALLET1-MPG"));
+    execute_reverted(&mut runner, &wallet1, update_covariance());
+
+    // MPGs the dex does not know are rejected
+    execute_reverted(
+        &mut runner,
+        &admin,
+        risk(CallMessage::InitializeCovarianceMatrix {
+            mpg_id: get_mpg_id::<S>("UNKNOWN-MPG"),
+        }),
+    );
+
+    // the risk module only takes calls routed through the dex
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<RiskModule<S>>(CallMessage::DeleteMarkPrices {
+            mpg_id: mpg_id(),
+        }),
+        assert: Box::new(move |result, _| {
+            assert!(!result.tx_receipt.is_successful());
+        }),
+    });
+
+    execute(&mut runner, &admin, update_covariance());
+}
+
+/// Runs `UpdateMarkPrices` for the product as `user`, `expected` are the product ids it updates
+fn update_mark_prices(runner: &mut Runner, user: &TestUser<S>, expected: Vec<ProductId>) {
+    runner.execute_transaction(TransactionTestCase {
+        input: user.create_plain_message::<Dex<S>>(risk(CallMessage::UpdateMarkPrices {
+            mpg_id: mpg_id(),
+            product_ids: vec![product_id()],
+        })),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result
+                .events
+                .contains(&TestRuntimeEvent::Risk(Event::MarkPricesUpdated {
+                    mpg_id: mpg_id(),
+                    product_ids: expected,
+                })));
+        }),
+    });
+}
+
+fn advance_slot(runner: &mut Runner, admin: &TestUser<S>) {
+    runner.execute_transaction(TransactionTestCase {
+        input: admin
+            .create_plain_message::<TimeModule<S>>(spicenet_time::CallMessage::UpdateSlot {}),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+        }),
+    });
+}
+
+#[test]
+fn mark_prices_are_updated_once_per_slot() {
+    let (TestRoles { admin, wallet1, .. }, mut runner) = setup();
+    create_mpg(&mut runner, &admin);
+
+    // the setup already updated the mark price in this slot, anyone can crank
+    update_mark_prices(&mut runner, &wallet1, vec![]);
+
+    advance_slot(&mut runner, &admin);
+    update_mark_prices(&mut runner, &wallet1, vec![product_id()]);
+    update_mark_prices(&mut runner, &wallet1, vec![]);
+
+    advance_slot(&mut runner, &admin);
+    update_mark_prices(&mut runner, &wallet1, vec![product_id()]);
+
+    // products outside of the MPG are rejected
+    execute_reverted(
+        &mut runner,
+        &wallet1,
+        risk(CallMessage::UpdateMarkPrices {
+            mpg_id: mpg_id(),
+            product_ids: vec![get_product_id::<S>(&mpg_id(), "ETH-PERP")],
+        }),
+    );
+}
+
+#[test]
+fn mark_prices_are_refreshed_at_the_beginning_of_each_slot() {
+    let (TestRoles { admin, wallet1, .. }, mut runner) = setup_with(RiskModuleConfig {
+        mark_price_refresh: Some(MarkPriceRefreshConfig {
+            mpg_ids: vec![mpg_id()],
+            max_products_per_slot: 4,
+        }),
+        default_risk_parameters: Default::default(),
+        risk_parameters: vec![],
+    });
+    create_mpg(&mut runner, &admin);
+
+    // the slot hook of the next block refreshed the product before the crank
+    advance_slot(&mut runner, &admin);
+    update_mark_prices(&mut runner, &wallet1, vec![]);
+
+    advance_slot(&mut runner, &admin);
+    update_mark_prices(&mut runner, &wallet1, vec![]);
+}
+
+#[test]
+fn mark_price_refresh_failures_are_recorded() {
+    let unknown_mpg_id = get_mpg_id::<S>("UNKNOWN-MPG");
+    let unpriced_mpg_id = get_mpg_id::<S>("UNPRICED-MPG");
+    let (TestRoles { admin, wallet1, .. }, mut runner) = setup_with(RiskModuleConfig {
+        mark_price_refresh: Some(MarkPriceRefreshConfig {
+            mpg_ids: vec![unknown_mpg_id, unpriced_mpg_id, mpg_id()],
+            max_products_per_slot: 4,
+        }),
+        default_risk_parameters: Default::default(),
+        risk_parameters: vec![],
+    });
+    create_mpg(&mut runner, &admin);
+    execute(&mut runner, &admin, create_mpg_message("UNPRICED-MPG"));
+
+    // the MPGs that cannot be refreshed do not hold back the one after them
+    advance_slot(&mut runner, &admin);
+    update_mark_prices(&mut runner, &wallet1, vec![]);
+
+    let failures = runner.query_state(|state| {
+        RiskModule::<S>::default()
+            .mark_price_refresh_failures(state)
+            .unwrap()
+    });
+    assert_eq!(
+        failures
+            .iter()
+            .map(|failure| (failure.mpg_id, failure.product_id))
+            .collect::<Vec<_>>(),
+        vec![(unknown_mpg_id, None), (unpriced_mpg_id, None)]
+    );
+}
+
+#[test]
+fn risk_profile_simulation() {
+    let (
+        TestRoles {
+            admin,
+            wallet1,
+            wallet2,
+        },
+        mut runner,
+    ) = setup();
+    create_mpg(&mut runner, &admin);
+    open_trg(&mut runner, &wallet1);
+
+    let simulate = |runner: &mut Runner, simulation: RiskSimulation<S>| {
+        runner.query_state(|state| {
+            Dex::<S>::default()
+                .risk_profile(&simulation, state)
+                .unwrap()
+        })
+    };
+
+    assert!(simulate(&mut runner, RiskSimulation::new(trg_id(&wallet2))).is_none());
+
+    let current = simulate(&mut runner, RiskSimulation::new(trg_id(&wallet1))).unwrap();
+    assert_eq!(
+        current.risk_profile.portfolio_value,
+        FastInt::from(cash(DEPOSIT))
+    );
+    assert_eq!(current.risk_profile.portfolio_std_dev, ZERO_FAST_INT);
+    assert_eq!(current.health_status, HealthStatus::Healthy);
+
+    // buying at the mark price keeps the value of the portfolio and adds its risk
+    let with_fill = simulate(
+        &mut runner,
+        RiskSimulation {
+            fills: vec![HypotheticalFill {
+                product_id: product_id(),
+                side: Side::Bid,
+                price: Fractional::new(INDEX_PRICE, 0),
+                size: Fractional::new(1, 0),
+            }],
+            ..RiskSimulation::new(trg_id(&wallet1))
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        with_fill.risk_profile.portfolio_value,
+        FastInt::from(cash(DEPOSIT))
+    );
+    assert!(with_fill.risk_profile.portfolio_std_dev > ZERO_FAST_INT);
+
+    let with_deposit = simulate(
+        &mut runner,
+        RiskSimulation {
+            deposit: Some(Fractional::new(500, 0)),
+            ..RiskSimulation::new(trg_id(&wallet1))
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        with_deposit.risk_profile.portfolio_value,
+        FastInt::from(cash(DEPOSIT + 50_000))
+    );
+
+    // nothing was written
+    let trg = load_trg(&mut runner, &wallet1);
+    assert_eq!(trg.cash_balance, cash(DEPOSIT));
+    assert_eq!(position(&trg), ZERO_FRAC);
+}
+
+#[test]
+fn update_risk_parameters() {
+    let (TestRoles { admin, wallet1, .. }, mut runner) = setup();
+    create_mpg(&mut runner, &admin);
+
+    let risk_parameters = RiskParameters {
+        liquidation_sds: FastInt::from(2),
+        order_placement_sds: FastInt::from(4),
+        price_band_proportion: Some(Fractional::new(1, 1)),
+        mark_price_ema_alpha: FastInt::from(1),
+        max_mark_price_staleness: 30,
+    };
+    let update = |risk_parameters: RiskParameters| {
+        risk(CallMessage::UpdateRiskParameters {
+            mpg_id: mpg_id(),
+            risk_parameters,
+        })
+    };
+
+    execute_reverted(&mut runner, &wallet1, update(risk_parameters));
+    for invalid in [
+        RiskParameters {
+            liquidation_sds: ZERO_FAST_INT,
+            ..risk_parameters
+        },
+        RiskParameters {
+            order_placement_sds: FastInt::from(-1),
+            ..risk_parameters
+        },
+        RiskParameters {
+            price_band_proportion: Some(Fractional::new(1, 0)),
+            ..risk_parameters
+        },
+        RiskParameters {
+            mark_price_ema_alpha: FastInt::from(2),
+            ..risk_parameters
+        },
+    ] {
+        execute_reverted(&mut runner, &admin, update(invalid));
+    }
+    assert_eq!(
+        runner.query_state(|state| {
+            RiskModule::<S>::default()
+                .get_risk_parameters(&mpg_id(), state)
+                .unwrap()
+        }),
+        RiskParameters::default()
+    );
+
+    let expected = risk_parameters;
+    runner.execute_transaction(TransactionTestCase {
+        input: admin.create_plain_message::<Dex<S>>(update(risk_parameters)),
+        assert: Box::new(move |result, _| {
+            assert!(result.tx_receipt.is_successful());
+            assert!(result.events.contains(&TestRuntimeEvent::Risk(
+                Event::RiskParametersUpdated {
+                    mpg_id: mpg_id(),
+                    risk_parameters: expected,
+                }
+            )));
+        }),
+    });
+
+    assert_eq!(
+        runner.query_state(|state| {
+            RiskModule::<S>::default()
+                .get_risk_parameters(&mpg_id(), state)
+                .unwrap()
+        }),
+        risk_parameters
+    );
+}
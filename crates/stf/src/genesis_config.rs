@@ -15,6 +15,7 @@ use capsule::CapsuleConfig;
 use spicenet_aaob::AAOBConfig;
 use spicenet_risk::genesis::RiskModuleConfig;
 use lut::LookupTableConfig;
+use spicenet_dex::DexConfig;
 
 use super::GenesisConfig;
 use crate::Runtime;
@@ -46,6 +47,8 @@ pub struct GenesisPaths {
     pub risk_genesis_path: PathBuf,
     /// Lookup Table genesis path.
     pub lut_genesis_path: PathBuf,
+    /// Dex genesis path.
+    pub dex_genesis_path: PathBuf,
 }
 
 impl GenesisPaths {
@@ -68,6 +71,7 @@ impl GenesisPaths {
             aaob_genesis_path: dir.as_ref().join("aaob.json"),
             risk_genesis_path: dir.as_ref().join("risk.json"),
             lut_genesis_path: dir.as_ref().join("lut.json"),
+            dex_genesis_path: dir.as_ref().join("dex.json"),
         }
     }
 }
@@ -108,6 +112,8 @@ pub fn create_genesis_config<S: Spec>(
 
     let lut_config: LookupTableConfig<S> = read_genesis_json(&genesis_paths.lut_genesis_path)?;
 
+    let dex_config: DexConfig<S> = read_genesis_json(&genesis_paths.dex_genesis_path)?;
+
     Ok(GenesisConfig::new(
         accounts_config,
         nonces_config,
@@ -123,6 +129,7 @@ pub fn create_genesis_config<S: Spec>(
         aaob_config,
         risk_config,
         lut_config,
+        dex_config,
     ))
 }
 
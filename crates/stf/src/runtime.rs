@@ -81,6 +81,8 @@ pub struct Runtime<S: Spec> {
     pub risk: spicenet_risk::RiskModule<S>,
     /// The lookup module
     pub lut: lut::LookupTable<S>,
+    /// The dex module, its slot hook refreshes mark prices after the time module set the slot time
+    pub dex: spicenet_dex::Dex<S>,
 }
 
 impl<S: Spec> sov_modules_stf_blueprint::Runtime<S> for Runtime<S>